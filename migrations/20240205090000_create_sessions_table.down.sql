@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS refresh_token_hash TEXT;
+DROP TABLE sessions;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS sessions (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  last_used_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  device_id TEXT NOT NULL,
+  refresh_token_hash TEXT,
+  user_agent TEXT,
+  ip TEXT,
+  user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  UNIQUE (user_id, device_id)
+);
+
+ALTER TABLE users DROP COLUMN IF EXISTS refresh_token_hash;
@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS failed_logins (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  email TEXT NOT NULL,
+  reason TEXT NOT NULL,
+  ip TEXT,
+  user_agent TEXT,
+  user_id INT REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX failed_logins_email_idx ON failed_logins (email);
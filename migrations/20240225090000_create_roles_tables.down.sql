@@ -0,0 +1,10 @@
+-- Add down migration script here
+ALTER TABLE courses DROP COLUMN is_public;
+
+DROP TABLE user_roles;
+
+DROP TABLE role_permissions;
+
+DROP TABLE permissions;
+
+DROP TABLE roles;
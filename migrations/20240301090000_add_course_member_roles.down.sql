@@ -0,0 +1,10 @@
+-- Add down migration script here
+ALTER TABLE course_user DROP CONSTRAINT course_user_course_id_user_id_key;
+
+ALTER TABLE course_user ADD COLUMN owned BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE course_user SET owned = TRUE WHERE role = 'owner';
+
+ALTER TABLE course_user DROP COLUMN created_at;
+
+ALTER TABLE course_user DROP COLUMN role;
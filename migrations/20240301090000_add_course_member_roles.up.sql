@@ -0,0 +1,15 @@
+-- Add up migration script here
+ALTER TABLE course_user
+  ADD COLUMN role TEXT NOT NULL DEFAULT 'student'
+  CHECK (role IN ('owner', 'editor', 'student'));
+
+ALTER TABLE course_user ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+UPDATE course_user SET role = 'owner' WHERE owned;
+
+ALTER TABLE course_user DROP COLUMN owned;
+
+DELETE FROM course_user a USING course_user b
+WHERE a.id > b.id AND a.course_id = b.course_id AND a.user_id = b.user_id;
+
+ALTER TABLE course_user ADD CONSTRAINT course_user_course_id_user_id_key UNIQUE (course_id, user_id);
@@ -0,0 +1,13 @@
+-- Add down migration script here
+ALTER TABLE course_user DROP COLUMN invite_id;
+
+ALTER TABLE courses ADD COLUMN invite_link TEXT;
+
+UPDATE courses c SET invite_link = (
+  SELECT token FROM course_invites i
+  WHERE i.course_id = c.id AND i.revoked_at IS NULL
+  ORDER BY i.created_at DESC
+  LIMIT 1
+);
+
+DROP TABLE course_invites;
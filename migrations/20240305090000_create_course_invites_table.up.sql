@@ -0,0 +1,22 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS course_invites (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  name TEXT,
+  token TEXT NOT NULL UNIQUE,
+  expires_at TIMESTAMP,
+  max_uses INT,
+  uses INT NOT NULL DEFAULT 0,
+  revoked_at TIMESTAMP,
+  created_by INT REFERENCES users(id) ON DELETE SET NULL,
+  course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE
+);
+
+CREATE INDEX course_invites_course_id_idx ON course_invites (course_id);
+
+INSERT INTO course_invites (token, course_id)
+SELECT invite_link, id FROM courses WHERE invite_link IS NOT NULL;
+
+ALTER TABLE courses DROP COLUMN invite_link;
+
+ALTER TABLE course_user ADD COLUMN invite_id INT REFERENCES course_invites(id) ON DELETE SET NULL;
@@ -0,0 +1,14 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS courses_public_created_at_idx;
+
+DROP INDEX IF EXISTS courses_tags_idx;
+
+DROP INDEX IF EXISTS courses_search_idx;
+
+ALTER TABLE courses
+  DROP COLUMN search,
+  DROP COLUMN tags,
+  DROP COLUMN level,
+  DROP COLUMN description;
+
+DROP TYPE IF EXISTS course_level;
@@ -0,0 +1,26 @@
+-- Add up migration script here
+DO $$
+BEGIN
+  IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'course_level') THEN
+    CREATE TYPE course_level AS ENUM (
+       'beginner',
+       'intermediate',
+       'advanced'
+    );
+  END IF;
+END $$;
+
+ALTER TABLE courses
+  ADD COLUMN description TEXT NOT NULL DEFAULT '',
+  ADD COLUMN level course_level,
+  ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}',
+  ADD COLUMN search tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', title), 'A') ||
+    setweight(to_tsvector('simple', description), 'B')
+  ) STORED;
+
+CREATE INDEX courses_search_idx ON courses USING GIN (search);
+
+CREATE INDEX courses_tags_idx ON courses USING GIN (tags);
+
+CREATE INDEX courses_public_created_at_idx ON courses (created_at DESC, id DESC) WHERE is_public;
@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE lessons DROP CONSTRAINT lessons_course_id_position_key;
+
+ALTER TABLE lessons
+  DROP COLUMN module_id,
+  DROP COLUMN position;
+
+DROP TABLE course_modules;
@@ -0,0 +1,27 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS course_modules (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  title TEXT NOT NULL,
+  position INT NOT NULL,
+  course_id INT NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+  UNIQUE (course_id, position) DEFERRABLE INITIALLY DEFERRED
+);
+
+ALTER TABLE lessons
+  ADD COLUMN position INT NOT NULL DEFAULT 0,
+  ADD COLUMN module_id INT REFERENCES course_modules(id) ON DELETE SET NULL;
+
+-- keep current order of lessons, it was order of creation
+UPDATE lessons l SET position = o.position
+FROM (
+  SELECT id, ROW_NUMBER() OVER (PARTITION BY course_id ORDER BY created_at, id) - 1 AS position
+  FROM lessons
+) o
+WHERE l.id = o.id;
+
+-- positions are swapped by reorder inside one transaction
+ALTER TABLE lessons
+  ADD CONSTRAINT lessons_course_id_position_key
+  UNIQUE (course_id, position) DEFERRABLE INITIALLY DEFERRED;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS lesson_progress (
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  completed_at TIMESTAMP,
+  time_spent_secs INT NOT NULL DEFAULT 0,
+  last_position INT NOT NULL DEFAULT 0,
+  user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  lesson_id INT NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+  PRIMARY KEY (user_id, lesson_id)
+);
+
+CREATE INDEX lesson_progress_lesson_id_idx ON lesson_progress (lesson_id);
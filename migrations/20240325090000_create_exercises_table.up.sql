@@ -0,0 +1,25 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS exercises (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  position INT NOT NULL DEFAULT 0,
+  prompt TEXT NOT NULL,
+  -- type of exercise and right answers, see `ExerciseContent`
+  content JSONB NOT NULL,
+  lesson_id INT NOT NULL REFERENCES lessons(id) ON DELETE CASCADE
+);
+
+CREATE INDEX exercises_lesson_id_idx ON exercises (lesson_id, position);
+
+CREATE TABLE IF NOT EXISTS exercise_attempts (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  answer JSONB NOT NULL,
+  score DOUBLE PRECISION NOT NULL,
+  correct BOOLEAN NOT NULL,
+  exercise_id INT NOT NULL REFERENCES exercises(id) ON DELETE CASCADE,
+  user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX exercise_attempts_user_id_idx ON exercise_attempts (user_id, exercise_id);
@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE cards DROP COLUMN lesson_word_id;
+
+DROP TABLE lesson_word_groups;
+
+DROP TABLE lesson_words;
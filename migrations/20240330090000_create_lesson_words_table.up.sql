@@ -0,0 +1,29 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS lesson_words (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  position INT NOT NULL DEFAULT 0,
+  word TEXT NOT NULL,
+  translation TEXT NOT NULL,
+  example TEXT,
+  lesson_id INT NOT NULL REFERENCES lessons(id) ON DELETE CASCADE
+);
+
+CREATE INDEX lesson_words_lesson_id_idx ON lesson_words (lesson_id, position);
+
+-- group of cards created by user from vocabulary of lesson
+CREATE TABLE IF NOT EXISTS lesson_word_groups (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  lesson_id INT NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+  user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  group_id INT NOT NULL REFERENCES card_group(id) ON DELETE CASCADE,
+  UNIQUE (lesson_id, user_id)
+);
+
+-- card copied from word of lesson, it is updated and deleted together with word
+ALTER TABLE cards
+  ADD COLUMN lesson_word_id INT REFERENCES lesson_words(id) ON DELETE CASCADE;
+
+CREATE INDEX cards_lesson_word_id_idx ON cards (lesson_word_id);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE lessons DROP COLUMN published_revision_id;
+
+DROP TABLE lesson_revisions;
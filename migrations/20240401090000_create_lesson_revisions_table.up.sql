@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS lesson_revisions (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  content TEXT NOT NULL,
+  author_id INT REFERENCES users(id) ON DELETE SET NULL,
+  lesson_id INT NOT NULL REFERENCES lessons(id) ON DELETE CASCADE
+);
+
+CREATE INDEX lesson_revisions_lesson_id_idx ON lesson_revisions (lesson_id, id);
+
+-- text of published revision is written into `content_path`,
+-- newer revisions are drafts visible only for authors of course
+ALTER TABLE lessons
+  ADD COLUMN published_revision_id INT REFERENCES lesson_revisions(id) ON DELETE SET NULL;
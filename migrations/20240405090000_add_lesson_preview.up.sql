@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- preview lessons are open for everyone, other lessons only for members of course
+ALTER TABLE lessons ADD COLUMN preview BOOLEAN NOT NULL DEFAULT FALSE;
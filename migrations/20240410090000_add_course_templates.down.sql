@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE courses DROP COLUMN is_template;
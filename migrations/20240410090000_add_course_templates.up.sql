@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- template courses can be cloned by everyone who can see them
+ALTER TABLE courses ADD COLUMN is_template BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX courses_is_template_idx ON courses (id) WHERE is_template;
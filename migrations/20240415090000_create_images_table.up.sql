@@ -0,0 +1,15 @@
+-- Add up migration script here
+-- uploaded image, variants of image are saved near file with the same name
+CREATE TABLE IF NOT EXISTS images (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  filename TEXT NOT NULL UNIQUE,
+  original_name TEXT,
+  content_type TEXT NOT NULL,
+  size BIGINT NOT NULL,
+  width INT,
+  height INT,
+  owner_id INT REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX images_owner_id_idx ON images (owner_id);
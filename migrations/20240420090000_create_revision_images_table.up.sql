@@ -0,0 +1,16 @@
+-- Add up migration script here
+-- images used by text of lesson revisions, storage gc keeps them while revision exists
+CREATE TABLE IF NOT EXISTS revision_images (
+  revision_id INT NOT NULL REFERENCES lesson_revisions(id) ON DELETE CASCADE,
+  filename TEXT NOT NULL,
+  PRIMARY KEY (revision_id, filename)
+);
+
+CREATE INDEX revision_images_filename_idx ON revision_images (filename);
+
+-- references of existing revisions, names of variants are saved as name of original
+INSERT INTO revision_images (revision_id, filename)
+SELECT DISTINCT r.id, regexp_replace(rtrim(m[1], '.'), '(_small|_medium|_large)?(\.webp)?$', '')
+FROM lesson_revisions r,
+  regexp_matches(r.content, '(?:image:|audio:|/api/image/)([A-Za-z0-9_.-]+)', 'g') AS m
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,113 @@
+use actix_web::{get, put, web, HttpResponse, Responder};
+use validator::Validate;
+
+use crate::{
+    extractors::permission::{perms, Authorized},
+    models::{common::ErrorResponse, role::UpdateRolesData},
+    services::{
+        role::{find_user_access, set_user_roles},
+        user::find_user_by_id,
+    },
+    AppState,
+};
+
+pub fn admin_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .service(get_user_roles)
+            .service(update_user_roles),
+    );
+}
+
+/// Get roles and permissions of user
+///
+/// Path:
+/// **/api/admin/users/*{id}*/roles**
+#[get("/users/{id}/roles")]
+async fn get_user_roles(
+    _: Authorized<perms::ManageRoles>,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_user_roles";
+
+    let user_id = path.into_inner();
+
+    log::info!("{}: attempting to get roles of user: {}", op, user_id);
+
+    if let Err(err) = find_user_by_id(user_id, &app_data.pool).await {
+        log::error!("{}: user: {} not found, error: {}", op, user_id, err);
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "user not found".to_string(),
+        });
+    }
+
+    match find_user_access(user_id, &app_data.pool).await {
+        Ok(access) => HttpResponse::Ok().json(access),
+        Err(err) => {
+            log::error!("{}: can not get roles of user, error: {}", op, err);
+
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Replace roles of user
+///
+/// New roles are applied to user tokens on next refresh
+///
+/// Path:
+/// **/api/admin/users/*{id}*/roles**
+#[put("/users/{id}/roles")]
+async fn update_user_roles(
+    creds: Authorized<perms::ManageRoles>,
+    path: web::Path<i32>,
+    data: web::Json<UpdateRolesData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "update_user_roles";
+
+    let user_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to set roles: {:?} of user: {} by admin: {}",
+        op,
+        data.roles,
+        user_id,
+        creds.uid
+    );
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "user must have at least one role".to_string(),
+        });
+    }
+
+    if let Err(err) = find_user_by_id(user_id, &app_data.pool).await {
+        log::error!("{}: user: {} not found, error: {}", op, user_id, err);
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "user not found".to_string(),
+        });
+    }
+
+    if let Err(err) = set_user_roles(user_id, &data.roles, &app_data.pool).await {
+        log::error!("{}: can not set roles of user, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    log::info!("{}: roles of user: {} are updated", op, user_id);
+
+    match find_user_access(user_id, &app_data.pool).await {
+        Ok(access) => HttpResponse::Ok().json(access),
+        Err(err) => {
+            log::error!("{}: can not get roles of user, error: {}", op, err);
+
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
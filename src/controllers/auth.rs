@@ -1,32 +1,73 @@
-use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
-use crypto::{digest::Digest, sha2::Sha256};
+use std::{env, error::Error};
+
+use actix_web::{
+    delete, get, middleware::from_fn, post, web, HttpRequest, HttpResponse, Responder,
+};
 use jwt_simple::prelude::Duration;
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
     extractors::jwt_cred::{get_token_from_req, AuthError, JwtCred},
+    middlewares::rate_limit::{ip_rate_limit, too_many_requests},
     models::{
-        auth::{SignInData, SignUpData, Tokens},
+        audit::CreateFailedLogin,
+        auth::{
+            ChangePasswordData, DeleteAccountData, SignInData, SignUpData, Tokens, VerifyEmailData,
+        },
         common::ErrorResponse,
+        role::UserAccess,
+        session::{CreateSession, SessionOut},
         user::CreateUser,
     },
-    services::user::{create_user, find_user_by_email, find_user_by_id},
-    utils::jwt::scopes,
+    services::{
+        audit::record_failed_login,
+        role::find_user_access,
+        session::{
+            delete_user_session, delete_user_sessions, find_session_by_id, find_user_sessions,
+            rotate_session_token, update_session_token, upsert_session,
+        },
+        user::{
+            create_email_verification, create_user, delete_user, find_user_by_email,
+            find_user_by_id, update_password, verify_email,
+        },
+    },
+    utils::{
+        denylist::{deny_sessions, deny_token},
+        jwt::{scopes, JwtUtil, ACCESS_DURATION_MIN, REFRESH_DURATION_DAY},
+        mailer::{send_mail, Mail},
+        rate_limit::{account_lockout, client_ip, register_failure, reset_failures},
+    },
     AppState,
 };
 
+use super::{
+    oauth::oauth_config,
+    two_factor::{two_factor_challenge, two_factor_config},
+};
+
 pub fn auth_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/auth")
+            .wrap(from_fn(ip_rate_limit))
             .service(signup)
             .service(signin)
             .service(logout)
-            .service(refresh_token),
+            .service(refresh_token)
+            .service(verify)
+            .service(resend_verification)
+            .service(get_sessions)
+            .service(revoke_session)
+            .service(revoke_all_sessions)
+            .service(change_password)
+            .service(delete_account)
+            .configure(oauth_config)
+            .configure(two_factor_config),
     );
 }
 
-const ACCESS_DURATION_MIN: u64 = 20;
-const REFRESH_DURATION_DAY: u64 = 14;
+/// Header with id of client device, new device id is generated if header is missing
+const DEVICE_ID_HEADER: &str = "X-Device-Id";
 
 /// Sign up request
 ///
@@ -35,12 +76,14 @@ const REFRESH_DURATION_DAY: u64 = 14;
 /// Path:
 /// **/api/auth/signup**
 #[post("/signup")]
-pub async fn signup(data: web::Json<SignUpData>, app_data: web::Data<AppState>) -> impl Responder {
+pub async fn signup(
+    req: HttpRequest,
+    data: web::Json<SignUpData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
     let op = "signup";
     log::info!("{}: attempting to sign up user", op);
 
-    let jwt_controller = &app_data.jwt;
-
     if data.validate().is_err() {
         log::error!("{}: data is not validated", op);
 
@@ -57,7 +100,6 @@ pub async fn signup(data: web::Json<SignUpData>, app_data: web::Data<AppState>)
         email: data.email.clone(),
         username: data.username.clone(),
         password_hash: hashed_password,
-        refresh_token_hash: None,
     };
 
     let new_id = match create_user(&user, &app_data.pool).await {
@@ -75,56 +117,55 @@ pub async fn signup(data: web::Json<SignUpData>, app_data: web::Data<AppState>)
         new_id
     );
 
-    let access = jwt_controller
-        .encode_token(
-            JwtCred {
-                uid: new_id,
-                email: data.email.clone(),
-                scope: scopes::ACCESS.to_string(),
-            },
-            Duration::from_mins(ACCESS_DURATION_MIN),
-        )
-        .unwrap();
-
-    let refresh = jwt_controller
-        .encode_token(
-            JwtCred {
-                uid: new_id,
-                email: data.email.clone(),
-                scope: scopes::REFRESH.to_string(),
-            },
-            Duration::from_days(REFRESH_DURATION_DAY),
-        )
-        .unwrap();
+    send_verification(new_id, &data.email, &app_data).await;
 
-    log::info!("{}: tokens was successfuly generated", op);
+    let tokens = match start_session(&req, new_id, &data.email, &app_data).await {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            log::error!("{}: can not start session, error: {}", op, err);
 
-    let user = find_user_by_id(new_id, &app_data.pool).await.unwrap();
-    // NOTE: mb it need to wrap in match
-    user.update_refresh_token(Some(&refresh), &app_data.pool)
-        .await
-        .expect("failed update user refresh hash");
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
 
     log::info!(
-        "{}: refresh token was successfuly update and tokens are secccessfully sended",
+        "{}: session was successfuly started and tokens are secccessfully sended",
         op
     );
 
-    HttpResponse::Created().json(Tokens { access, refresh })
+    HttpResponse::Created().json(tokens)
 }
 
 /// Sign in request
 ///
-/// Login user in system return pair of JWT
+/// Login user in system return pair of JWT,
+/// if user enabled 2FA challenge for **/api/auth/2fa/verify** is returned instead
 ///
 /// Path:
 /// **/api/auth/signin**
 #[post("/signin")]
-pub async fn signin(data: web::Json<SignInData>, app_data: web::Data<AppState>) -> impl Responder {
+pub async fn signin(
+    req: HttpRequest,
+    data: web::Json<SignInData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
     let op = "sigin";
     log::info!("{}: attempting to login user", op);
 
-    let jwt = &app_data.jwt;
+    let locked = {
+        let mut redis_conn = app_data.redis.lock().unwrap();
+        account_lockout(&mut redis_conn, &data.email)
+    };
+
+    match locked {
+        Ok(Some(retry_after)) => {
+            log::warn!("{}: account: {} is locked", op, data.email);
+
+            return too_many_requests(retry_after);
+        }
+        Ok(None) => {}
+        Err(err) => log::error!("{}: can not check account lockout, error: {}", op, err),
+    }
 
     let user = find_user_by_email(&data.email, &app_data.pool).await;
 
@@ -138,6 +179,12 @@ pub async fn signin(data: web::Json<SignInData>, app_data: web::Data<AppState>)
                 err
             );
 
+            if let Some(retry_after) =
+                register_failed_signin(&req, &data.email, None, "user not found", &app_data).await
+            {
+                return too_many_requests(retry_after);
+            }
+
             return HttpResponse::NotFound().json(ErrorResponse {
                 message: String::from("user not found"),
             });
@@ -155,49 +202,65 @@ pub async fn signin(data: web::Json<SignInData>, app_data: web::Data<AppState>)
     if !valid {
         log::error!("{}: user enter invalid password", op);
 
+        if let Some(retry_after) = register_failed_signin(
+            &req,
+            &data.email,
+            Some(user.id),
+            "invalid password",
+            &app_data,
+        )
+        .await
+        {
+            return too_many_requests(retry_after);
+        }
+
         return HttpResponse::Forbidden().json(ErrorResponse {
             message: String::from("invalid password"),
         });
     }
 
-    let access = jwt
-        .encode_token(
-            JwtCred {
-                uid: user.id,
-                email: user.email.clone(),
-                scope: scopes::ACCESS.to_string(),
-            },
-            Duration::from_mins(ACCESS_DURATION_MIN),
-        )
-        .unwrap();
-    let refresh = jwt
-        .encode_token(
-            JwtCred {
-                uid: user.id,
-                email: user.email.clone(),
-                scope: scopes::REFRESH.to_string(),
-            },
-            Duration::from_days(REFRESH_DURATION_DAY),
-        )
-        .unwrap();
+    {
+        let mut redis_conn = app_data.redis.lock().unwrap();
 
-    log::info!("{}: tokens was successfuly generated", op);
+        if let Err(err) = reset_failures(&mut redis_conn, &data.email) {
+            log::error!("{}: can not reset failed attempts, error: {}", op, err);
+        }
+    }
 
-    user.update_refresh_token(Some(&refresh), &app_data.pool)
-        .await
-        .expect("failed update user refresh hash");
+    match two_factor_challenge(user.id, &user.email, &app_data).await {
+        Ok(Some(challenge)) => {
+            log::info!("{}: user: {} has to pass 2FA", op, user.id);
+
+            return HttpResponse::Accepted().json(challenge);
+        }
+        Ok(None) => {}
+        Err(err) => {
+            log::error!("{}: can not check 2FA, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    let tokens = match start_session(&req, user.id, &user.email, &app_data).await {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            log::error!("{}: can not start session, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
 
     log::info!(
-        "{}: refresh token was successfuly update and tokens are secccessfully sended",
+        "{}: session was successfuly started and tokens are secccessfully sended",
         op
     );
 
-    HttpResponse::Ok().json(Tokens { access, refresh })
+    HttpResponse::Ok().json(tokens)
 }
 
 /// Logout request
 ///
-/// Clear refresh token from database
+/// Revoke current session of user and its access tokens, other sessions stay active
 ///
 /// Path:
 /// **/api/auth/logout**
@@ -206,27 +269,45 @@ pub async fn logout(creds: JwtCred, app_data: web::Data<AppState>) -> impl Respo
     let op = "logout";
     log::info!("{}: attempting to logout user", op);
 
-    let user = match find_user_by_id(creds.uid, &app_data.pool).await {
-        Ok(user) => user,
-        Err(err) => {
+    let session_id = match creds.sid {
+        Some(sid) => sid,
+        None => {
+            log::error!("{}: token of user: {} has no session", op, creds.uid);
+
+            return HttpResponse::Unauthorized();
+        }
+    };
+
+    match delete_user_session(creds.uid, session_id, &app_data.pool).await {
+        Ok(true) => {}
+        Ok(false) => {
             log::error!(
-                "{}: cannot found the user by id: {}, error: {}",
+                "{}: session: {} of user: {} was not found",
                 op,
-                creds.uid,
-                err
+                session_id,
+                creds.uid
             );
 
             return HttpResponse::NotFound();
         }
-    };
+        Err(err) => {
+            log::error!("{}: can not delete session, error: {}", op, err);
+
+            return HttpResponse::InternalServerError();
+        }
+    }
 
-    log::info!("{}: user was successfuly founded, user: {:?}", op, user);
+    {
+        let mut redis_conn = app_data.redis.lock().unwrap();
 
-    user.update_refresh_token(None, &app_data.pool)
-        .await
-        .unwrap();
+        if let Err(err) = deny_token(&mut redis_conn, &creds) {
+            log::error!("{}: can not deny access token, error: {}", op, err);
+        }
+    }
 
-    log::info!("{}: user refresh token was successfuly updated to None", op);
+    deny_revoked_sessions(&app_data, &[session_id]);
+
+    log::info!("{}: session: {} was successfuly revoked", op, session_id);
 
     HttpResponse::Ok()
 }
@@ -235,6 +316,8 @@ pub async fn logout(creds: JwtCred, app_data: web::Data<AppState>) -> impl Respo
 ///
 /// Get refresh token in header and generate new pairs of JWT
 ///
+/// If already used refresh token is sent, the session is revoked
+///
 /// Path:
 /// **/api/auth/refresh**
 #[post("/refresh")]
@@ -244,7 +327,7 @@ pub async fn refresh_token(req: HttpRequest, app_data: web::Data<AppState>) -> i
 
     let jwt = &app_data.jwt;
 
-    let token = get_token_from_req(req);
+    let token = get_token_from_req(req.clone());
 
     let claims: JwtCred = match token {
         Ok(ref token) => match jwt.get_claims(&token.as_str(), scopes::REFRESH) {
@@ -285,13 +368,227 @@ pub async fn refresh_token(req: HttpRequest, app_data: web::Data<AppState>) -> i
         claims
     );
 
-    let user = match find_user_by_id(claims.uid, &app_data.pool).await {
+    let session = match claims.sid {
+        Some(sid) => find_session_by_id(sid, &app_data.pool).await.ok(),
+        None => None,
+    };
+
+    let session = match session {
+        Some(session) if session.user_id == claims.uid => session,
+        _ => {
+            log::error!(
+                "{}: session: {:?} of user: {} is not exist",
+                op,
+                claims.sid,
+                claims.uid
+            );
+
+            return HttpResponse::Unauthorized().json(ErrorResponse {
+                message: String::from("user not unauthorized"),
+            });
+        }
+    };
+
+    log::info!(
+        "{}: session founded in database, session: {}",
+        op,
+        session.id
+    );
+
+    // roles are loaded again, so changes of roles are applied on refresh
+    let access = match find_user_access(claims.uid, &app_data.pool).await {
+        Ok(access) => access,
+        Err(err) => {
+            log::error!("{}: can not get roles of user, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let tokens = match generate_tokens(jwt, claims.uid, &claims.email, session.id, access) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            log::error!("{}: can not generate tokens, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    log::info!("{}: tokens was successfuly generated", op);
+
+    match rotate_session_token(session.id, &token.unwrap(), &tokens.refresh, &app_data.pool).await {
+        Ok(true) => {}
+        Ok(false) => {
+            // token has valid signature but was already rotated, so it was stolen or replayed
+            log::warn!(
+                "{}: refresh token reuse detected, revoke session: {} of user: {}",
+                op,
+                session.id,
+                session.user_id
+            );
+
+            if let Err(err) = delete_user_session(session.user_id, session.id, &app_data.pool).await
+            {
+                log::error!("{}: can not revoke session, error: {}", op, err);
+            }
+
+            deny_revoked_sessions(&app_data, &[session.id]);
+
+            return HttpResponse::Unauthorized().json(ErrorResponse {
+                message: String::from("token are invalid"),
+            });
+        }
+        Err(err) => {
+            log::error!("{}: can not update session token, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    log::info!(
+        "{}: refresh token was successfuly update and tokens are secccessfully sended",
+        op
+    );
+
+    HttpResponse::Ok().json(tokens)
+}
+
+/// Get all sessions of user request
+///
+/// Path:
+/// **/api/auth/sessions**
+#[get("/sessions")]
+pub async fn get_sessions(creds: JwtCred, app_data: web::Data<AppState>) -> impl Responder {
+    let op = "get_sessions";
+    log::info!("{}: attempting to get sessions of user: {}", op, creds.uid);
+
+    match find_user_sessions(creds.uid, &app_data.pool).await {
+        Ok(sessions) => {
+            let sessions: Vec<SessionOut> = sessions
+                .into_iter()
+                .map(|session| SessionOut::from_session(session, creds.sid))
+                .collect();
+
+            log::info!(
+                "{}: sessions are successfuly returned, count: {}",
+                op,
+                sessions.len()
+            );
+
+            HttpResponse::Ok().json(sessions)
+        }
+        Err(err) => {
+            log::error!("{}: can not get sessions, error: {}", op, err);
+
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Revoke session of user by id request
+///
+/// Path:
+/// **/api/auth/sessions/*{id}***
+#[delete("/sessions/{id}")]
+pub async fn revoke_session(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "revoke_session";
+
+    let session_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to revoke session: {} of user: {}",
+        op,
+        session_id,
+        creds.uid
+    );
+
+    match delete_user_session(creds.uid, session_id, &app_data.pool).await {
+        Ok(true) => {
+            deny_revoked_sessions(&app_data, &[session_id]);
+
+            log::info!("{}: session: {} was successfuly revoked", op, session_id);
+
+            HttpResponse::Ok().finish()
+        }
+        Ok(false) => {
+            log::warn!("{}: session: {} was not found", op, session_id);
+
+            HttpResponse::NotFound().finish()
+        }
+        Err(err) => {
+            log::error!("{}: can not revoke session, error: {}", op, err);
+
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Revoke all sessions of user request
+///
+/// Path:
+/// **/api/auth/sessions**
+#[delete("/sessions")]
+pub async fn revoke_all_sessions(creds: JwtCred, app_data: web::Data<AppState>) -> impl Responder {
+    let op = "revoke_all_sessions";
+    log::info!(
+        "{}: attempting to revoke all sessions of user: {}",
+        op,
+        creds.uid
+    );
+
+    match delete_user_sessions(creds.uid, &app_data.pool).await {
+        Ok(session_ids) => deny_revoked_sessions(&app_data, &session_ids),
+        Err(err) => {
+            log::error!("{}: can not revoke sessions, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    log::info!("{}: sessions of user: {} are revoked", op, creds.uid);
+
+    HttpResponse::Ok().finish()
+}
+
+/// Change password request
+///
+/// Revoke all sessions of user and start new session for current device
+///
+/// Path:
+/// **/api/auth/password**
+#[post("/password")]
+pub async fn change_password(
+    req: HttpRequest,
+    creds: JwtCred,
+    data: web::Json<ChangePasswordData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "change_password";
+    log::info!(
+        "{}: attempting to change password of user: {}",
+        op,
+        creds.uid
+    );
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("invalid data"),
+        });
+    }
+
+    let user = match find_user_by_id(creds.uid, &app_data.pool).await {
         Ok(user) => user,
         Err(err) => {
             log::error!(
-                "{}: cannot find user by id: {}, error: {}",
+                "{}: cannot found the user by id: {}, error: {}",
                 op,
-                claims.uid,
+                creds.uid,
                 err
             );
 
@@ -301,63 +598,356 @@ pub async fn refresh_token(req: HttpRequest, app_data: web::Data<AppState>) -> i
         }
     };
 
-    log::info!("{}: user founded in database, user: {:?}", op, user);
+    if !bcrypt::verify(data.old_password.clone(), &user.password_hash).unwrap_or(false) {
+        log::error!("{}: user enter invalid password", op);
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: String::from("invalid password"),
+        });
+    }
+
+    let hashed_password = bcrypt::hash(data.new_password.clone(), bcrypt::DEFAULT_COST).unwrap();
+
+    if let Err(err) = update_password(user.id, &hashed_password, &app_data.pool).await {
+        log::error!("{}: can not update password, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    match delete_user_sessions(user.id, &app_data.pool).await {
+        Ok(session_ids) => deny_revoked_sessions(&app_data, &session_ids),
+        Err(err) => log::error!("{}: can not revoke sessions, error: {}", op, err),
+    }
+
+    log::info!("{}: password of user: {} was changed", op, user.id);
+
+    match start_session(&req, user.id, &user.email, &app_data).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(err) => {
+            log::error!("{}: can not start session, error: {}", op, err);
+
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Delete account request
+///
+/// Delete user with all data and revoke all tokens
+///
+/// Path:
+/// **/api/auth/account**
+#[delete("/account")]
+pub async fn delete_account(
+    creds: JwtCred,
+    data: web::Json<DeleteAccountData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "delete_account";
+    log::info!(
+        "{}: attempting to delete account of user: {}",
+        op,
+        creds.uid
+    );
+
+    let user = match find_user_by_id(creds.uid, &app_data.pool).await {
+        Ok(user) => user,
+        Err(err) => {
+            log::error!(
+                "{}: cannot found the user by id: {}, error: {}",
+                op,
+                creds.uid,
+                err
+            );
+
+            return HttpResponse::NotFound().finish();
+        }
+    };
+
+    if !bcrypt::verify(data.password.clone(), &user.password_hash).unwrap_or(false) {
+        log::error!("{}: user enter invalid password", op);
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: String::from("invalid password"),
+        });
+    }
+
+    match delete_user_sessions(user.id, &app_data.pool).await {
+        Ok(session_ids) => deny_revoked_sessions(&app_data, &session_ids),
+        Err(err) => {
+            log::error!("{}: can not revoke sessions, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    if let Err(err) = delete_user(user.id, &app_data.pool).await {
+        log::error!("{}: can not delete user: {}, error: {}", op, user.id, err);
+
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    log::info!("{}: user: {} was successfuly deleted", op, user.id);
+
+    HttpResponse::Ok().finish()
+}
+
+/// Save audit record of failed sign in and count it for account lockout
+///
+/// Return lockout duration if account was locked by this attempt
+pub(crate) async fn register_failed_signin(
+    req: &HttpRequest,
+    email: &str,
+    user_id: Option<i32>,
+    reason: &str,
+    app_data: &AppState,
+) -> Option<u64> {
+    let op = "register_failed_signin";
+
+    let attempt = CreateFailedLogin {
+        email: email.to_string(),
+        reason: reason.to_string(),
+        ip: client_ip(req, &app_data.rate_limit),
+        user_agent: req
+            .headers()
+            .get(actix_web::http::header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string()),
+        user_id,
+    };
+
+    if let Err(err) = record_failed_login(&attempt, &app_data.pool).await {
+        log::error!("{}: can not save failed attempt, error: {}", op, err);
+    }
+
+    let mut redis_conn = app_data.redis.lock().unwrap();
+
+    match register_failure(&mut redis_conn, &app_data.rate_limit, email) {
+        Ok(Some(duration)) => {
+            log::warn!("{}: account: {} locked for {} secs", op, email, duration);
+
+            Some(duration)
+        }
+        Ok(None) => None,
+        Err(err) => {
+            log::error!("{}: can not count failed attempt, error: {}", op, err);
+
+            None
+        }
+    }
+}
+
+/// Put revoked sessions into denylist, so their access tokens stop working immediately
+pub(crate) fn deny_revoked_sessions(app_data: &AppState, session_ids: &[i32]) {
+    let mut redis_conn = app_data.redis.lock().unwrap();
 
-    if user.refresh_token_hash.is_none() {
-        log::error!("{}: user are logouted, user: {:?}", op, user);
+    if let Err(err) = deny_sessions(&mut redis_conn, session_ids) {
+        log::error!(
+            "deny_revoked_sessions: can not deny sessions: {:?}, error: {}",
+            session_ids,
+            err
+        );
+    }
+}
+
+/// Generate pair of JWT for session of user, access token carries roles and permissions
+fn generate_tokens(
+    jwt: &JwtUtil,
+    user_id: i32,
+    email: &str,
+    session_id: i32,
+    user_access: UserAccess,
+) -> Result<Tokens, jwt_simple::Error> {
+    let access = jwt.encode_token(
+        JwtCred {
+            uid: user_id,
+            email: email.to_string(),
+            scope: scopes::ACCESS.to_string(),
+            sid: Some(session_id),
+            roles: user_access.roles,
+            perms: user_access.permissions,
+            ..Default::default()
+        },
+        Duration::from_mins(ACCESS_DURATION_MIN),
+    )?;
+
+    let refresh = jwt.encode_token(
+        JwtCred {
+            uid: user_id,
+            email: email.to_string(),
+            scope: scopes::REFRESH.to_string(),
+            sid: Some(session_id),
+            ..Default::default()
+        },
+        Duration::from_days(REFRESH_DURATION_DAY),
+    )?;
 
-        return HttpResponse::Unauthorized().json(ErrorResponse {
-            message: String::from("user not unauthorized"),
+    Ok(Tokens { access, refresh })
+}
+
+/// Create session for device from request and return pair of JWT for it
+pub(crate) async fn start_session(
+    req: &HttpRequest,
+    user_id: i32,
+    email: &str,
+    app_data: &AppState,
+) -> Result<Tokens, Box<dyn Error>> {
+    let headers = req.headers();
+
+    let device_id = headers
+        .get(DEVICE_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let user_agent = headers
+        .get(actix_web::http::header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+
+    let ip = client_ip(req, &app_data.rate_limit);
+
+    let session_id = upsert_session(
+        &CreateSession {
+            device_id,
+            user_agent,
+            ip,
+            user_id,
+        },
+        &app_data.pool,
+    )
+    .await?;
+
+    let access = find_user_access(user_id, &app_data.pool).await?;
+
+    let tokens = generate_tokens(&app_data.jwt, user_id, email, session_id, access)?;
+
+    update_session_token(session_id, &tokens.refresh, &app_data.pool).await?;
+
+    Ok(tokens)
+}
+
+/// Verify email request
+///
+/// Get token from verification email and mark email of user as verified
+///
+/// Path:
+/// **/api/auth/verify**
+#[post("/verify")]
+pub async fn verify(
+    data: web::Json<VerifyEmailData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "verify";
+    log::info!("{}: attempting to verify email", op);
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("invalid data"),
         });
     }
 
-    let mut hasher = Sha256::new();
-    hasher.input_str(token.unwrap().as_str());
-    let hashed_refresh = hasher.result_str();
+    match verify_email(&data.token, &app_data.pool).await {
+        Ok(user_id) => {
+            log::info!(
+                "{}: email of user: {} was successfuly verified",
+                op,
+                user_id
+            );
+
+            HttpResponse::Ok().finish()
+        }
+        Err(err) => {
+            log::error!("{}: can not verify email, error: {}", op, err);
 
-    log::info!("{}: token from header are hashed", op);
+            HttpResponse::BadRequest().json(ErrorResponse {
+                message: String::from("invalid or expired token"),
+            })
+        }
+    }
+}
 
-    if user.refresh_token_hash.clone().unwrap().as_str() != hashed_refresh.as_str() {
-        log::error!("{}: tokens in database and in header are not equal", op);
+/// Resend verification email request
+///
+/// Send new verification token to email of user
+///
+/// Path:
+/// **/api/auth/verify/resend**
+#[post("/verify/resend")]
+pub async fn resend_verification(creds: JwtCred, app_data: web::Data<AppState>) -> impl Responder {
+    let op = "resend_verification";
+    log::info!("{}: attempting to resend verification email", op);
 
-        return HttpResponse::Unauthorized().json(ErrorResponse {
-            message: String::from("token are invalid"),
+    let user = match find_user_by_id(creds.uid, &app_data.pool).await {
+        Ok(user) => user,
+        Err(err) => {
+            log::error!(
+                "{}: cannot found the user by id: {}, error: {}",
+                op,
+                creds.uid,
+                err
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: String::from("user not found"),
+            });
+        }
+    };
+
+    if user.email_verified {
+        log::warn!("{}: email of user: {} already verified", op, user.id);
+
+        return HttpResponse::Conflict().json(ErrorResponse {
+            message: String::from("email already verified"),
         });
     }
 
-    log::info!("{}: tokens are equals", op);
+    send_verification(user.id, &user.email, &app_data).await;
 
-    let access = jwt
-        .encode_token(
-            JwtCred {
-                uid: user.id,
-                email: user.email.clone(),
-                scope: scopes::ACCESS.to_string(),
-            },
-            Duration::from_mins(ACCESS_DURATION_MIN),
-        )
-        .unwrap();
-    let refresh = jwt
-        .encode_token(
-            JwtCred {
-                uid: user.id,
-                email: user.email.clone(),
-                scope: scopes::REFRESH.to_string(),
-            },
-            Duration::from_days(REFRESH_DURATION_DAY),
-        )
-        .unwrap();
+    HttpResponse::Ok().finish()
+}
 
-    log::info!("{}: tokens was successfuly generated", op);
+/// Create verification token and send it to user email
+///
+/// Errors are only logged, user can request email again
+pub(crate) async fn send_verification(user_id: i32, email: &str, app_data: &AppState) {
+    let op = "send_verification";
 
-    user.update_refresh_token(Some(&refresh), &app_data.pool)
-        .await
-        .unwrap();
+    let token = match create_email_verification(user_id, &app_data.pool).await {
+        Ok(token) => token,
+        Err(err) => {
+            log::error!(
+                "{}: can not create verification token for user: {}, error: {}",
+                op,
+                user_id,
+                err
+            );
 
-    log::info!(
-        "{}: refresh token was successfuly update and tokens are secccessfully sended",
-        op
-    );
+            return;
+        }
+    };
+
+    let mut body = format!("Your ReadCraft verification token: {}", token);
 
-    HttpResponse::Ok().json(Tokens { access, refresh })
+    if let Ok(url) = env::var("EMAIL_VERIFY_URL") {
+        body = format!("{}\n\nOr follow the link: {}?token={}", body, url, token);
+    }
+
+    let mail = Mail {
+        to: email.to_string(),
+        subject: String::from("Confirm your email"),
+        body,
+    };
+
+    match send_mail(&app_data.mailer, mail).await {
+        Ok(_) => log::info!("{}: verification email sent to user: {}", op, user_id),
+        Err(err) => log::error!(
+            "{}: can not send verification email to user: {}, error: {}",
+            op,
+            user_id,
+            err
+        ),
+    }
 }
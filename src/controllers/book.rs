@@ -1,13 +1,15 @@
+use std::{error::Error, io::Cursor};
+
 use actix_multipart::Multipart;
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use actix_web::{
+    delete, get,
+    http::header::{CacheControl, CacheDirective},
+    post, put, web, HttpRequest, HttpResponse, Responder,
+};
 use epub::doc::EpubDoc;
 use futures_util::{StreamExt, TryStreamExt};
 use serde::Deserialize;
 use sqlx::Postgres;
-use tokio::{
-    fs::File,
-    io::{AsyncReadExt, AsyncWriteExt},
-};
 use uuid::Uuid;
 use validator::Validate;
 
@@ -17,9 +19,16 @@ use crate::{
         book::{Book, CreateBook, UpdateBook},
         common::ErrorResponse,
     },
-    services::book::{
-        all_user_book, create_book_db, delete_book_db, find_book_by_id, get_book_chunk,
-        set_book_chunk, update_book_db, user_is_onwer_book,
+    services::{
+        book::{
+            all_user_book, create_book_db, delete_book_db, find_book_by_id, get_book_chunk,
+            set_book_chunk, update_book_db, user_is_onwer_book,
+        },
+        storage::book_file_is_shared,
+    },
+    utils::{
+        download::{attachment, stream_blob},
+        storage::BlobKind,
     },
     AppState,
 };
@@ -168,18 +177,17 @@ async fn delete_book(
         user_id
     );
 
-    if let Err(err) = check_available(book_id, user_id, &app_data.pool, op).await {
-        match err {
-            AvailableError::NotFound => {
-                return HttpResponse::NotFound().json(ErrorResponse {
-                    message: "book is not exist".to_string(),
-                });
-            }
-            AvailableError::Forbidden => {
-                return HttpResponse::Forbidden().json(ErrorResponse {
-                    message: "user is not owned of book".to_string(),
-                });
-            }
+    let book = match check_available(book_id, user_id, &app_data.pool, op).await {
+        Ok(book) => book,
+        Err(AvailableError::NotFound) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "book is not exist".to_string(),
+            });
+        }
+        Err(AvailableError::Forbidden) => {
+            return HttpResponse::Forbidden().json(ErrorResponse {
+                message: "user is not owned of book".to_string(),
+            });
         }
     };
 
@@ -194,6 +202,26 @@ async fn delete_book(
         return HttpResponse::InternalServerError().finish();
     }
 
+    // the same file can be attached to several books, unused file is removed by gc
+    match book_file_is_shared(&book.filename, book_id, &app_data.pool).await {
+        Ok(false) => {
+            if let Err(err) = app_data
+                .storage
+                .delete(BlobKind::Book, &book.filename)
+                .await
+            {
+                log::warn!(
+                    "{}: cannot delete file: {} of book, error: {}",
+                    op,
+                    book.filename,
+                    err
+                );
+            }
+        }
+        Ok(true) => {}
+        Err(err) => log::error!("{}: cannot check usage of book file, error: {}", op, err),
+    }
+
     log::info!("{}: book are successfuly deleted", op);
 
     HttpResponse::Ok().finish()
@@ -299,10 +327,14 @@ async fn get_chunk(
         }
     };
 
-    // FIXME: directory must be a const!
-    let filepath = format!("./uploads/books/{}", &book.filename);
+    let mut book_text = match read_epub(&book.filename, &app_data).await {
+        Ok(doc) => doc,
+        Err(err) => {
+            log::error!("{}: can not open the book: {}, error: {}", op, book_id, err);
 
-    let mut book_text = EpubDoc::new(&filepath).unwrap();
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
 
     book_text.set_current_page(chunk as usize);
     let content = book_text.get_current_str().unwrap().0;
@@ -356,10 +388,14 @@ async fn change_page(
         },
     };
 
-    // FIXME: directory must be a const!
-    let filepath = format!("./uploads/books/{}", &book.filename);
+    let num_pages = match read_epub(&book.filename, &app_data).await {
+        Ok(doc) => doc.get_num_pages() as i32,
+        Err(err) => {
+            log::error!("{}: can not open the book: {}, error: {}", op, book_id, err);
 
-    let num_pages = EpubDoc::new(&filepath).unwrap().get_num_pages() as i32;
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
 
     if options.page < 0 || options.page >= num_pages {
         let new_progress = 100;
@@ -427,12 +463,13 @@ async fn change_page(
     return HttpResponse::Ok().finish();
 }
 
-/// Download the file by path in directory uploads/books/{filename}
+/// Download the EPUB file of book, supports `Range` and `If-None-Match`
 ///
 /// Path:
 /// GET: **/api/book/download/{book_id}**
 #[get("/download/{book_id}")]
 async fn download_book(
+    req: HttpRequest,
     creds: JwtCred,
     info: web::Path<i32>,
     app_data: web::Data<AppState>,
@@ -464,18 +501,10 @@ async fn download_book(
         },
     };
 
-    let filepath = format!("uploads/books/{}", book.filename);
-
-    // Открываем файл
-    let mut file = match File::open(&filepath).await {
-        Ok(file) => file,
+    let meta = match app_data.storage.stat(BlobKind::Book, &book.filename).await {
+        Ok(meta) => meta,
         Err(err) => {
-            log::error!(
-                "{}: file by path: {} not found, error: {}",
-                op,
-                filepath,
-                err
-            );
+            log::error!("{}: file: {} not found, error: {}", op, book.filename, err);
 
             return HttpResponse::NotFound().json(ErrorResponse {
                 message: "file not found".to_string(),
@@ -483,23 +512,22 @@ async fn download_book(
         }
     };
 
-    // Читаем содержимое файла в буфер
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).await.unwrap();
-
-    // Устанавливаем заголовок Content-Disposition
-    let content_disposition = format!(
-        "attachment; filename=\"{}\"",
-        format!("{}.epub", book.title)
-    );
+    let mut res = HttpResponse::Ok();
+    res.content_type("application/epub+zip")
+        .insert_header(attachment(&format!("{}.epub", book.title)))
+        .insert_header(CacheControl(vec![CacheDirective::Private]));
 
     log::info!("{}: book is successfuly downloaded", op);
 
-    // Строим HTTP-ответ с буфером и заголовком Content-Disposition
-    HttpResponse::Ok()
-        .content_type("application/octet-stream")
-        .append_header(("Content-Disposition", content_disposition))
-        .body(buffer)
+    stream_blob(
+        &req,
+        app_data.storage.as_ref(),
+        BlobKind::Book,
+        &book.filename,
+        meta,
+        res,
+    )
+    .await
 }
 
 /// Upload the file for book
@@ -507,7 +535,11 @@ async fn download_book(
 /// Path:
 /// POST: **/api/book/upload**
 #[post("/upload")]
-async fn upload_book(_: JwtCred, mut payload: Multipart) -> impl Responder {
+async fn upload_book(
+    _: JwtCred,
+    mut payload: Multipart,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
     let op = "upload_book";
     let mut filename = String::new();
 
@@ -543,29 +575,21 @@ async fn upload_book(_: JwtCred, mut payload: Multipart) -> impl Responder {
 
         filename = Uuid::new_v4().to_string();
 
-        // FIXME: directory must be a const!
-        let filepath = format!("./uploads/books/{}", &filename);
-
-        let mut file = match File::create(&filepath).await {
-            Ok(file) => file,
-            Err(err) => {
-                log::error!(
-                    "{}: failed to save the file, file path: {}, error: {}",
-                    op,
-                    filepath,
-                    err
-                );
-
-                return HttpResponse::InternalServerError().finish();
-            }
-        };
+        let mut data = Vec::new();
 
         while let Some(chunk) = field.try_next().await.unwrap() {
-            if let Err(err) = file.write_all(&chunk).await {
-                log::error!("{}: failed to write the chunk, error: {}", op, err);
+            data.extend_from_slice(&chunk);
+        }
+
+        if let Err(err) = app_data.storage.put(BlobKind::Book, &filename, data).await {
+            log::error!(
+                "{}: failed to save the file: {}, error: {}",
+                op,
+                filename,
+                err
+            );
 
-                return HttpResponse::InternalServerError().finish();
-            };
+            return HttpResponse::InternalServerError().finish();
         }
 
         // FIXME: need to wait then file are saving
@@ -595,6 +619,16 @@ async fn upload_book(_: JwtCred, mut payload: Multipart) -> impl Responder {
     HttpResponse::Ok().json(filename)
 }
 
+/// Read EPUB file of book from storage
+async fn read_epub(
+    filename: &str,
+    app_data: &AppState,
+) -> Result<EpubDoc<Cursor<Vec<u8>>>, Box<dyn Error>> {
+    let data = app_data.storage.get(BlobKind::Book, filename).await?;
+
+    Ok(EpubDoc::from_reader(Cursor::new(data))?)
+}
+
 enum AvailableError {
     NotFound,
     Forbidden,
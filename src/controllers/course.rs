@@ -1,13 +1,36 @@
+use std::error::Error;
+use std::io::ErrorKind;
+
 use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
 use futures_util::future::try_join_all;
 use serde::Deserialize;
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::extractors::jwt_cred::JwtCred;
+use crate::extractors::permission::{perms, Authorized};
 
 use crate::models::common::ErrorResponse;
-use crate::models::course::{CourseOut, CreateCourse, UpdateCourse};
+use crate::models::course::{
+    AddCourseMember, CatalogCursor, CatalogPage, CatalogQuery, CatalogSort, CloneCourse, CourseOut,
+    CourseRole, CreateCourse, PublishCourse, TemplateCourse, TransferCourse, UpdateCourse,
+    UpdateCourseDetails,
+};
+use crate::models::image::{variant_filename, ImageEncoding, ImageSize};
+use crate::models::invite::CreateInvite;
+use crate::models::lesson::LessonCopy;
+use crate::models::role::Permission;
 use crate::services::course::*;
+use crate::services::image::copy_image_db;
+use crate::services::invite::{
+    create_invite, find_active_invite, find_course_invites, revoke_invite, use_invite,
+};
+use crate::services::lesson::find_lessons_in_course;
+use crate::services::user::{find_user_by_email, find_user_by_id, find_users_by_username};
+use crate::utils::access::{can_edit_course, can_manage_course, can_view_course};
+use crate::utils::mailer::{send_mail, Mail};
+use crate::utils::policy::UnverifiedAction;
+use crate::utils::storage::BlobKind;
 use crate::AppState;
 
 pub fn course_config(cfg: &mut web::ServiceConfig) {
@@ -16,11 +39,24 @@ pub fn course_config(cfg: &mut web::ServiceConfig) {
             .service(create_course)
             .service(get_course)
             .service(get_courses)
+            .service(get_catalog)
+            .service(update_details)
             .service(delete_course)
             .service(update_course)
             .service(subscribe)
             .service(unsubscribe)
             .service(generate_invite_link)
+            .service(publish_course)
+            .service(set_template)
+            .service(get_templates)
+            .service(clone_course)
+            .service(get_members)
+            .service(add_member)
+            .service(remove_member)
+            .service(transfer_ownership)
+            .service(get_invites)
+            .service(create_course_invite)
+            .service(revoke_course_invite)
             .service(is_owner),
     );
 }
@@ -33,7 +69,7 @@ pub fn course_config(cfg: &mut web::ServiceConfig) {
 /// **/api/course/create**
 #[post("/create")]
 pub async fn create_course(
-    creds: JwtCred,
+    creds: Authorized<perms::CreateCourse>,
     course: web::Json<CreateCourse>,
     app_data: web::Data<AppState>,
 ) -> impl Responder {
@@ -52,6 +88,18 @@ pub async fn create_course(
         });
     }
 
+    if !app_data
+        .unverified_policy
+        .allows(UnverifiedAction::CreateCourse, creds.uid, &app_data.pool)
+        .await
+    {
+        log::warn!("{}: email of user: {} is not verified", op, creds.uid);
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: String::from("email is not verified"),
+        });
+    }
+
     let new_course_id = match create_course_db(creds.uid, &course, &app_data.pool).await {
         Ok(id) => id,
         Err(err) => {
@@ -82,7 +130,7 @@ pub struct GetAllCoursesFilter {
     subscriptions: Option<bool>,
 }
 
-/// Get all courses request
+/// Get all courses request, return public courses and courses where user is member
 ///
 /// Path:
 /// **/api/course/all**
@@ -109,7 +157,7 @@ pub async fn get_courses(
     if filter.subscriptions.unwrap_or(false) {
         courses = get_subscribed(creds.uid, &app_data.pool).await;
     } else {
-        courses = get_courses_db(&app_data.pool).await;
+        courses = get_courses_db(user_id, &app_data.pool).await;
     }
 
     let courses = match courses {
@@ -162,9 +210,22 @@ pub async fn get_course(
     );
 
     let course = match find_course_by_id(course_id, &app_data.pool).await {
-        Ok(course) => CourseOut::from_course(course, user_id, &app_data.pool)
-            .await
-            .unwrap(),
+        Ok(course) if can_view_course(&creds, &course, &app_data.pool).await => {
+            CourseOut::from_course(course, user_id, &app_data.pool)
+                .await
+                .unwrap()
+        }
+        Ok(_) => {
+            log::warn!(
+                "{}: course by id: {} is private, user: {} is not member",
+                op,
+                course_id,
+                user_id
+            );
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "course by id is not exist".to_string(),
+            });
+        }
         Err(err) => {
             log::error!(
                 "{}: course by id: {} is not exist, error: {}",
@@ -219,10 +280,7 @@ pub async fn update_course(
         });
     }
 
-    if !user_is_owner(user_id, course_id, &app_data.pool)
-        .await
-        .unwrap_or(false)
-    {
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
         log::warn!(
             "{}: user by id: {}, is not owner of course id: {}",
             op,
@@ -278,10 +336,7 @@ pub async fn delete_course(
         return HttpResponse::NotFound();
     }
 
-    if !user_is_owner(user_id, course_id, &app_data.pool)
-        .await
-        .unwrap_or(false)
-    {
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
         log::warn!(
             "{}: user by id: {}, is not owner of course id: {}",
             op,
@@ -323,20 +378,26 @@ pub async fn subscribe(
         link
     );
 
-    let course_id = match find_course_by_invite_link(&link, &app_data.pool).await {
-        Ok(course) => course.id,
-        Err(err) => {
+    let invite = match find_active_invite(&link, &app_data.pool).await {
+        Ok(Some(invite)) => invite,
+        Ok(None) => {
             log::error!(
-                "{}: course not found, error: {}, course_id: {}",
+                "{}: invite link is not found, expired or revoked, link: {}",
                 op,
-                err,
                 link
             );
 
             return HttpResponse::NotFound();
         }
+        Err(err) => {
+            log::error!("{}: can not find invite link, error: {}", op, err);
+
+            return HttpResponse::InternalServerError();
+        }
     };
 
+    let course_id = invite.course_id;
+
     log::info!(
         "{}: find course by link {}, course id: {}",
         op,
@@ -358,17 +419,25 @@ pub async fn subscribe(
         return HttpResponse::Forbidden();
     }
 
-    if let Err(err) = subscribe_to_course(user_id, course_id, &app_data.pool).await {
-        log::error!(
-            "{}: error with subscribe to course, error: {}, course_id: {}, user_id: {}",
-            op,
-            err,
-            course_id,
-            creds.uid
-        );
+    match use_invite(invite.id, user_id, &app_data.pool).await {
+        Ok(true) => {}
+        Ok(false) => {
+            log::warn!("{}: invite link: {} was used up", op, invite.id);
 
-        return HttpResponse::InternalServerError();
-    };
+            return HttpResponse::NotFound();
+        }
+        Err(err) => {
+            log::error!(
+                "{}: error with subscribe to course, error: {}, course_id: {}, user_id: {}",
+                op,
+                err,
+                course_id,
+                creds.uid
+            );
+
+            return HttpResponse::InternalServerError();
+        }
+    }
 
     log::info!(
         "{}: user are successfuly subscribed, user_id: {}, course_id: {}",
@@ -481,7 +550,9 @@ pub async fn is_owner(
     HttpResponse::Ok().json(ownered)
 }
 
-/// Generate invite link for course
+/// Generate invite link for course without limits
+///
+/// Previous links stay valid, use `/api/course/{id}/invites` to manage them
 ///
 /// Path:
 /// **/api/course/invite/generate/*{id}***
@@ -503,10 +574,7 @@ pub async fn generate_invite_link(
         course_id
     );
 
-    if !user_is_owner(user_id, course_id, &app_data.pool)
-        .await
-        .unwrap_or(false)
-    {
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
         log::error!(
             "{}: user is not owner of course, user_id: {}, course_id: {}",
             op,
@@ -519,8 +587,20 @@ pub async fn generate_invite_link(
         });
     }
 
-    match generate_link(course_id, &app_data.pool).await {
-        Ok(link) => HttpResponse::Ok().json(link),
+    if !app_data
+        .unverified_policy
+        .allows(UnverifiedAction::InviteLink, user_id, &app_data.pool)
+        .await
+    {
+        log::warn!("{}: email of user: {} is not verified", op, user_id);
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "email is not verified".to_string(),
+        });
+    }
+
+    match create_invite(course_id, user_id, &CreateInvite::default(), &app_data.pool).await {
+        Ok(invite) => HttpResponse::Ok().json(invite.token),
         Err(err) => {
             log::error!("{}: can not generate invite link, error: {}", op, err);
 
@@ -530,3 +610,1077 @@ pub async fn generate_invite_link(
         }
     }
 }
+
+/// Change visibility of course request
+///
+/// Only users with publish permission (teachers and admins) can make course public
+///
+/// Path:
+/// **/api/course/publish/*{id}***
+#[put("/publish/{id}")]
+pub async fn publish_course(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    data: web::Json<PublishCourse>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "publish_course";
+
+    let user_id = creds.uid;
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to change visibility of course, user_id: {}, course_id: {}, public: {}",
+        op,
+        user_id,
+        course_id,
+        data.public
+    );
+
+    if let Err(err) = find_course_by_id(course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: course by id: {} was not found, error: {}",
+            op,
+            course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course by id not founded".to_string(),
+        });
+    }
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            user_id,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    if data.public && !creds.has_permission(Permission::PublishCourse) {
+        log::warn!("{}: user by id: {} can not publish courses", op, user_id);
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "only teachers can publish courses".to_string(),
+        });
+    }
+
+    if data.public
+        && !app_data
+            .unverified_policy
+            .allows(UnverifiedAction::PublishCourse, user_id, &app_data.pool)
+            .await
+    {
+        log::warn!("{}: email of user: {} is not verified", op, user_id);
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: String::from("email is not verified"),
+        });
+    }
+
+    if let Err(err) = set_course_public(course_id, data.public, &app_data.pool).await {
+        log::error!("{}: cannot change visibility of course, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "cannot change visibility of course".to_string(),
+        });
+    }
+
+    HttpResponse::Ok().json(course_id)
+}
+
+/// Mark course as template or unmark it, templates can be cloned by everyone who can see them
+///
+/// Path:
+/// **/api/course/template/*{id}***
+#[put("/template/{id}")]
+pub async fn set_template(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    data: web::Json<TemplateCourse>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "set_template";
+
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to change template flag of course, user_id: {}, course_id: {}, template: {}",
+        op,
+        creds.uid,
+        course_id,
+        data.template
+    );
+
+    if let Err(err) = find_course_by_id(course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: course by id: {} was not found, error: {}",
+            op,
+            course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course by id not founded".to_string(),
+        });
+    }
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    if let Err(err) = set_course_template(course_id, data.template, &app_data.pool).await {
+        log::error!(
+            "{}: cannot change template flag of course, error: {}",
+            op,
+            err
+        );
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "cannot change template flag of course".to_string(),
+        });
+    }
+
+    HttpResponse::Ok().json(course_id)
+}
+
+/// Get templates which user can clone: public templates and templates where user is member
+///
+/// Path:
+/// **/api/course/templates**
+#[get("/templates")]
+pub async fn get_templates(creds: JwtCred, app_data: web::Data<AppState>) -> impl Responder {
+    let op = "get_templates";
+
+    let user_id = creds.uid;
+
+    log::info!("{}: attempting to get templates, user_id: {}", op, user_id);
+
+    let courses = match find_template_courses(user_id, &app_data.pool).await {
+        Ok(courses) => courses,
+        Err(err) => {
+            log::error!("{}: error getting templates, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "can't get templates".to_string(),
+            });
+        }
+    };
+
+    let courses = match try_join_all(
+        courses
+            .into_iter()
+            .map(|course| CourseOut::from_course(course, user_id, &app_data.pool)),
+    )
+    .await
+    {
+        Ok(courses) => courses,
+        Err(err) => {
+            log::error!("{}: error getting templates, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "can't get templates".to_string(),
+            });
+        }
+    };
+
+    HttpResponse::Ok().json(courses)
+}
+
+/// Clone course with modules, lessons and vocabulary into new private course of user
+///
+/// Editors can clone their courses with drafts and exercises, other users can clone
+/// only published text of templates which they can see
+///
+/// Path:
+/// **/api/course/*{id}*/clone**
+#[post("/{id}/clone")]
+pub async fn clone_course(
+    creds: Authorized<perms::CreateCourse>,
+    path: web::Path<i32>,
+    data: web::Json<CloneCourse>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "clone_course";
+
+    let course_id = path.into_inner();
+    let user_id = creds.uid;
+
+    log::info!(
+        "{}: attempting to clone course: {}, user_id: {}",
+        op,
+        course_id,
+        user_id
+    );
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated, data: {:?}", op, data);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("title field is empty"),
+        });
+    }
+
+    let course = match find_course_by_id(course_id, &app_data.pool).await {
+        Ok(course) if can_view_course(&creds, &course, &app_data.pool).await => course,
+        _ => {
+            log::warn!("{}: course: {} is not available for user", op, course_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "course by id is not exist".to_string(),
+            });
+        }
+    };
+
+    // drafts and answers of exercises are copied only for editors of course
+    let by_editor = can_edit_course(&creds, course_id, &app_data.pool).await;
+
+    if !course.is_template && !by_editor {
+        log::warn!(
+            "{}: user by id: {} can not clone course: {}",
+            op,
+            user_id,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "course is not template".to_string(),
+        });
+    }
+
+    if !app_data
+        .unverified_policy
+        .allows(UnverifiedAction::CreateCourse, user_id, &app_data.pool)
+        .await
+    {
+        log::warn!("{}: email of user: {} is not verified", op, user_id);
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: String::from("email is not verified"),
+        });
+    }
+
+    let lessons = match find_lessons_in_course(course_id, &app_data.pool).await {
+        Ok(lessons) => lessons,
+        Err(err) => {
+            log::error!("{}: cannot get lessons of course, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot clone course".to_string(),
+            });
+        }
+    };
+
+    let mut copies = Vec::with_capacity(lessons.len());
+
+    for lesson in lessons {
+        let content_path = format!("{}.md", Uuid::new_v4());
+
+        // unpublished lesson has no file of text
+        if let Err(err) = app_data
+            .storage
+            .copy(BlobKind::Lesson, &lesson.content_path, &content_path)
+            .await
+        {
+            if err.kind() != ErrorKind::NotFound {
+                log::error!(
+                    "{}: cannot copy text of lesson: {}, error: {}",
+                    op,
+                    lesson.id,
+                    err
+                );
+
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    message: "cannot clone course".to_string(),
+                });
+            }
+        }
+
+        let cover_path = match &lesson.cover_path {
+            Some(cover_path) => {
+                let new_cover_path = Uuid::new_v4().to_string();
+
+                match copy_image(&app_data, cover_path, &new_cover_path, user_id).await {
+                    Ok(_) => Some(new_cover_path),
+                    // copy must not depend on files of source course
+                    Err(err) => {
+                        log::warn!(
+                            "{}: cannot copy cover of lesson: {}, error: {}",
+                            op,
+                            lesson.id,
+                            err
+                        );
+
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+
+        copies.push(LessonCopy {
+            lesson,
+            content_path,
+            cover_path,
+        });
+    }
+
+    let title = data.title.clone().unwrap_or(course.title.clone());
+
+    match clone_course_db(&course, &title, user_id, &copies, by_editor, &app_data.pool).await {
+        Ok(new_course_id) => {
+            log::info!(
+                "{}: course: {} was cloned into course: {}",
+                op,
+                course_id,
+                new_course_id
+            );
+
+            HttpResponse::Created().json(new_course_id)
+        }
+        Err(err) => {
+            log::error!("{}: cannot clone course, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot clone course".to_string(),
+            })
+        }
+    }
+}
+
+/// Copy image with its thumbnails and metadata, images uploaded before thumbnails
+/// have only original
+async fn copy_image(
+    app_data: &AppState,
+    from: &str,
+    to: &str,
+    owner_id: i32,
+) -> Result<(), Box<dyn Error>> {
+    app_data.storage.copy(BlobKind::Image, from, to).await?;
+
+    for size in ImageSize::THUMBNAILS
+        .into_iter()
+        .chain([ImageSize::Original])
+    {
+        for encoding in [ImageEncoding::Original, ImageEncoding::Webp] {
+            if size == ImageSize::Original && encoding == ImageEncoding::Original {
+                continue;
+            }
+
+            if let Err(err) = app_data
+                .storage
+                .copy(
+                    BlobKind::Image,
+                    &variant_filename(from, size, encoding),
+                    &variant_filename(to, size, encoding),
+                )
+                .await
+            {
+                if err.kind() != ErrorKind::NotFound {
+                    return Err(err.into());
+                }
+            }
+        }
+    }
+
+    // copy is owned by new course, storage gc tracks it by metadata
+    copy_image_db(from, to, owner_id, &app_data.pool).await
+}
+
+/// Get members of course with their roles, available for owners and editors
+///
+/// Path:
+/// **/api/course/*{id}*/members**
+#[get("/{id}/members")]
+pub async fn get_members(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_members";
+
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to get members of course, user_id: {}, course_id: {}",
+        op,
+        creds.uid,
+        course_id
+    );
+
+    if let Err(err) = find_course_by_id(course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: course by id: {} was not found, error: {}",
+            op,
+            course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course by id not founded".to_string(),
+        });
+    }
+
+    if !can_edit_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, can not edit course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not editor of course".to_string(),
+        });
+    }
+
+    match find_course_members(course_id, &app_data.pool).await {
+        Ok(members) => HttpResponse::Ok().json(members),
+        Err(err) => {
+            log::error!("{}: can not get members of course, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "can not get members of course".to_string(),
+            })
+        }
+    }
+}
+
+/// Add collaborator or student to course by email or username
+///
+/// Role of existing member is changed, owner can be changed only by transfer
+///
+/// Path:
+/// **/api/course/*{id}*/members**
+#[post("/{id}/members")]
+pub async fn add_member(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    data: web::Json<AddCourseMember>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "add_member";
+
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to add member to course, user_id: {}, course_id: {}, data: {:?}",
+        op,
+        creds.uid,
+        course_id,
+        data
+    );
+
+    if data.validate().is_err() || data.email.is_some() == data.username.is_some() {
+        log::error!("{}: data is not validated, data: {:?}", op, data);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "email or username is required".to_string(),
+        });
+    }
+
+    if data.role == CourseRole::Owner {
+        log::error!("{}: owner can not be added, use transfer", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "ownership can be changed only by transfer".to_string(),
+        });
+    }
+
+    let course = match find_course_by_id(course_id, &app_data.pool).await {
+        Ok(course) => course,
+        Err(err) => {
+            log::warn!(
+                "{}: course by id: {} was not found, error: {}",
+                op,
+                course_id,
+                err
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "course by id not founded".to_string(),
+            });
+        }
+    };
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    let user = match (&data.email, &data.username) {
+        (Some(email), _) => find_user_by_email(email, &app_data.pool).await.ok(),
+        (_, Some(username)) => match find_users_by_username(username, &app_data.pool).await {
+            Ok(mut users) if users.len() == 1 => users.pop(),
+            Ok(users) if users.len() > 1 => {
+                log::warn!("{}: username: {} is not unique", op, username);
+
+                return HttpResponse::Conflict().json(ErrorResponse {
+                    message: "several users have this username, use email".to_string(),
+                });
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let user = match user {
+        Some(user) => user,
+        None => {
+            log::warn!("{}: user to add was not found", op);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "user not found".to_string(),
+            });
+        }
+    };
+
+    if let Ok(Some(CourseRole::Owner)) = find_member_role(user.id, course_id, &app_data.pool).await
+    {
+        log::warn!("{}: user: {} is owner of course", op, user.id);
+
+        return HttpResponse::Conflict().json(ErrorResponse {
+            message: "ownership can be changed only by transfer".to_string(),
+        });
+    }
+
+    if let Err(err) = set_course_member(user.id, course_id, data.role, &app_data.pool).await {
+        log::error!("{}: can not add member to course, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "can not add member to course".to_string(),
+        });
+    }
+
+    let mail = Mail {
+        to: user.email.clone(),
+        subject: format!("You were added to course \"{}\"", course.title),
+        body: format!(
+            "You were added to course \"{}\" on ReadCraft as {}.",
+            course.title, data.role
+        ),
+    };
+
+    if let Err(err) = send_mail(&app_data.mailer, mail).await {
+        log::error!("{}: can not send notification, error: {}", op, err);
+    }
+
+    log::info!(
+        "{}: user: {} was added to course: {} as {}",
+        op,
+        user.id,
+        course_id,
+        data.role
+    );
+
+    HttpResponse::Ok().json(user.id)
+}
+
+/// Remove member from course, owner can not be removed
+///
+/// Path:
+/// **/api/course/*{id}*/members/*{user_id}***
+#[delete("/{id}/members/{user_id}")]
+pub async fn remove_member(
+    creds: JwtCred,
+    path: web::Path<(i32, i32)>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "remove_member";
+
+    let (course_id, member_id) = path.into_inner();
+
+    log::info!(
+        "{}: attempting to remove member: {} from course: {} by user: {}",
+        op,
+        member_id,
+        course_id,
+        creds.uid
+    );
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    match find_member_role(member_id, course_id, &app_data.pool).await {
+        Ok(Some(CourseRole::Owner)) => {
+            log::warn!("{}: owner can not be removed", op);
+
+            return HttpResponse::Conflict().json(ErrorResponse {
+                message: "owner can not be removed, transfer ownership first".to_string(),
+            });
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            log::warn!("{}: user: {} is not member of course", op, member_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "member not found".to_string(),
+            });
+        }
+        Err(err) => {
+            log::error!("{}: can not get member of course, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    if let Err(err) = unsubscribe_to_course(member_id, course_id, &app_data.pool).await {
+        log::error!("{}: can not remove member, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "can not remove member".to_string(),
+        });
+    }
+
+    log::info!(
+        "{}: member: {} was removed from course: {}",
+        op,
+        member_id,
+        course_id
+    );
+
+    HttpResponse::Ok().finish()
+}
+
+/// Transfer ownership of course to another user, previous owner becomes editor
+///
+/// Path:
+/// **/api/course/*{id}*/transfer**
+#[post("/{id}/transfer")]
+pub async fn transfer_ownership(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    data: web::Json<TransferCourse>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "transfer_ownership";
+
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to transfer course: {} to user: {} by user: {}",
+        op,
+        course_id,
+        data.user_id,
+        creds.uid
+    );
+
+    if let Err(err) = find_course_by_id(course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: course by id: {} was not found, error: {}",
+            op,
+            course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course by id not founded".to_string(),
+        });
+    }
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    if let Err(err) = find_user_by_id(data.user_id, &app_data.pool).await {
+        log::warn!("{}: user: {} not found, error: {}", op, data.user_id, err);
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "user not found".to_string(),
+        });
+    }
+
+    if let Err(err) = transfer_course(data.user_id, course_id, &app_data.pool).await {
+        log::error!("{}: can not transfer course, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "can not transfer course".to_string(),
+        });
+    }
+
+    log::info!(
+        "{}: course: {} was transferred to user: {}",
+        op,
+        course_id,
+        data.user_id
+    );
+
+    HttpResponse::Ok().json(course_id)
+}
+
+/// Create invite link with optional name, expiration and max number of uses
+///
+/// Path:
+/// **/api/course/*{id}*/invites**
+#[post("/{id}/invites")]
+pub async fn create_course_invite(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    data: web::Json<CreateInvite>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "create_course_invite";
+
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to create invite link for course: {} by user: {}",
+        op,
+        course_id,
+        creds.uid
+    );
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "invalid data".to_string(),
+        });
+    }
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    if !app_data
+        .unverified_policy
+        .allows(UnverifiedAction::InviteLink, creds.uid, &app_data.pool)
+        .await
+    {
+        log::warn!("{}: email of user: {} is not verified", op, creds.uid);
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "email is not verified".to_string(),
+        });
+    }
+
+    match create_invite(course_id, creds.uid, &data, &app_data.pool).await {
+        Ok(invite) => {
+            log::info!(
+                "{}: invite link: {} was created for course: {}",
+                op,
+                invite.id,
+                course_id
+            );
+
+            HttpResponse::Ok().json(invite)
+        }
+        Err(err) => {
+            log::error!("{}: can not create invite link, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "can not generate invite link".to_string(),
+            })
+        }
+    }
+}
+
+/// Get invite links of course with users who joined by every link
+///
+/// Path:
+/// **/api/course/*{id}*/invites**
+#[get("/{id}/invites")]
+pub async fn get_invites(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_invites";
+
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to get invite links of course: {} by user: {}",
+        op,
+        course_id,
+        creds.uid
+    );
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    match find_course_invites(course_id, &app_data.pool).await {
+        Ok(invites) => HttpResponse::Ok().json(invites),
+        Err(err) => {
+            log::error!("{}: can not get invite links, error: {}", op, err);
+
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Revoke invite link, users who already joined stay in course
+///
+/// Path:
+/// **/api/course/*{id}*/invites/*{invite_id}***
+#[delete("/{id}/invites/{invite_id}")]
+pub async fn revoke_course_invite(
+    creds: JwtCred,
+    path: web::Path<(i32, i32)>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "revoke_course_invite";
+
+    let (course_id, invite_id) = path.into_inner();
+
+    log::info!(
+        "{}: attempting to revoke invite link: {} of course: {} by user: {}",
+        op,
+        invite_id,
+        course_id,
+        creds.uid
+    );
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    match revoke_invite(course_id, invite_id, &app_data.pool).await {
+        Ok(true) => {
+            log::info!("{}: invite link: {} was revoked", op, invite_id);
+
+            HttpResponse::Ok().finish()
+        }
+        Ok(false) => {
+            log::warn!("{}: invite link: {} was not found", op, invite_id);
+
+            HttpResponse::NotFound().json(ErrorResponse {
+                message: "invite link not found".to_string(),
+            })
+        }
+        Err(err) => {
+            log::error!("{}: can not revoke invite link, error: {}", op, err);
+
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Default count of courses on catalog page
+const CATALOG_PAGE_SIZE: i64 = 20;
+
+/// Catalog of public courses request
+///
+/// Filter by language, level and tags, search by title and description.
+/// Sort is `recent` (default) or `popular`, next page is requested by `cursor` from response
+///
+/// Path:
+/// **/api/course/catalog?language=en&level=beginner&tags=grammar,verbs&q=text&sort=popular**
+#[get("/catalog")]
+pub async fn get_catalog(
+    _creds: JwtCred,
+    query: web::Query<CatalogQuery>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_catalog";
+
+    log::info!("{}: attempting to get catalog, query: {:?}", op, query);
+
+    if query.validate().is_err() {
+        log::error!("{}: query is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "invalid query".to_string(),
+        });
+    }
+
+    let cursor = match query.cursor.as_deref().map(CatalogCursor::decode) {
+        Some(None) => {
+            log::error!("{}: invalid cursor", op);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: "invalid cursor".to_string(),
+            });
+        }
+        Some(Some(cursor)) if !cursor.matches(query.sort) => {
+            log::error!("{}: cursor does not match sort", op);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: "cursor does not match sort".to_string(),
+            });
+        }
+        Some(cursor) => cursor,
+        None => None,
+    };
+
+    let limit = query.limit.unwrap_or(CATALOG_PAGE_SIZE);
+
+    // one more course is requested to know if next page exists
+    let mut courses =
+        match find_catalog_courses(&query, cursor.as_ref(), limit + 1, &app_data.pool).await {
+            Ok(courses) => courses,
+            Err(err) => {
+                log::error!("{}: error getting catalog, error: {}", op, err);
+
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    message: "can't get courses".to_string(),
+                });
+            }
+        };
+
+    let next_cursor = if courses.len() as i64 > limit {
+        courses.truncate(limit as usize);
+
+        courses.last().map(|course| {
+            CatalogCursor {
+                sort: query.sort,
+                created_at: (query.sort == CatalogSort::Recent).then_some(course.created_at),
+                subscribers: (query.sort == CatalogSort::Popular).then_some(course.subscribers),
+                id: course.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    log::info!(
+        "{}: catalog is returned, course count: {}",
+        op,
+        courses.len()
+    );
+
+    HttpResponse::Ok().json(CatalogPage {
+        courses,
+        next_cursor,
+    })
+}
+
+/// Update description, level and tags of course, which are used in catalog
+///
+/// Path:
+/// **/api/course/*{id}*/details**
+#[put("/{id}/details")]
+pub async fn update_details(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    data: web::Json<UpdateCourseDetails>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "update_course_details";
+
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to update details of course: {} by user: {}",
+        op,
+        course_id,
+        creds.uid
+    );
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "invalid data".to_string(),
+        });
+    }
+
+    if let Err(err) = find_course_by_id(course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: course by id: {} was not found, error: {}",
+            op,
+            course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course by id not founded".to_string(),
+        });
+    }
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    if let Err(err) = update_course_details(course_id, &data, &app_data.pool).await {
+        log::error!("{}: cannot update details of course, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "cannot update course".to_string(),
+        });
+    }
+
+    log::info!("{}: details of course: {} were updated", op, course_id);
+
+    HttpResponse::Ok().json(course_id)
+}
@@ -0,0 +1,434 @@
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    extractors::jwt_cred::JwtCred,
+    models::{
+        common::ErrorResponse,
+        exercise::{CreateExercise, ExerciseOut, SubmitAnswer, UpdateExercise},
+    },
+    services::{course::find_course_by_lesson, exercise::*, lesson::find_lesson_by_id},
+    utils::{
+        access::{can_edit_course, can_edit_lesson, can_read_lesson},
+        exercise::{grade, is_valid_content, to_task},
+    },
+    AppState,
+};
+
+pub fn exercise_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/exercise")
+            .service(create_exercise)
+            .service(get_exercises)
+            .service(update_exercise)
+            .service(delete_exercise)
+            .service(submit_answer)
+            .service(get_attempts),
+    );
+}
+
+/// Create exercise in lesson
+///
+/// Path:
+/// **/api/exercise/create**
+#[post("/create")]
+pub async fn create_exercise(
+    creds: JwtCred,
+    exercise: web::Json<CreateExercise>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "create_exercise";
+
+    log::info!(
+        "{}: attempting to create exercise in lesson: {}, user_id: {}",
+        op,
+        exercise.lesson_id,
+        creds.uid
+    );
+
+    if exercise.validate().is_err() || !is_valid_content(&exercise.content) {
+        log::error!("{}: data is not validated, data: {:?}", op, exercise);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("invalid data"),
+        });
+    }
+
+    let course = match find_course_by_lesson(exercise.lesson_id, &app_data.pool).await {
+        Ok(course) => course,
+        Err(_) => {
+            log::warn!("{}: lesson by id: {} not found", op, exercise.lesson_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson not found".to_string(),
+            });
+        }
+    };
+
+    if !can_edit_course(&creds, course.id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {} is not owner of course id: {}",
+            op,
+            creds.uid,
+            course.id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    match create_exercise_db(&exercise, &app_data.pool).await {
+        Ok(exercise_id) => {
+            log::info!(
+                "{}: exercise are successfuly created, id: {}",
+                op,
+                exercise_id
+            );
+
+            HttpResponse::Created().json(exercise_id)
+        }
+        Err(err) => {
+            log::error!("{}: cannot create exercise, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: String::from("cannot create exercise"),
+            })
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetExercisesFilter {
+    answers: Option<bool>,
+}
+
+/// Get exercises of lesson without answers
+///
+/// Path:
+/// **/api/exercise/lesson/*{lesson_id}***
+/// or with answers for authors of course
+/// **/api/exercise/lesson/*{lesson_id}*?answers=true**
+#[get("/lesson/{lesson_id}")]
+pub async fn get_exercises(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    filter: web::Query<GetExercisesFilter>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_exercises";
+
+    let lesson_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to get exercises of lesson: {}, filter: {:?}",
+        op,
+        lesson_id,
+        filter
+    );
+
+    let lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
+        Ok(lesson) if can_read_lesson(&creds, &lesson, &app_data.pool).await => lesson,
+        _ => {
+            log::warn!("{}: lesson: {} is not available for user", op, lesson_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson not found".to_string(),
+            });
+        }
+    };
+
+    let with_answers = filter.answers.unwrap_or(false);
+
+    if with_answers && !can_edit_lesson(&creds, &lesson, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {} can not see answers of course: {:?}",
+            op,
+            creds.uid,
+            lesson.course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    let exercises = match find_exercises_in_lesson(lesson_id, &app_data.pool).await {
+        Ok(exercises) => exercises,
+        Err(err) => {
+            log::error!("{}: cannot get exercises, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot get exercises".to_string(),
+            });
+        }
+    };
+
+    if with_answers {
+        return HttpResponse::Ok().json(exercises);
+    }
+
+    let exercises: Vec<ExerciseOut> = exercises
+        .into_iter()
+        .map(|exercise| ExerciseOut {
+            id: exercise.id,
+            position: exercise.position,
+            task: to_task(&exercise.content),
+            prompt: exercise.prompt,
+            lesson_id: exercise.lesson_id,
+        })
+        .collect();
+
+    HttpResponse::Ok().json(exercises)
+}
+
+/// Update exercise
+///
+/// Path:
+/// **/api/exercise/update**
+#[put("/update")]
+pub async fn update_exercise(
+    creds: JwtCred,
+    new_exercise: web::Json<UpdateExercise>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "update_exercise";
+
+    log::info!(
+        "{}: attempting to update exercise: {}, user_id: {}",
+        op,
+        new_exercise.id,
+        creds.uid
+    );
+
+    if new_exercise.validate().is_err() || !is_valid_content(&new_exercise.content) {
+        log::error!("{}: data is not valid, data: {:?}", op, new_exercise);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "data is not valid".to_string(),
+        });
+    }
+
+    let exercise = match find_exercise_by_id(new_exercise.id, &app_data.pool).await {
+        Ok(exercise) => exercise,
+        Err(err) => {
+            log::error!(
+                "{}: exercise by id: {} is not exist, error: {}",
+                op,
+                new_exercise.id,
+                err
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "exercise by id is not exist".to_string(),
+            });
+        }
+    };
+
+    let can_edit = match find_course_by_lesson(exercise.lesson_id, &app_data.pool).await {
+        Ok(course) => can_edit_course(&creds, course.id, &app_data.pool).await,
+        Err(_) => false,
+    };
+
+    if !can_edit {
+        log::warn!(
+            "{}: user by id: {} can not edit exercise: {}",
+            op,
+            creds.uid,
+            exercise.id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    if let Err(err) = update_exercise_db(&new_exercise, &app_data.pool).await {
+        log::error!("{}: cannot update exercise, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "cannot update exercise".to_string(),
+        });
+    }
+
+    HttpResponse::Ok().json(exercise.id)
+}
+
+/// Delete exercise with attempts of students
+///
+/// Path:
+/// **/api/exercise/delete/*{id}***
+#[delete("/delete/{id}")]
+pub async fn delete_exercise(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "delete_exercise";
+
+    let exercise_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to delete exercise: {}, user_id: {}",
+        op,
+        exercise_id,
+        creds.uid
+    );
+
+    let exercise = match find_exercise_by_id(exercise_id, &app_data.pool).await {
+        Ok(exercise) => exercise,
+        Err(err) => {
+            log::error!(
+                "{}: exercise by id: {} was not found, error: {}",
+                op,
+                exercise_id,
+                err
+            );
+
+            return HttpResponse::NotFound();
+        }
+    };
+
+    let can_edit = match find_course_by_lesson(exercise.lesson_id, &app_data.pool).await {
+        Ok(course) => can_edit_course(&creds, course.id, &app_data.pool).await,
+        Err(_) => false,
+    };
+
+    if !can_edit {
+        log::warn!(
+            "{}: user by id: {} can not delete exercise: {}",
+            op,
+            creds.uid,
+            exercise_id
+        );
+
+        return HttpResponse::Forbidden();
+    }
+
+    if let Err(err) = delete_exercise_db(exercise_id, &app_data.pool).await {
+        log::error!("{}: cannot delete exercise, error: {}", op, err);
+
+        return HttpResponse::InternalServerError();
+    }
+
+    HttpResponse::Ok()
+}
+
+/// Submit answer to exercise, answer is graded and saved as attempt
+///
+/// Path:
+/// **/api/exercise/submit/*{id}***
+#[post("/submit/{id}")]
+pub async fn submit_answer(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    data: web::Json<SubmitAnswer>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "submit_answer";
+
+    let exercise_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to submit answer to exercise: {}, user_id: {}",
+        op,
+        exercise_id,
+        creds.uid
+    );
+
+    let exercise = match find_exercise_by_id(exercise_id, &app_data.pool).await {
+        Ok(exercise) => exercise,
+        Err(err) => {
+            log::error!(
+                "{}: exercise by id: {} was not found, error: {}",
+                op,
+                exercise_id,
+                err
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "exercise not found".to_string(),
+            });
+        }
+    };
+
+    match find_lesson_by_id(exercise.lesson_id, &app_data.pool).await {
+        Ok(lesson) if can_read_lesson(&creds, &lesson, &app_data.pool).await => {}
+        _ => {
+            log::warn!(
+                "{}: exercise: {} is not available for user",
+                op,
+                exercise_id
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "exercise not found".to_string(),
+            });
+        }
+    }
+
+    let score = match grade(&exercise.content, &data.answer) {
+        Some(score) => score,
+        None => {
+            log::error!("{}: kind of answer does not match exercise", op);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: "kind of answer does not match exercise".to_string(),
+            });
+        }
+    };
+
+    match create_attempt(exercise_id, creds.uid, &data.answer, score, &app_data.pool).await {
+        Ok(attempt) => {
+            log::info!(
+                "{}: answer of user: {} was graded, score: {}",
+                op,
+                creds.uid,
+                score
+            );
+
+            HttpResponse::Ok().json(attempt)
+        }
+        Err(err) => {
+            log::error!("{}: cannot save attempt, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot save attempt".to_string(),
+            })
+        }
+    }
+}
+
+/// Get attempts of user for exercise, newest first
+///
+/// Path:
+/// **/api/exercise/attempts/*{id}***
+#[get("/attempts/{id}")]
+pub async fn get_attempts(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_attempts";
+
+    let exercise_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to get attempts of user: {} for exercise: {}",
+        op,
+        creds.uid,
+        exercise_id
+    );
+
+    match find_attempts(exercise_id, creds.uid, &app_data.pool).await {
+        Ok(attempts) => HttpResponse::Ok().json(attempts),
+        Err(err) => {
+            log::error!("{}: cannot get attempts, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot get attempts".to_string(),
+            })
+        }
+    }
+}
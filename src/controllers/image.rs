@@ -1,40 +1,93 @@
-use std::env;
+use std::io::ErrorKind;
 
 use actix_multipart::Multipart;
-use actix_web::{get, post, web, HttpResponse, Responder};
-use futures_util::{StreamExt, TryStreamExt};
-use tokio::{
-    fs::File,
-    io::{AsyncReadExt, AsyncWriteExt},
+use actix_web::{
+    get,
+    http::header::{self, CacheControl, CacheDirective},
+    post, web, HttpRequest, HttpResponse, Responder,
 };
+use futures_util::StreamExt;
+use serde::Deserialize;
 use uuid::Uuid;
 
-use crate::{extractors::jwt_cred::JwtCred, models::common::ErrorResponse};
+use crate::{
+    extractors::jwt_cred::JwtCred,
+    models::{
+        common::ErrorResponse,
+        image::{variant_filename, ImageEncoding, ImageSize, ImageType},
+    },
+    services::image::{find_image_by_filename, save_image},
+    utils::{
+        download::stream_blob,
+        image::{content_type, process_image, InvalidImage, MAX_IMAGE_SIZE},
+        storage::{BlobKind, ByteRange},
+    },
+    AppState,
+};
+
+/// Images are saved under new names, so they can be cached for a long time
+const IMAGE_MAX_AGE: u32 = 60 * 60 * 24 * 30;
+/// Sanitized SVG starts with `<svg`, other types have signature in first bytes
+const IMAGE_HEAD_SIZE: u64 = 512;
 
 pub fn image_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/image")
+            .service(get_image_info)
             .service(get_image)
             .service(upload_image),
     );
 }
 
-/// Upload image of lesson to server
+/// Upload image of lesson, book or course to server
+///
+/// Only JPEG, PNG, GIF, WebP and SVG are accepted, type is detected by content of file,
+/// animated GIFs are rejected.
+/// Image is decoded, saved without metadata and with thumbnails and WebP variants,
+/// returns filename of image
 ///
 /// Path:
-/// GET: /api/lesson/image/upload
+/// POST: /api/image/upload
 #[post("/upload")]
-async fn upload_image(_: JwtCred, mut payload: Multipart) -> impl Responder {
+async fn upload_image(
+    creds: JwtCred,
+    mut payload: Multipart,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
     let op = "upload_image";
-    let mut filename = String::new();
 
     log::info!("{}: attempting to upload image", op);
 
-    while let Some(item) = payload.next().await {
-        let mut field = match item {
-            Ok(f) => f,
+    let mut field = match payload.next().await {
+        Some(Ok(field)) => field,
+        Some(Err(err)) => {
+            log::error!("{}: lost file in request, error: {}", op, err);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: "Failed to process the request".to_string(),
+            });
+        }
+        None => {
+            log::error!("{}: file is missing in request", op);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: "file is missing".to_string(),
+            });
+        }
+    };
+
+    let original_name = field
+        .content_disposition()
+        .get_filename()
+        .map(|name| name.to_string());
+
+    let mut data = Vec::new();
+
+    while let Some(chunk) = field.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
             Err(err) => {
-                log::error!("{}: lost file in request, error: {}", op, err);
+                log::error!("{}: failed to read the chunk, error: {}", op, err);
 
                 return HttpResponse::BadRequest().json(ErrorResponse {
                     message: "Failed to process the request".to_string(),
@@ -42,33 +95,83 @@ async fn upload_image(_: JwtCred, mut payload: Multipart) -> impl Responder {
             }
         };
 
-        filename = Uuid::new_v4().to_string();
+        if data.len() + chunk.len() > MAX_IMAGE_SIZE {
+            log::warn!("{}: image is too large", op);
 
-        let lessons_dir = env::var("IMAGE_DIR").unwrap_or("./uploads/images".to_string());
+            return HttpResponse::PayloadTooLarge().json(ErrorResponse {
+                message: "image is too large".to_string(),
+            });
+        }
 
-        let filepath = format!("{}/{}", lessons_dir, &filename);
+        data.extend_from_slice(&chunk);
+    }
 
-        let mut file = match File::create(&filepath).await {
-            Ok(file) => file,
-            Err(err) => {
-                log::error!(
-                    "{}: failed to save the image, filepath: {}, error: {}",
-                    op,
-                    filepath,
-                    err
-                );
-
-                return HttpResponse::InternalServerError().finish();
-            }
-        };
+    if payload.next().await.is_some() {
+        log::error!("{}: request contains several files", op);
 
-        while let Some(chunk) = field.try_next().await.unwrap() {
-            if let Err(err) = file.write_all(&chunk).await {
-                log::error!("{}: failed to write the chunk, error: {}", op, err);
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "only one file can be uploaded".to_string(),
+        });
+    }
 
-                return HttpResponse::InternalServerError().finish();
-            };
+    // decoding and resizing are heavy, so they are not run on workers of server
+    let image = match web::block(move || process_image(&data)).await {
+        Ok(Ok(image)) => image,
+        Ok(Err(InvalidImage::Unsupported)) => {
+            log::warn!("{}: type of file is not supported", op);
+
+            return HttpResponse::UnsupportedMediaType().json(ErrorResponse {
+                message: "only JPEG, PNG, GIF, WebP and SVG images are supported".to_string(),
+            });
+        }
+        Ok(Err(InvalidImage::Animated)) => {
+            log::warn!("{}: animated image is not supported", op);
+
+            return HttpResponse::UnsupportedMediaType().json(ErrorResponse {
+                message: "animated images are not supported".to_string(),
+            });
+        }
+        Ok(Err(InvalidImage::TooLarge)) => {
+            log::warn!("{}: dimensions of image are too large", op);
+
+            return HttpResponse::PayloadTooLarge().json(ErrorResponse {
+                message: "image is too large".to_string(),
+            });
+        }
+        Ok(Err(err)) => {
+            log::warn!("{}: file is not valid image, error: {}", op, err);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: "file is not valid image".to_string(),
+            });
         }
+        Err(err) => {
+            log::error!("{}: cannot process image, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let filename = Uuid::new_v4().to_string();
+
+    if let Err(err) = save_image(
+        &filename,
+        original_name,
+        image,
+        creds.uid,
+        app_data.storage.as_ref(),
+        &app_data.pool,
+    )
+    .await
+    {
+        log::error!(
+            "{}: failed to save the image: {}, error: {}",
+            op,
+            filename,
+            err
+        );
+
+        return HttpResponse::InternalServerError().finish();
     }
 
     log::info!(
@@ -80,49 +183,156 @@ async fn upload_image(_: JwtCred, mut payload: Multipart) -> impl Responder {
     HttpResponse::Ok().json(filename)
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GetImageFilter {
+    size: Option<ImageSize>,
+    format: Option<ImageEncoding>,
+}
+
 /// Get the image from filename
 ///
 /// Path:
-/// GET: /api/lesson/image/{filename}
+/// GET: /api/image/{filename}
+/// or thumbnail in WebP
+/// GET: /api/image/{filename}?size=small&format=webp
 #[get("/{filename}")]
-async fn get_image(path: web::Path<String>) -> impl Responder {
+async fn get_image(
+    req: HttpRequest,
+    path: web::Path<String>,
+    filter: web::Query<GetImageFilter>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
     let op = "get_image";
 
     let filename = path.into_inner();
+    let size = filter.size.unwrap_or_default();
+    let encoding = filter.format.unwrap_or_default();
 
-    log::info!("{}: attempting to get image: {}", op, filename);
+    log::info!(
+        "{}: attempting to get image: {}, size: {:?}, format: {:?}",
+        op,
+        filename,
+        size,
+        encoding
+    );
 
-    let lessons_dir = env::var("IMAGE_DIR").unwrap_or("./uploads/images".to_string());
-    let filepath = format!("{}/{}", lessons_dir, &filename);
+    let mut key = variant_filename(&filename, size, encoding);
+    let mut encoding = encoding;
 
-    match File::open(&filepath).await {
-        Ok(mut file) => {
-            let mut buffer = Vec::new();
+    let meta = match app_data.storage.stat(BlobKind::Image, &key).await {
+        Ok(meta) => Ok(meta),
+        // images uploaded before thumbnails have only original
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            key = filename.clone();
+            encoding = ImageEncoding::Original;
+            app_data.storage.stat(BlobKind::Image, &key).await
+        }
+        Err(err) => Err(err),
+    };
 
-            if let Err(err) = file.read_to_end(&mut buffer).await {
-                log::error!(
-                    "{}: error reading image: {}, error: {:?}",
-                    op,
-                    filepath,
-                    err
-                );
+    let meta = match meta {
+        Ok(meta) => meta,
+        Err(err) => {
+            log::error!("{}: can not open the image: {}, error: {}", op, key, err);
 
-                return HttpResponse::InternalServerError().finish();
-            }
+            return HttpResponse::NotFound().finish();
+        }
+    };
 
-            log::info!("{}: image succesfuly returned", op);
+    let mime_type = match encoding {
+        ImageEncoding::Webp => ImageType::Webp.mime_type().to_string(),
+        ImageEncoding::Original => match find_image_by_filename(&filename, &app_data.pool).await {
+            Ok(image) => image.content_type,
+            // images uploaded before metadata have no row, type is detected by
+            // beginning of file, image is not loaded into memory
+            Err(_) => match read_head(&app_data, &key, meta.size).await {
+                Ok(head) => content_type(&head).to_string(),
+                Err(err) => {
+                    log::error!("{}: error reading image: {}, error: {:?}", op, key, err);
 
-            HttpResponse::Ok().content_type("image/jpeg").body(buffer)
-        }
+                    return HttpResponse::InternalServerError().finish();
+                }
+            },
+        },
+    };
+
+    let mut res = HttpResponse::Ok();
+    res.content_type(mime_type.as_str())
+        .insert_header(CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(IMAGE_MAX_AGE),
+        ]))
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"));
+
+    // SVG opened directly in browser is document, it must not run anything
+    if mime_type == ImageType::Svg.mime_type() {
+        res.insert_header((
+            header::CONTENT_SECURITY_POLICY,
+            "default-src 'none'; style-src 'unsafe-inline'",
+        ));
+    }
+
+    log::info!("{}: image succesfuly returned", op);
+
+    stream_blob(
+        &req,
+        app_data.storage.as_ref(),
+        BlobKind::Image,
+        &key,
+        meta,
+        res,
+    )
+    .await
+}
+
+/// First bytes of stored image which are enough to detect its type
+async fn read_head(app_data: &AppState, key: &str, size: u64) -> std::io::Result<Vec<u8>> {
+    if size == 0 {
+        return Ok(Vec::new());
+    }
+
+    let range = ByteRange {
+        start: 0,
+        end: size.min(IMAGE_HEAD_SIZE) - 1,
+    };
+
+    let mut stream = app_data
+        .storage
+        .stream(BlobKind::Image, key, Some(range))
+        .await?;
+    let mut head = Vec::new();
+
+    while let Some(chunk) = stream.next().await {
+        head.extend_from_slice(&chunk?);
+    }
+
+    Ok(head)
+}
+
+/// Get metadata of uploaded image: type, size, dimensions and owner
+///
+/// Path:
+/// GET: /api/image/info/{filename}
+#[get("/info/{filename}")]
+async fn get_image_info(
+    _: JwtCred,
+    path: web::Path<String>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_image_info";
+
+    let filename = path.into_inner();
+
+    log::info!("{}: attempting to get metadata of image: {}", op, filename);
+
+    match find_image_by_filename(&filename, &app_data.pool).await {
+        Ok(image) => HttpResponse::Ok().json(image),
         Err(err) => {
-            log::error!(
-                "{}: can not open the image from path: {}, error: {}",
-                op,
-                filepath,
-                err
-            );
-
-            HttpResponse::NotFound().finish()
+            log::warn!("{}: image: {} was not found, error: {}", op, filename, err);
+
+            HttpResponse::NotFound().json(ErrorResponse {
+                message: "image not found".to_string(),
+            })
         }
     }
 }
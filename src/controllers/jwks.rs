@@ -0,0 +1,24 @@
+use actix_web::{get, web, HttpResponse, Responder};
+
+use crate::AppState;
+
+/// Get public keys for verifying tokens issued by this server
+///
+/// Path:
+/// **/.well-known/jwks.json**
+#[get("/.well-known/jwks.json")]
+async fn jwks(app_data: web::Data<AppState>) -> impl Responder {
+    let op = "jwks";
+
+    let jwks = app_data.jwt.jwks();
+
+    log::info!(
+        "{}: public keys are returned, count: {}",
+        op,
+        jwks.keys.len()
+    );
+
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(jwks)
+}
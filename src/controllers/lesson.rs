@@ -1,13 +1,8 @@
-use std::env;
-
-
-use tokio::{
-    fs::{create_dir_all, File},
-    io::{AsyncReadExt, AsyncWriteExt},
-};
+use std::io::ErrorKind;
 
+use actix_multipart::Multipart;
 use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
-use dotenvy::dotenv;
+use futures_util::StreamExt;
 use serde::Deserialize;
 use uuid::Uuid;
 use validator::Validate;
@@ -16,11 +11,22 @@ use crate::{
     extractors::jwt_cred::JwtCred,
     models::{
         common::ErrorResponse,
-        lesson::{CreateLesson, UpdateLesson},
+        lesson::{
+            CreateLesson, CreateModule, Lesson, LessonOut, LessonRevision, PreviewLesson,
+            ReorderLessons, TextFormat, UpdateLesson, UpdateModule,
+        },
     },
     services::{
-        course::{find_course_by_id, user_is_owner},
-        lesson::*,
+        course::find_course_by_id, image::save_image, lesson::*, vocabulary::find_words_in_lesson,
+    },
+    utils::{
+        access::{
+            can_edit_course, can_edit_lesson, can_read_lesson, can_study_course, can_view_course,
+        },
+        diff::diff_lines,
+        import::{import_document, ImportFormat, MAX_IMPORT_SIZE},
+        markdown::render_markdown,
+        storage::BlobKind,
     },
     AppState,
 };
@@ -29,12 +35,24 @@ pub fn lesson_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/lesson")
             .service(create_lesson)
+            .service(import_lessons)
             .service(get_lessons)
             .service(get_lesson)
             .service(update_lesson)
+            .service(set_preview)
             .service(upload_lesson_text)
             .service(get_lesson_text)
-            .service(delete_lesson),
+            .service(get_revisions)
+            .service(get_revision)
+            .service(publish_revision)
+            .service(restore_revision)
+            .service(get_revisions_diff)
+            .service(delete_lesson)
+            .service(reorder)
+            .service(create_module)
+            .service(get_modules)
+            .service(update_module)
+            .service(delete_module),
     );
 }
 
@@ -75,10 +93,7 @@ pub async fn create_lesson(
         });
     }
 
-    if !user_is_owner(user_id, lesson.course_id, &app_data.pool)
-        .await
-        .unwrap_or(false)
-    {
+    if !can_edit_course(&creds, lesson.course_id, &app_data.pool).await {
         log::warn!(
             "{}: user by id: {} is not owner of course id: {}",
             op,
@@ -91,6 +106,24 @@ pub async fn create_lesson(
         });
     }
 
+    if let Some(module_id) = lesson.module_id {
+        match find_module_by_id(module_id, &app_data.pool).await {
+            Ok(module) if module.course_id == lesson.course_id => {}
+            _ => {
+                log::warn!(
+                    "{}: module by id: {} not found in course: {}",
+                    op,
+                    module_id,
+                    lesson.course_id
+                );
+
+                return HttpResponse::NotFound().json(ErrorResponse {
+                    message: "module not found".to_string(),
+                });
+            }
+        }
+    }
+
     let lesson_filename = Uuid::new_v4().to_string();
 
     let new_course_id = match create_lesson_db(
@@ -124,20 +157,192 @@ pub async fn create_lesson(
     HttpResponse::Created().json(new_course_id)
 }
 
+/// Import lessons into the end of course from uploaded document
+///
+/// EPUB is split by chapters, zip of markdown files gives lesson for every file
+/// and HTML document is one lesson. Images are moved into image store,
+/// text of lessons is saved as drafts. Returns ids of created lessons in order
+///
+/// Path:
+/// **/api/lesson/import/*{course_id}***
+#[post("/import/{course_id}")]
+pub async fn import_lessons(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    mut payload: Multipart,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "import_lessons";
+
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to import lessons into course: {}, user_id: {}",
+        op,
+        course_id,
+        creds.uid
+    );
+
+    if let Err(err) = find_course_by_id(course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: course by id: {} not found, error: {}",
+            op,
+            course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course not found".to_string(),
+        });
+    }
+
+    if !can_edit_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {} is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id,
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    let mut field = match payload.next().await {
+        Some(Ok(field)) => field,
+        _ => {
+            log::error!("{}: lost file in request", op);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: "Failed to process the request".to_string(),
+            });
+        }
+    };
+
+    let filename = field
+        .content_disposition()
+        .get_filename()
+        .unwrap_or_default()
+        .to_string();
+
+    let Some(format) = ImportFormat::from_filename(&filename) else {
+        log::error!("{}: unsupported file format, filename: {}", op, filename);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "invalid file format".to_string(),
+        });
+    };
+
+    let mut data = Vec::new();
+
+    while let Some(chunk) = field.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                log::error!("{}: failed to read the chunk, error: {}", op, err);
+
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    message: "Failed to process the request".to_string(),
+                });
+            }
+        };
+
+        if (data.len() + chunk.len()) as u64 > MAX_IMPORT_SIZE {
+            log::error!("{}: file is too large, filename: {}", op, filename);
+
+            return HttpResponse::PayloadTooLarge().json(ErrorResponse {
+                message: "file is too large".to_string(),
+            });
+        }
+
+        data.extend_from_slice(&chunk);
+    }
+
+    // parsing of documents is blocking
+    let document = match web::block(move || import_document(format, &filename, data)).await {
+        Ok(Ok(document)) => document,
+        Ok(Err(err)) => {
+            log::error!("{}: cannot read document, error: {}", op, err);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: "cannot read document".to_string(),
+            });
+        }
+        Err(err) => {
+            log::error!("{}: cannot import document, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot import document".to_string(),
+            });
+        }
+    };
+
+    if document.lessons.is_empty() {
+        log::warn!("{}: document has no lessons", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "document has no lessons".to_string(),
+        });
+    }
+
+    for image in document.images {
+        if let Err(err) = save_image(
+            &image.filename,
+            Some(image.original_name),
+            image.image,
+            creds.uid,
+            app_data.storage.as_ref(),
+            &app_data.pool,
+        )
+        .await
+        {
+            log::error!("{}: failed to save the image, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot import document".to_string(),
+            });
+        }
+    }
+
+    let lesson_ids =
+        match import_lessons_db(course_id, creds.uid, &document.lessons, &app_data.pool).await {
+            Ok(lesson_ids) => lesson_ids,
+            Err(err) => {
+                log::error!("{}: cannot create lessons, error: {}", op, err);
+
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    message: "cannot import document".to_string(),
+                });
+            }
+        };
+
+    log::info!(
+        "{}: {} lessons are imported into course: {}",
+        op,
+        lesson_ids.len(),
+        course_id
+    );
+
+    HttpResponse::Created().json(lesson_ids)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GetAllLessonsFilter {
     course: Option<i32>,
 }
 
-/// Get all lessons in database
+/// Get all lessons available for user: lessons of courses where user is member
+/// and preview lessons
 ///
 /// Path:
 /// **/api/lesson/all**
-/// Or get lesson in course by query
+/// Or get lesson in course by query, only preview lessons are returned
+/// if user is not member of the course
 /// **/api/lesson/all?course=*{course_id}***
 #[get("/all")]
 pub async fn get_lessons(
-    _: JwtCred,
+    creds: JwtCred,
     filter: web::Query<GetAllLessonsFilter>,
     app_data: web::Data<AppState>,
 ) -> impl Responder {
@@ -145,42 +350,8 @@ pub async fn get_lessons(
 
     log::info!("{}: attempting to get lessons, filter: {:?}", op, filter);
 
-    match filter.course {
-        Some(course_id) => match find_course_by_id(course_id, &app_data.pool).await {
-            Ok(_) => match find_lessons_in_course(course_id, &app_data.pool).await {
-                Ok(lessons) => {
-                    log::info!("{}: courses are successfuly returned", op);
-
-                    HttpResponse::Ok().json(lessons)
-                }
-                Err(err) => {
-                    log::error!(
-                        "{}: cannot get all lessons in course id: {}, error: {}",
-                        op,
-                        course_id,
-                        err
-                    );
-
-                    HttpResponse::InternalServerError().json(ErrorResponse {
-                        message: "".to_string(),
-                    })
-                }
-            },
-            Err(err) => {
-                log::error!(
-                    "{}: course by id: {} is not exist, error: {}",
-                    op,
-                    course_id,
-                    err
-                );
-
-                HttpResponse::NotFound().json(ErrorResponse {
-                    message: "course is not exist".to_string(),
-                })
-            }
-        },
-
-        None => match find_all_lessons(&app_data.pool).await {
+    let Some(course_id) = filter.course else {
+        return match find_available_lessons(creds.uid, &app_data.pool).await {
             Ok(lessons) => {
                 log::info!("{}: courses are successfuly returned", op);
 
@@ -193,17 +364,67 @@ pub async fn get_lessons(
                     message: "cannot get all lessons".to_string(),
                 })
             }
-        },
+        };
+    };
+
+    if let Err(err) = find_course_by_id(course_id, &app_data.pool).await {
+        log::error!(
+            "{}: course by id: {} is not exist, error: {}",
+            op,
+            course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course is not exist".to_string(),
+        });
+    }
+
+    let lessons = match find_lessons_in_course(course_id, &app_data.pool).await {
+        Ok(lessons) => lessons,
+        Err(err) => {
+            log::error!(
+                "{}: cannot get all lessons in course id: {}, error: {}",
+                op,
+                course_id,
+                err
+            );
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "".to_string(),
+            });
+        }
+    };
+
+    if can_study_course(&creds, course_id, &app_data.pool).await {
+        log::info!("{}: courses are successfuly returned", op);
+
+        return HttpResponse::Ok().json(lessons);
+    }
+
+    let lessons: Vec<Lesson> = lessons
+        .into_iter()
+        .filter(|lesson| lesson.preview)
+        .collect();
+
+    if lessons.is_empty() {
+        log::warn!("{}: course: {} is not available for user", op, course_id);
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course is not exist".to_string(),
+        });
     }
+
+    HttpResponse::Ok().json(lessons)
 }
 
-/// Get the lesson by id
+/// Get the lesson by id with previous and next lessons of course
 ///
 /// Path:
 /// **/api/lesson/get/*{id}***
 #[get("/get/{id}")]
 pub async fn get_lesson(
-    _: JwtCred,
+    creds: JwtCred,
     path: web::Path<i32>,
     app_data: web::Data<AppState>,
 ) -> impl Responder {
@@ -228,9 +449,28 @@ pub async fn get_lesson(
         }
     };
 
+    if !can_read_lesson(&creds, &lesson, &app_data.pool).await {
+        log::warn!("{}: lesson: {} is not available for user", op, lesson_id);
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "lesson by id is not exist".to_string(),
+        });
+    }
+
+    let (prev, next) = match find_lesson_neighbours(&lesson, &app_data.pool).await {
+        Ok(neighbours) => neighbours,
+        Err(err) => {
+            log::error!("{}: cannot get neighbours of lesson, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot get lesson".to_string(),
+            });
+        }
+    };
+
     log::info!("{}: lesson are getting, lesson: {:?}", op, lesson);
 
-    HttpResponse::Ok().json(lesson)
+    HttpResponse::Ok().json(LessonOut { lesson, prev, next })
 }
 
 /// Update lesson
@@ -279,10 +519,7 @@ pub async fn update_lesson(
         }
     };
 
-    if !user_is_owner(user_id, updating_lesson.course_id.unwrap(), &app_data.pool)
-        .await
-        .unwrap_or(false)
-    {
+    if !can_edit_lesson(&creds, &updating_lesson, &app_data.pool).await {
         log::warn!(
             "{}: user by id: {}, is not owner of course id: {:?}",
             op,
@@ -312,92 +549,92 @@ pub async fn update_lesson(
     HttpResponse::Ok().json(lesson_id)
 }
 
-/// Delete lesson by id from path
+/// Open lesson for users who are not members of course or close it
 ///
 /// Path:
-/// **/api/lesson/delete/*{id}***
-#[delete("/delete/{id}")]
-pub async fn delete_lesson(
+/// **/api/lesson/preview/*{id}***
+#[put("/preview/{id}")]
+pub async fn set_preview(
     creds: JwtCred,
     path: web::Path<i32>,
+    data: web::Json<PreviewLesson>,
     app_data: web::Data<AppState>,
 ) -> impl Responder {
-    let op = "delete_lesson";
+    let op = "set_preview";
 
     let lesson_id = path.into_inner();
-    let user_id = creds.uid;
 
     log::info!(
-        "{}: attempting to delete lesson, lesson id: {}, user_id: {}",
+        "{}: attempting to change preview of lesson: {}, user_id: {}, preview: {}",
         op,
         lesson_id,
-        user_id
+        creds.uid,
+        data.preview
     );
 
-    let deleting_lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
+    let lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
         Ok(lesson) => lesson,
         Err(err) => {
             log::error!(
-                "{}: lesson by id: {} was not found, error: {}",
+                "{}: lesson by id: {} is not exist, error: {}",
                 op,
                 lesson_id,
                 err,
             );
 
-            return HttpResponse::NotFound();
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson by id is not exist".to_string(),
+            });
         }
     };
 
-    let course_id = deleting_lesson.course_id.unwrap();
-    if !user_is_owner(user_id, course_id, &app_data.pool)
-        .await
-        .unwrap_or(false)
-    {
+    if !can_edit_lesson(&creds, &lesson, &app_data.pool).await {
         log::warn!(
-            "{}: user by id: {}, is not owner of course id: {}",
+            "{}: user by id: {}, is not owner of course id: {:?}",
             op,
-            user_id,
-            course_id
+            creds.uid,
+            lesson.course_id
         );
 
-        return HttpResponse::Forbidden();
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
     }
 
-    if let Err(err) = delete_lesson_db(lesson_id, &app_data.pool).await {
-        log::error!("{}: cannot delete lesson, error: {}", op, err);
+    if let Err(err) = set_lesson_preview(lesson_id, data.preview, &app_data.pool).await {
+        log::error!("{}: cannot change preview of lesson, error: {}", op, err);
 
-        return HttpResponse::InternalServerError();
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "cannot change preview of lesson".to_string(),
+        });
     }
 
-    // FIXME: also delte lesson file
-
-    HttpResponse::Ok()
+    HttpResponse::Ok().json(lesson_id)
 }
 
-/// Upload lesson text to server
+/// Delete lesson by id from path
 ///
 /// Path:
-/// **/api/lesson/upload/*{id}***
-#[post("/upload/{id}")]
-pub async fn upload_lesson_text(
+/// **/api/lesson/delete/*{id}***
+#[delete("/delete/{id}")]
+pub async fn delete_lesson(
     creds: JwtCred,
     path: web::Path<i32>,
-    lesson_text: String,
     app_data: web::Data<AppState>,
 ) -> impl Responder {
-    let op = "upload_lesson_text";
+    let op = "delete_lesson";
 
     let lesson_id = path.into_inner();
     let user_id = creds.uid;
 
     log::info!(
-        "{}: attempting to upload lesson text to lesson: {}, user id: {}",
+        "{}: attempting to delete lesson, lesson id: {}, user_id: {}",
         op,
         lesson_id,
         user_id
     );
 
-    let uploading_lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
+    let deleting_lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
         Ok(lesson) => lesson,
         Err(err) => {
             log::error!(
@@ -411,71 +648,53 @@ pub async fn upload_lesson_text(
         }
     };
 
-    if !user_is_owner(user_id, uploading_lesson.course_id.unwrap(), &app_data.pool)
-        .await
-        .unwrap_or(false)
-    {
+    if !can_edit_lesson(&creds, &deleting_lesson, &app_data.pool).await {
         log::warn!(
             "{}: user by id: {}, is not owner of course id: {:?}",
             op,
             user_id,
-            uploading_lesson.course_id
+            deleting_lesson.course_id
         );
 
         return HttpResponse::Forbidden();
     }
 
-    // create directory first
-    // FIXME: add it into app_data!
-    dotenv().ok();
-    let file_path = env::var("LESSONS_DIR").unwrap_or("./lessons".to_string());
-
-    create_dir_all(&file_path).await.unwrap();
-
-    // write text into file
-    let mut lesson_file =
-        match File::create(format!("{}/{}", &file_path, uploading_lesson.content_path)).await {
-            Ok(file) => file,
-            Err(err) => {
-                log::error!(
-                    "{}: cannot create file, file path: {}/{}, error: {}",
-                    op,
-                    file_path,
-                    uploading_lesson.content_path,
-                    err
-                );
+    if let Err(err) = delete_lesson_db(lesson_id, &app_data.pool).await {
+        log::error!("{}: cannot delete lesson, error: {}", op, err);
 
-                return HttpResponse::InternalServerError();
-            }
-        };
+        return HttpResponse::InternalServerError();
+    }
 
-    if let Err(err) = lesson_file.write_all(lesson_text.as_bytes()).await {
-        log::error!(
-            "{}: cannot write lesson into file, file path: {}, error: {}",
+    if let Err(err) = app_data
+        .storage
+        .delete(BlobKind::Lesson, &deleting_lesson.content_path)
+        .await
+    {
+        log::warn!(
+            "{}: cannot delete file: {} of lesson, error: {}",
             op,
-            uploading_lesson.content_path,
+            deleting_lesson.content_path,
             err
         );
-
-        return HttpResponse::InternalServerError();
-    };
-
-    log::info!("{}: lessons are writed into file successfuly", op);
+    }
 
     HttpResponse::Ok()
 }
 
-/// Get lesson text
+/// Save lesson text as new draft revision, returns id of revision
+///
+/// Students see the text only after revision is published
 ///
 /// Path:
-/// **/api/lesson/text/*{id}***
-#[get("text/{id}")]
-pub async fn get_lesson_text(
+/// **/api/lesson/upload/*{id}***
+#[post("/upload/{id}")]
+pub async fn upload_lesson_text(
     creds: JwtCred,
     path: web::Path<i32>,
+    lesson_text: String,
     app_data: web::Data<AppState>,
 ) -> impl Responder {
-    let op = "get_lesson_text";
+    let op = "upload_lesson_text";
 
     let lesson_id = path.into_inner();
     let user_id = creds.uid;
@@ -487,7 +706,7 @@ pub async fn get_lesson_text(
         user_id
     );
 
-    let lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
+    let uploading_lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
         Ok(lesson) => lesson,
         Err(err) => {
             log::error!(
@@ -503,39 +722,773 @@ pub async fn get_lesson_text(
         }
     };
 
-    // FIXME: add it into app_data!
-    dotenv().ok();
-    let file_path = env::var("LESSONS_DIR").unwrap_or("./uploads/lessons".to_string());
-
-    let mut file = match File::open(format!("{}/{}", &file_path, lesson.content_path)).await {
-        Ok(file) => file,
+    if !can_edit_lesson(&creds, &uploading_lesson, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {:?}",
+            op,
+            user_id,
+            uploading_lesson.course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    match create_revision_db(lesson_id, user_id, &lesson_text, &app_data.pool).await {
+        Ok(revision_id) => {
+            log::info!(
+                "{}: draft of lesson: {} is saved, revision id: {}",
+                op,
+                lesson_id,
+                revision_id
+            );
+
+            HttpResponse::Ok().json(revision_id)
+        }
+        Err(err) => {
+            log::error!("{}: cannot save revision, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot save lesson text".to_string(),
+            })
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetLessonTextFilter {
+    draft: Option<bool>,
+    format: Option<TextFormat>,
+}
+
+/// Get published lesson text
+///
+/// Path:
+/// **/api/lesson/text/*{id}***
+/// or the newest draft for authors of course
+/// **/api/lesson/text/*{id}*?draft=true**
+/// or sanitized HTML instead of markdown
+/// **/api/lesson/text/*{id}*?format=html**
+#[get("text/{id}")]
+pub async fn get_lesson_text(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    filter: web::Query<GetLessonTextFilter>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_lesson_text";
+
+    let lesson_id = path.into_inner();
+    let user_id = creds.uid;
+
+    log::info!(
+        "{}: attempting to get lesson text of lesson: {}, user id: {}",
+        op,
+        lesson_id,
+        user_id
+    );
+
+    let lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
+        Ok(lesson) => lesson,
         Err(err) => {
             log::error!(
-                "{}: cannot open the file, file path: {}/{}, error: {}",
+                "{}: lesson by id: {} was not found, error: {}",
                 op,
-                file_path,
-                lesson.content_path,
-                err
+                lesson_id,
+                err,
             );
 
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                message: "can not open the file".to_string(),
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson not found".to_string(),
+            });
+        }
+    };
+
+    let mut text = None;
+
+    if !can_read_lesson(&creds, &lesson, &app_data.pool).await {
+        log::warn!("{}: lesson: {} is not available for user", op, lesson_id);
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "lesson not found".to_string(),
+        });
+    }
+
+    if filter.draft.unwrap_or(false) {
+        if !can_edit_lesson(&creds, &lesson, &app_data.pool).await {
+            log::warn!(
+                "{}: user by id: {} can not see drafts of lesson: {}",
+                op,
+                user_id,
+                lesson_id
+            );
+
+            return HttpResponse::Forbidden().json(ErrorResponse {
+                message: "user is not owner of course".to_string(),
+            });
+        }
+
+        text = match find_latest_revision(lesson_id, &app_data.pool).await {
+            Ok(revision) => revision.map(|revision| revision.content),
+            Err(err) => {
+                log::error!("{}: cannot get draft, error: {}", op, err);
+
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    message: "cannot get draft".to_string(),
+                });
+            }
+        };
+    } else {
+        text = match find_published_revision(lesson_id, &app_data.pool).await {
+            Ok(revision) => revision.map(|revision| revision.content),
+            Err(err) => {
+                log::error!("{}: cannot get published text, error: {}", op, err);
+
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    message: "cannot get lesson text".to_string(),
+                });
+            }
+        };
+    }
+
+    // lesson text written before revisions is only in file
+    let text = match text {
+        Some(text) => text,
+        None => {
+            let data = match app_data
+                .storage
+                .get(BlobKind::Lesson, &lesson.content_path)
+                .await
+            {
+                Ok(data) => data,
+                Err(err) if err.kind() == ErrorKind::NotFound => {
+                    log::warn!("{}: lesson: {} is not published", op, lesson_id);
+
+                    return HttpResponse::NotFound().json(ErrorResponse {
+                        message: "lesson text is not published".to_string(),
+                    });
+                }
+                Err(err) => {
+                    log::error!(
+                        "{}: cannot open the file: {}, error: {}",
+                        op,
+                        lesson.content_path,
+                        err
+                    );
+
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        message: "can not open the file".to_string(),
+                    });
+                }
+            };
+
+            match String::from_utf8(data) {
+                Ok(text) => text,
+                Err(err) => {
+                    log::error!("{}: can not get data from file, error: {}", op, err);
+
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        message: "can not get data from file".to_string(),
+                    });
+                }
+            }
+        }
+    };
+
+    match filter.format.unwrap_or_default() {
+        TextFormat::Markdown => HttpResponse::Ok().json(text),
+        TextFormat::Html => {
+            let vocabulary = match find_words_in_lesson(lesson_id, &app_data.pool).await {
+                Ok(words) => words,
+                Err(err) => {
+                    log::error!("{}: cannot get vocabulary, error: {}", op, err);
+
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        message: "cannot render lesson text".to_string(),
+                    });
+                }
+            };
+
+            HttpResponse::Ok().json(render_markdown(&text, &vocabulary))
+        }
+    }
+}
+
+/// Get history of lesson text, newest first
+///
+/// Path:
+/// **/api/lesson/revisions/*{id}***
+#[get("/revisions/{id}")]
+pub async fn get_revisions(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_revisions";
+
+    let lesson_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to get revisions of lesson: {}, user id: {}",
+        op,
+        lesson_id,
+        creds.uid
+    );
+
+    let lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
+        Ok(lesson) => lesson,
+        Err(err) => {
+            log::error!(
+                "{}: lesson by id: {} was not found, error: {}",
+                op,
+                lesson_id,
+                err,
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson not found".to_string(),
             });
         }
     };
 
-    let mut buf = String::new();
+    if !can_edit_lesson(&creds, &lesson, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {} can not see revisions of lesson: {}",
+            op,
+            creds.uid,
+            lesson_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
 
-    match file.read_to_string(&mut buf).await {
-        Ok(_) => HttpResponse::Ok().json(buf),
+    match find_lesson_revisions(lesson_id, &app_data.pool).await {
+        Ok(revisions) => HttpResponse::Ok().json(revisions),
         Err(err) => {
-            log::error!("{}: can not get data from file, error: {}", op, err);
+            log::error!("{}: cannot get revisions, error: {}", op, err);
+
             HttpResponse::InternalServerError().json(ErrorResponse {
-                message: "can not get data from file".to_string(),
+                message: "cannot get revisions".to_string(),
             })
         }
     }
 }
 
-// TODO:
-// change order of lessons in course
+/// Find revision which can be edited by user
+async fn find_editable_revision(
+    creds: &JwtCred,
+    revision_id: i32,
+    app_data: &AppState,
+) -> Option<LessonRevision> {
+    let revision = find_revision_by_id(revision_id, &app_data.pool)
+        .await
+        .ok()?;
+    let lesson = find_lesson_by_id(revision.lesson_id, &app_data.pool)
+        .await
+        .ok()?;
+
+    if can_edit_lesson(creds, &lesson, &app_data.pool).await {
+        Some(revision)
+    } else {
+        None
+    }
+}
+
+/// Get revision of lesson with text
+///
+/// Path:
+/// **/api/lesson/revision/*{revision_id}***
+#[get("/revision/{revision_id}")]
+pub async fn get_revision(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_revision";
+
+    let revision_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to get revision: {}, user id: {}",
+        op,
+        revision_id,
+        creds.uid
+    );
+
+    match find_editable_revision(&creds, revision_id, &app_data).await {
+        Some(revision) => HttpResponse::Ok().json(revision),
+        None => {
+            log::warn!(
+                "{}: revision: {} is not available for user",
+                op,
+                revision_id
+            );
+
+            HttpResponse::NotFound().json(ErrorResponse {
+                message: "revision not found".to_string(),
+            })
+        }
+    }
+}
+
+/// Publish revision, its text is shown to students
+///
+/// Path:
+/// **/api/lesson/revision/*{revision_id}*/publish**
+#[post("/revision/{revision_id}/publish")]
+pub async fn publish_revision(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "publish_revision";
+
+    let revision_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to publish revision: {}, user id: {}",
+        op,
+        revision_id,
+        creds.uid
+    );
+
+    let revision = match find_editable_revision(&creds, revision_id, &app_data).await {
+        Some(revision) => revision,
+        None => {
+            log::warn!(
+                "{}: revision: {} is not available for user",
+                op,
+                revision_id
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "revision not found".to_string(),
+            });
+        }
+    };
+
+    // published text is read from revision, so concurrent publishing
+    // can not leave it different from published revision of lesson
+    if let Err(err) = publish_revision_db(&revision, &app_data.pool).await {
+        log::error!("{}: cannot publish revision, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "cannot publish revision".to_string(),
+        });
+    }
+
+    log::info!(
+        "{}: revision: {} of lesson: {} is published",
+        op,
+        revision.id,
+        revision.lesson_id
+    );
+
+    HttpResponse::Ok().json(revision.id)
+}
+
+/// Restore older revision, its text is saved as new draft
+///
+/// Path:
+/// **/api/lesson/revision/*{revision_id}*/restore**
+#[post("/revision/{revision_id}/restore")]
+pub async fn restore_revision(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "restore_revision";
+
+    let revision_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to restore revision: {}, user id: {}",
+        op,
+        revision_id,
+        creds.uid
+    );
+
+    let revision = match find_editable_revision(&creds, revision_id, &app_data).await {
+        Some(revision) => revision,
+        None => {
+            log::warn!(
+                "{}: revision: {} is not available for user",
+                op,
+                revision_id
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "revision not found".to_string(),
+            });
+        }
+    };
+
+    match create_revision_db(
+        revision.lesson_id,
+        creds.uid,
+        &revision.content,
+        &app_data.pool,
+    )
+    .await
+    {
+        Ok(new_revision_id) => {
+            log::info!(
+                "{}: revision: {} is restored as: {}",
+                op,
+                revision_id,
+                new_revision_id
+            );
+
+            HttpResponse::Created().json(new_revision_id)
+        }
+        Err(err) => {
+            log::error!("{}: cannot restore revision, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot restore revision".to_string(),
+            })
+        }
+    }
+}
+
+/// Get line diff between two revisions of the same lesson
+///
+/// Path:
+/// **/api/lesson/diff/*{from}*/*{to}***
+#[get("/diff/{from}/{to}")]
+pub async fn get_revisions_diff(
+    creds: JwtCred,
+    path: web::Path<(i32, i32)>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_revisions_diff";
+
+    let (from_id, to_id) = path.into_inner();
+
+    log::info!(
+        "{}: attempting to get diff between revisions: {} and {}, user id: {}",
+        op,
+        from_id,
+        to_id,
+        creds.uid
+    );
+
+    let from = find_editable_revision(&creds, from_id, &app_data).await;
+    let to = find_editable_revision(&creds, to_id, &app_data).await;
+
+    match (from, to) {
+        (Some(from), Some(to)) if from.lesson_id == to.lesson_id => {
+            HttpResponse::Ok().json(diff_lines(&from.content, &to.content))
+        }
+        _ => {
+            log::warn!(
+                "{}: revisions: {} and {} are not available for user",
+                op,
+                from_id,
+                to_id
+            );
+
+            HttpResponse::NotFound().json(ErrorResponse {
+                message: "revision not found".to_string(),
+            })
+        }
+    }
+}
+
+/// Change order of lessons and modules in course
+///
+/// Get JSON with all modules and all lessons of course in new order,
+/// lesson can be moved to another module
+///
+/// Path:
+/// **/api/lesson/order/*{course_id}***
+#[put("/order/{course_id}")]
+pub async fn reorder(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    order: web::Json<ReorderLessons>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "reorder_lessons";
+
+    let course_id = path.into_inner();
+    let user_id = creds.uid;
+
+    log::info!(
+        "{}: attempting to change order of lessons in course: {}, user_id: {}",
+        op,
+        course_id,
+        user_id
+    );
+
+    if let Err(err) = find_course_by_id(course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: course by id: {} not found, error: {}",
+            op,
+            course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course not found".to_string(),
+        });
+    }
+
+    if !can_edit_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            user_id,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    match reorder_lessons(course_id, &order, &app_data.pool).await {
+        Ok(true) => {
+            log::info!("{}: order of course: {} was changed", op, course_id);
+
+            HttpResponse::Ok().json(course_id)
+        }
+        Ok(false) => {
+            log::error!("{}: order does not match lessons of course", op);
+
+            HttpResponse::BadRequest().json(ErrorResponse {
+                message: "order must contain every module and lesson of course".to_string(),
+            })
+        }
+        Err(err) => {
+            log::error!("{}: cannot change order, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot change order".to_string(),
+            })
+        }
+    }
+}
+
+/// Create module in course, new module is placed at the end
+///
+/// Path:
+/// **/api/lesson/module/create**
+#[post("/module/create")]
+pub async fn create_module(
+    creds: JwtCred,
+    module: web::Json<CreateModule>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "create_module";
+
+    log::info!("{}: attempting to create module, module: {:?}", op, module);
+
+    if module.validate().is_err() {
+        log::error!("{}: data is not validated, data: {:?}", op, module);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("title field is empty"),
+        });
+    }
+
+    if let Err(err) = find_course_by_id(module.course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: course by id: {} not found, error: {}",
+            op,
+            module.course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course not found".to_string(),
+        });
+    }
+
+    if !can_edit_course(&creds, module.course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {} is not owner of course id: {}",
+            op,
+            creds.uid,
+            module.course_id,
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    match create_module_db(&module, &app_data.pool).await {
+        Ok(module_id) => {
+            log::info!("{}: module are successfuly created, id: {}", op, module_id);
+
+            HttpResponse::Created().json(module_id)
+        }
+        Err(err) => {
+            log::error!("{}: cannot create module, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: String::from("cannot create module"),
+            })
+        }
+    }
+}
+
+/// Get modules of course in order
+///
+/// Path:
+/// **/api/lesson/modules/*{course_id}***
+#[get("/modules/{course_id}")]
+pub async fn get_modules(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_modules";
+
+    let course_id = path.into_inner();
+
+    log::info!("{}: attempting to get modules of course: {}", op, course_id);
+
+    match find_course_by_id(course_id, &app_data.pool).await {
+        Ok(course) if can_view_course(&creds, &course, &app_data.pool).await => {}
+        _ => {
+            log::warn!("{}: course: {} is not available for user", op, course_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "course is not exist".to_string(),
+            });
+        }
+    }
+
+    match find_modules_in_course(course_id, &app_data.pool).await {
+        Ok(modules) => HttpResponse::Ok().json(modules),
+        Err(err) => {
+            log::error!("{}: cannot get modules, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot get modules".to_string(),
+            })
+        }
+    }
+}
+
+/// Update title of module
+///
+/// Path:
+/// **/api/lesson/module/update**
+#[put("/module/update")]
+pub async fn update_module(
+    creds: JwtCred,
+    new_module: web::Json<UpdateModule>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "update_module";
+
+    log::info!("{}: attempting to update module: {:?}", op, new_module);
+
+    if new_module.validate().is_err() {
+        log::error!("{}: data is not valid, data: {:?}", op, new_module);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "data is not valid".to_string(),
+        });
+    }
+
+    let module = match find_module_by_id(new_module.id, &app_data.pool).await {
+        Ok(module) => module,
+        Err(err) => {
+            log::error!(
+                "{}: module by id: {} is not exist, error: {}",
+                op,
+                new_module.id,
+                err
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "module by id is not exist".to_string(),
+            });
+        }
+    };
+
+    if !can_edit_course(&creds, module.course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            module.course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    if let Err(err) = update_module_db(&new_module, &app_data.pool).await {
+        log::error!("{}: cannot update module, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "cannot update module".to_string(),
+        });
+    }
+
+    HttpResponse::Ok().json(module.id)
+}
+
+/// Delete module, lessons of module are kept in course
+///
+/// Path:
+/// **/api/lesson/module/delete/*{id}***
+#[delete("/module/delete/{id}")]
+pub async fn delete_module(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "delete_module";
+
+    let module_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to delete module: {}, user_id: {}",
+        op,
+        module_id,
+        creds.uid
+    );
+
+    let module = match find_module_by_id(module_id, &app_data.pool).await {
+        Ok(module) => module,
+        Err(err) => {
+            log::error!(
+                "{}: module by id: {} was not found, error: {}",
+                op,
+                module_id,
+                err
+            );
+
+            return HttpResponse::NotFound();
+        }
+    };
+
+    if !can_edit_course(&creds, module.course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            module.course_id
+        );
+
+        return HttpResponse::Forbidden();
+    }
+
+    if let Err(err) = delete_module_db(module_id, &app_data.pool).await {
+        log::error!("{}: cannot delete module, error: {}", op, err);
+
+        return HttpResponse::InternalServerError();
+    }
+
+    HttpResponse::Ok()
+}
@@ -1,9 +1,16 @@
+pub mod admin;
 pub mod auth;
 pub mod book;
 pub mod card;
 pub mod course;
+pub mod exercise;
 pub mod group;
 pub mod image;
+pub mod jwks;
 pub mod language;
 pub mod lesson;
+pub mod oauth;
+pub mod progress;
 pub mod translator;
+pub mod two_factor;
+pub mod vocabulary;
@@ -0,0 +1,380 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use redis::Commands;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    models::{
+        auth::{OAuthAuthorizeOut, OAuthCallbackData},
+        common::ErrorResponse,
+        user::CreateUser,
+    },
+    services::{
+        identity::{create_identity, find_identity_user},
+        session::delete_user_sessions,
+        user::{
+            create_user, find_user_by_email, find_user_by_id, set_email_verified, update_password,
+        },
+    },
+    utils::oidc::{pkce_challenge, random_string, ExternalUser, AUTH_REQUEST_TTL_SEC},
+    AppState,
+};
+
+use super::{
+    auth::{deny_revoked_sessions, send_verification, start_session},
+    two_factor::two_factor_challenge,
+};
+
+pub fn oauth_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/oauth").service(authorize).service(callback));
+}
+
+/// Authorization request stored in redis until provider redirect user back
+#[derive(Debug, Deserialize, Serialize)]
+struct AuthRequest {
+    provider: String,
+    verifier: String,
+}
+
+fn auth_request_key(state: &str) -> String {
+    format!("oauth:state:{}", state)
+}
+
+/// Start OAuth login request
+///
+/// Return URL of provider login page, client should redirect user to it
+///
+/// Path:
+/// **/api/auth/oauth/{provider}/authorize**
+#[get("/{provider}/authorize")]
+pub async fn authorize(
+    provider_name: web::Path<String>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "oauth_authorize";
+    log::info!("{}: attempting to start login by: {}", op, provider_name);
+
+    let provider = match app_data.oidc.provider(&provider_name) {
+        Some(provider) => provider,
+        None => {
+            log::error!("{}: provider: {} is not configured", op, provider_name);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: String::from("provider not found"),
+            });
+        }
+    };
+
+    let state = random_string(32);
+    let verifier = random_string(64);
+
+    let url = match provider.authorize_url(&state, &pkce_challenge(&verifier)) {
+        Ok(url) => url,
+        Err(err) => {
+            log::error!("{}: invalid authorize url of provider, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let request = serde_json::to_string(&AuthRequest {
+        provider: provider.name.clone(),
+        verifier,
+    })
+    .unwrap();
+
+    let mut redis_conn = app_data.redis.lock().unwrap();
+
+    if let Err(err) =
+        redis_conn.set_ex::<_, _, ()>(auth_request_key(&state), request, AUTH_REQUEST_TTL_SEC)
+    {
+        log::error!("{}: can not save authorization request, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    log::info!("{}: authorization request was successfuly created", op);
+
+    HttpResponse::Ok().json(OAuthAuthorizeOut { url })
+}
+
+/// Finish OAuth login request
+///
+/// Exchange code from provider, link external identity to user
+/// or create new user and return pair of JWT or 2FA challenge
+///
+/// Path:
+/// **/api/auth/oauth/{provider}/callback**
+#[post("/{provider}/callback")]
+pub async fn callback(
+    req: HttpRequest,
+    provider_name: web::Path<String>,
+    data: web::Json<OAuthCallbackData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "oauth_callback";
+    log::info!("{}: attempting to finish login by: {}", op, provider_name);
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("invalid data"),
+        });
+    }
+
+    let provider = match app_data.oidc.provider(&provider_name) {
+        Some(provider) => provider,
+        None => {
+            log::error!("{}: provider: {} is not configured", op, provider_name);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: String::from("provider not found"),
+            });
+        }
+    };
+
+    // state is one time, remove it before exchange so it can not be replayed
+    let request = {
+        let mut redis_conn = app_data.redis.lock().unwrap();
+        let key = auth_request_key(&data.state);
+
+        let request: Option<String> = redis_conn.get(&key).unwrap_or(None);
+        let _: redis::RedisResult<()> = redis_conn.del(&key);
+
+        request.and_then(|request| serde_json::from_str::<AuthRequest>(&request).ok())
+    };
+
+    let request = match request {
+        Some(request) if request.provider == provider.name => request,
+        _ => {
+            log::error!("{}: unknown or expired state", op);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: String::from("invalid or expired state"),
+            });
+        }
+    };
+
+    // requests to provider are blocking, so they are sent from thread pool
+    let exchange = {
+        let provider = provider.clone();
+        let code = data.code.clone();
+
+        web::block(move || {
+            provider
+                .exchange_code(&code, &request.verifier)
+                .and_then(|access_token| provider.user_info(&access_token))
+                .map_err(|err| err.to_string())
+        })
+    };
+
+    let external = match exchange.await {
+        Ok(Ok(external)) => external,
+        Ok(Err(err)) => {
+            log::error!("{}: can not get user from provider, error: {}", op, err);
+
+            return HttpResponse::Unauthorized().json(ErrorResponse {
+                message: String::from("can not authorize by provider"),
+            });
+        }
+        Err(err) => {
+            log::error!("{}: can not run request to provider, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let (user_id, email) = match link_user(&provider.name, &external, &app_data).await {
+        Ok(user) => user,
+        Err(res) => return res,
+    };
+
+    match two_factor_challenge(user_id, &email, &app_data).await {
+        Ok(Some(challenge)) => {
+            log::info!("{}: user: {} has to pass 2FA", op, user_id);
+
+            return HttpResponse::Accepted().json(challenge);
+        }
+        Ok(None) => {}
+        Err(err) => {
+            log::error!("{}: can not check 2FA, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    let tokens = match start_session(&req, user_id, &email, &app_data).await {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            log::error!("{}: can not start session, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    log::info!(
+        "{}: user: {} successfuly logged in by: {}",
+        op,
+        user_id,
+        provider.name
+    );
+
+    HttpResponse::Ok().json(tokens)
+}
+
+/// Find user linked to external identity
+///
+/// Identity is linked to existing user with the same email only if provider verified email,
+/// otherwise new user is created. Password and sessions of unverified existing user are revoked
+async fn link_user(
+    provider: &str,
+    external: &ExternalUser,
+    app_data: &AppState,
+) -> Result<(i32, String), HttpResponse> {
+    let op = "oauth_link_user";
+
+    let linked = find_identity_user(provider, &external.subject, &app_data.pool)
+        .await
+        .map_err(|err| {
+            log::error!("{}: can not find identity, error: {}", op, err);
+
+            HttpResponse::InternalServerError().finish()
+        })?;
+
+    if let Some(user_id) = linked {
+        let user = find_user_by_id(user_id, &app_data.pool)
+            .await
+            .map_err(|err| {
+                log::error!("{}: can not find user: {}, error: {}", op, user_id, err);
+
+                HttpResponse::InternalServerError().finish()
+            })?;
+
+        return Ok((user.id, user.email));
+    }
+
+    let email = external.email.clone().ok_or_else(|| {
+        log::error!("{}: provider does not return email", op);
+
+        HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("email is not provided"),
+        })
+    })?;
+
+    let user_id = match find_user_by_email(&email, &app_data.pool).await {
+        Ok(user) => {
+            if !external.email_verified {
+                log::warn!(
+                    "{}: user with email exist, but email is not verified by provider",
+                    op
+                );
+
+                return Err(HttpResponse::Conflict().json(ErrorResponse {
+                    message: String::from("user already exist"),
+                }));
+            }
+
+            // account could be registered with this email by someone else before,
+            // so its password and sessions are dropped before owner of email gets it
+            if !user.email_verified {
+                reset_unverified_user(user.id, app_data).await?;
+            }
+
+            user.id
+        }
+        Err(_) => {
+            let username = external
+                .name
+                .clone()
+                .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+
+            // password is random, user can sign in only by provider
+            let password_hash = bcrypt::hash(random_string(32), bcrypt::DEFAULT_COST).unwrap();
+
+            let user_id = create_user(
+                &CreateUser {
+                    email: email.clone(),
+                    username,
+                    password_hash,
+                },
+                &app_data.pool,
+            )
+            .await
+            .map_err(|err| {
+                log::error!("{}: can not create user, error: {}", op, err);
+
+                HttpResponse::Conflict().json(ErrorResponse {
+                    message: String::from("user already exist"),
+                })
+            })?;
+
+            log::info!("{}: user was created, new user id: {}", op, user_id);
+
+            if !external.email_verified {
+                send_verification(user_id, &email, app_data).await;
+            }
+
+            user_id
+        }
+    };
+
+    if external.email_verified {
+        if let Err(err) = set_email_verified(user_id, &app_data.pool).await {
+            log::error!("{}: can not mark email as verified, error: {}", op, err);
+        }
+    }
+
+    create_identity(
+        provider,
+        &external.subject,
+        external.email.as_deref(),
+        user_id,
+        &app_data.pool,
+    )
+    .await
+    .map_err(|err| {
+        log::error!("{}: can not link identity, error: {}", op, err);
+
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    log::info!(
+        "{}: identity of {} linked to user: {}",
+        op,
+        provider,
+        user_id
+    );
+
+    Ok((user_id, email))
+}
+
+/// Replace password of user with random one and revoke all sessions of user
+async fn reset_unverified_user(user_id: i32, app_data: &AppState) -> Result<(), HttpResponse> {
+    let op = "oauth_reset_unverified_user";
+
+    let password_hash = bcrypt::hash(random_string(32), bcrypt::DEFAULT_COST).unwrap();
+
+    if let Err(err) = update_password(user_id, &password_hash, &app_data.pool).await {
+        log::error!("{}: can not reset password, error: {}", op, err);
+
+        return Err(HttpResponse::InternalServerError().finish());
+    }
+
+    match delete_user_sessions(user_id, &app_data.pool).await {
+        Ok(session_ids) => deny_revoked_sessions(app_data, &session_ids),
+        Err(err) => {
+            log::error!("{}: can not revoke sessions, error: {}", op, err);
+
+            return Err(HttpResponse::InternalServerError().finish());
+        }
+    }
+
+    log::warn!(
+        "{}: password and sessions of unverified user: {} are revoked",
+        op,
+        user_id
+    );
+
+    Ok(())
+}
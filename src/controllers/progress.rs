@@ -0,0 +1,206 @@
+use actix_web::{get, put, web, HttpResponse, Responder};
+use validator::Validate;
+
+use crate::{
+    extractors::jwt_cred::JwtCred,
+    models::{common::ErrorResponse, progress::UpdateLessonProgress},
+    services::{
+        course::find_course_by_id,
+        lesson::find_lesson_by_id,
+        progress::{find_course_dashboard, find_course_progress, upsert_lesson_progress},
+    },
+    utils::access::{can_manage_course, can_read_lesson, can_study_course},
+    AppState,
+};
+
+pub fn progress_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/progress")
+            .service(update_lesson_progress)
+            .service(get_course_progress)
+            .service(get_course_dashboard),
+    );
+}
+
+/// Save progress of user in lesson: completion, time spent and last position
+///
+/// Path:
+/// **/api/progress/lesson/*{id}***
+#[put("/lesson/{id}")]
+pub async fn update_lesson_progress(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    data: web::Json<UpdateLessonProgress>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "update_lesson_progress";
+
+    let lesson_id = path.into_inner();
+    let user_id = creds.uid;
+
+    log::info!(
+        "{}: attempting to update progress of user: {} in lesson: {}",
+        op,
+        user_id,
+        lesson_id
+    );
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated, data: {:?}", op, data);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "invalid data".to_string(),
+        });
+    }
+
+    let lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
+        Ok(lesson) => lesson,
+        Err(err) => {
+            log::error!(
+                "{}: lesson by id: {} is not exist, error: {}",
+                op,
+                lesson_id,
+                err
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson not found".to_string(),
+            });
+        }
+    };
+
+    let course_id = match lesson.course_id {
+        Some(course_id) if can_read_lesson(&creds, &lesson, &app_data.pool).await => course_id,
+        _ => {
+            log::warn!("{}: lesson: {} is not available for user", op, lesson_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson not found".to_string(),
+            });
+        }
+    };
+
+    match upsert_lesson_progress(user_id, lesson_id, &data, &app_data.pool).await {
+        Ok(progress) => {
+            log::info!(
+                "{}: progress of user: {} in course: {} was updated",
+                op,
+                user_id,
+                course_id
+            );
+
+            HttpResponse::Ok().json(progress)
+        }
+        Err(err) => {
+            log::error!("{}: cannot update progress, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot update progress".to_string(),
+            })
+        }
+    }
+}
+
+/// Get progress of user in course: percent of completed lessons and next lesson
+///
+/// Path:
+/// **/api/progress/course/*{id}***
+#[get("/course/{id}")]
+pub async fn get_course_progress(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_course_progress";
+
+    let course_id = path.into_inner();
+    let user_id = creds.uid;
+
+    log::info!(
+        "{}: attempting to get progress of user: {} in course: {}",
+        op,
+        user_id,
+        course_id
+    );
+
+    match find_course_by_id(course_id, &app_data.pool).await {
+        Ok(_) if can_study_course(&creds, course_id, &app_data.pool).await => {}
+        _ => {
+            log::warn!("{}: course: {} is not available for user", op, course_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "course by id is not exist".to_string(),
+            });
+        }
+    }
+
+    match find_course_progress(user_id, course_id, &app_data.pool).await {
+        Ok(progress) => HttpResponse::Ok().json(progress),
+        Err(err) => {
+            log::error!("{}: cannot get progress, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot get progress".to_string(),
+            })
+        }
+    }
+}
+
+/// Get progress of all students of course, available for owner
+///
+/// Path:
+/// **/api/progress/course/*{id}*/dashboard**
+#[get("/course/{id}/dashboard")]
+pub async fn get_course_dashboard(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_course_dashboard";
+
+    let course_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to get dashboard of course: {} by user: {}",
+        op,
+        course_id,
+        creds.uid
+    );
+
+    if let Err(err) = find_course_by_id(course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: course by id: {} was not found, error: {}",
+            op,
+            course_id,
+            err
+        );
+
+        return HttpResponse::NotFound().json(ErrorResponse {
+            message: "course by id not founded".to_string(),
+        });
+    }
+
+    if !can_manage_course(&creds, course_id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {}, is not owner of course id: {}",
+            op,
+            creds.uid,
+            course_id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    match find_course_dashboard(course_id, &app_data.pool).await {
+        Ok(dashboard) => HttpResponse::Ok().json(dashboard),
+        Err(err) => {
+            log::error!("{}: cannot get dashboard, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot get dashboard".to_string(),
+            })
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use std::error::Error;
+
+use actix_web::{delete, post, web, HttpRequest, HttpResponse, Responder};
+use jwt_simple::prelude::Duration;
+use validator::Validate;
+
+use crate::{
+    extractors::jwt_cred::JwtCred,
+    middlewares::rate_limit::too_many_requests,
+    models::{
+        common::ErrorResponse,
+        two_factor::{
+            RecoveryCodesOut, TotpCodeData, TotpEnrollOut, TwoFactorChallenge, TwoFactorVerifyData,
+        },
+    },
+    services::two_factor::{
+        delete_totp, enable_totp, find_totp, is_totp_enabled, replace_recovery_codes,
+        update_totp_step, upsert_totp_secret, use_recovery_code,
+    },
+    utils::{
+        denylist::{deny_token, is_denied},
+        jwt::scopes,
+        rate_limit::{account_lockout, register_challenge_failure},
+        totp::{
+            check_code, generate_recovery_codes, generate_secret, otpauth_url,
+            CHALLENGE_DURATION_MIN, CHALLENGE_MAX_ATTEMPTS,
+        },
+    },
+    AppState,
+};
+
+use super::auth::{register_failed_signin, start_session};
+
+pub fn two_factor_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/2fa")
+            .service(enroll)
+            .service(confirm)
+            .service(disable)
+            .service(verify_challenge),
+    );
+}
+
+/// Start TOTP enrollment request
+///
+/// Generate secret for authenticator app, 2FA is enabled only after confirmation
+///
+/// Path:
+/// **/api/auth/2fa/enroll**
+#[post("/enroll")]
+pub async fn enroll(creds: JwtCred, app_data: web::Data<AppState>) -> impl Responder {
+    let op = "enroll_totp";
+    log::info!("{}: attempting to enroll 2FA for user: {}", op, creds.uid);
+
+    match is_totp_enabled(creds.uid, &app_data.pool).await {
+        Ok(true) => {
+            log::warn!("{}: 2FA already enabled for user: {}", op, creds.uid);
+
+            return HttpResponse::Conflict().json(ErrorResponse {
+                message: String::from("2FA already enabled"),
+            });
+        }
+        Ok(false) => {}
+        Err(err) => {
+            log::error!("{}: can not get 2FA of user, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    let secret = generate_secret();
+
+    let url = match otpauth_url(&secret, &creds.email) {
+        Ok(url) => url,
+        Err(err) => {
+            log::error!("{}: can not build otpauth url, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    if let Err(err) = upsert_totp_secret(creds.uid, &secret, &app_data.pool).await {
+        log::error!("{}: can not save secret, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    log::info!("{}: secret was generated for user: {}", op, creds.uid);
+
+    HttpResponse::Ok().json(TotpEnrollOut {
+        secret,
+        otpauth_url: url,
+    })
+}
+
+/// Confirm TOTP enrollment request
+///
+/// Enable 2FA if code from authenticator app is valid and return recovery codes
+///
+/// Path:
+/// **/api/auth/2fa/confirm**
+#[post("/confirm")]
+pub async fn confirm(
+    creds: JwtCred,
+    data: web::Json<TotpCodeData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "confirm_totp";
+    log::info!("{}: attempting to confirm 2FA for user: {}", op, creds.uid);
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("invalid data"),
+        });
+    }
+
+    let totp = match find_totp(creds.uid, &app_data.pool).await {
+        Ok(Some(totp)) if !totp.enabled => totp,
+        Ok(_) => {
+            log::error!("{}: no pending enrollment for user: {}", op, creds.uid);
+
+            return HttpResponse::Conflict().json(ErrorResponse {
+                message: String::from("2FA enrollment is not started"),
+            });
+        }
+        Err(err) => {
+            log::error!("{}: can not get 2FA of user, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let step = match check_code(&totp.secret, &creds.email, &data.code, None) {
+        Ok(Some(step)) => step,
+        Ok(None) => {
+            log::error!("{}: invalid code", op);
+
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                message: String::from("invalid code"),
+            });
+        }
+        Err(err) => {
+            log::error!("{}: can not check code, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let recovery_codes = generate_recovery_codes();
+
+    if let Err(err) = replace_recovery_codes(creds.uid, &recovery_codes, &app_data.pool).await {
+        log::error!("{}: can not save recovery codes, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    if let Err(err) = enable_totp(creds.uid, step, &app_data.pool).await {
+        log::error!("{}: can not enable 2FA, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    log::info!("{}: 2FA was enabled for user: {}", op, creds.uid);
+
+    HttpResponse::Ok().json(RecoveryCodesOut { recovery_codes })
+}
+
+/// Disable 2FA request
+///
+/// Valid TOTP code or recovery code is required
+///
+/// Path:
+/// **/api/auth/2fa**
+#[delete("")]
+pub async fn disable(
+    creds: JwtCred,
+    data: web::Json<TotpCodeData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "disable_totp";
+    log::info!("{}: attempting to disable 2FA for user: {}", op, creds.uid);
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("invalid data"),
+        });
+    }
+
+    match check_second_factor(creds.uid, &creds.email, &data.code, &app_data).await {
+        Ok(true) => {}
+        Ok(false) => {
+            log::error!("{}: invalid code", op);
+
+            return HttpResponse::Forbidden().json(ErrorResponse {
+                message: String::from("invalid code"),
+            });
+        }
+        Err(err) => {
+            log::error!("{}: can not check code, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    if let Err(err) = delete_totp(creds.uid, &app_data.pool).await {
+        log::error!("{}: can not disable 2FA, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    log::info!("{}: 2FA was disabled for user: {}", op, creds.uid);
+
+    HttpResponse::Ok().finish()
+}
+
+/// Second step of sign in request
+///
+/// Exchange challenge from sign in and TOTP code or recovery code to pair of JWT
+///
+/// Path:
+/// **/api/auth/2fa/verify**
+#[post("/verify")]
+pub async fn verify_challenge(
+    req: HttpRequest,
+    data: web::Json<TwoFactorVerifyData>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "verify_challenge";
+    log::info!("{}: attempting to verify 2FA challenge", op);
+
+    if data.validate().is_err() {
+        log::error!("{}: data is not validated", op);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("invalid data"),
+        });
+    }
+
+    let claims = match app_data.jwt.get_claims(&data.challenge, scopes::TWO_FACTOR) {
+        Some(claims) => claims,
+        None => {
+            log::error!("{}: invalid challenge", op);
+
+            return HttpResponse::Unauthorized().json(ErrorResponse {
+                message: String::from("invalid or expired challenge"),
+            });
+        }
+    };
+
+    {
+        let mut redis_conn = app_data.redis.lock().unwrap();
+
+        if is_denied(&mut redis_conn, &claims).unwrap_or(true) {
+            log::error!("{}: challenge was already used", op);
+
+            return HttpResponse::Unauthorized().json(ErrorResponse {
+                message: String::from("invalid or expired challenge"),
+            });
+        }
+    }
+
+    let locked = {
+        let mut redis_conn = app_data.redis.lock().unwrap();
+        account_lockout(&mut redis_conn, &claims.email)
+    };
+
+    match locked {
+        Ok(Some(retry_after)) => {
+            log::warn!("{}: account: {} is locked", op, claims.email);
+
+            return too_many_requests(retry_after);
+        }
+        Ok(None) => {}
+        Err(err) => log::error!("{}: can not check account lockout, error: {}", op, err),
+    }
+
+    match check_second_factor(claims.uid, &claims.email, &data.code, &app_data).await {
+        Ok(true) => {}
+        Ok(false) => {
+            log::error!("{}: invalid code for user: {}", op, claims.uid);
+
+            deny_guessed_challenge(&claims, &app_data);
+
+            if let Some(retry_after) = register_failed_signin(
+                &req,
+                &claims.email,
+                Some(claims.uid),
+                "invalid 2fa code",
+                &app_data,
+            )
+            .await
+            {
+                return too_many_requests(retry_after);
+            }
+
+            return HttpResponse::Forbidden().json(ErrorResponse {
+                message: String::from("invalid code"),
+            });
+        }
+        Err(err) => {
+            log::error!("{}: can not check code, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    {
+        let mut redis_conn = app_data.redis.lock().unwrap();
+
+        if let Err(err) = deny_token(&mut redis_conn, &claims) {
+            log::error!("{}: can not deny used challenge, error: {}", op, err);
+        }
+    }
+
+    let tokens = match start_session(&req, claims.uid, &claims.email, &app_data).await {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            log::error!("{}: can not start session, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    log::info!("{}: user: {} passed 2FA", op, claims.uid);
+
+    HttpResponse::Ok().json(tokens)
+}
+
+/// Count invalid code of challenge and deny challenge when too many codes were guessed
+fn deny_guessed_challenge(claims: &JwtCred, app_data: &AppState) {
+    let op = "deny_guessed_challenge";
+
+    let Some(jti) = &claims.jti else {
+        return;
+    };
+
+    let mut redis_conn = app_data.redis.lock().unwrap();
+
+    match register_challenge_failure(&mut redis_conn, jti, CHALLENGE_DURATION_MIN * 60) {
+        Ok(fails) if fails >= CHALLENGE_MAX_ATTEMPTS => {
+            log::warn!("{}: too many invalid codes, challenge is denied", op);
+
+            if let Err(err) = deny_token(&mut redis_conn, claims) {
+                log::error!("{}: can not deny challenge, error: {}", op, err);
+            }
+        }
+        Ok(_) => {}
+        Err(err) => log::error!("{}: can not count invalid code, error: {}", op, err),
+    }
+}
+
+/// Return challenge token if user enabled 2FA, sign in should be finished by `verify_challenge`
+pub(crate) async fn two_factor_challenge(
+    user_id: i32,
+    email: &str,
+    app_data: &AppState,
+) -> Result<Option<TwoFactorChallenge>, Box<dyn Error>> {
+    if !is_totp_enabled(user_id, &app_data.pool).await? {
+        return Ok(None);
+    }
+
+    let challenge = app_data.jwt.encode_token(
+        JwtCred {
+            uid: user_id,
+            email: email.to_string(),
+            scope: scopes::TWO_FACTOR.to_string(),
+            ..Default::default()
+        },
+        Duration::from_mins(CHALLENGE_DURATION_MIN),
+    )?;
+
+    Ok(Some(TwoFactorChallenge { challenge }))
+}
+
+/// Check TOTP code, recovery codes are accepted once
+async fn check_second_factor(
+    user_id: i32,
+    email: &str,
+    code: &str,
+    app_data: &AppState,
+) -> Result<bool, Box<dyn Error>> {
+    let totp = match find_totp(user_id, &app_data.pool).await? {
+        Some(totp) if totp.enabled => totp,
+        _ => return Ok(false),
+    };
+
+    if code.contains('-') {
+        return use_recovery_code(user_id, code, &app_data.pool).await;
+    }
+
+    // step is saved only if it was not used by parallel request with the same code
+    match check_code(&totp.secret, email, code, totp.last_used_step)? {
+        Some(step) => update_totp_step(user_id, step, &app_data.pool).await,
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,401 @@
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use validator::Validate;
+
+use crate::{
+    extractors::jwt_cred::JwtCred,
+    models::{
+        card::CreateGroup,
+        common::ErrorResponse,
+        vocabulary::{CreateLessonWord, UpdateLessonWord},
+    },
+    services::{
+        card::find_user_root_group, course::find_course_by_lesson, lesson::find_lesson_by_id,
+        vocabulary::*,
+    },
+    utils::access::{can_edit_course, can_read_lesson},
+    AppState,
+};
+
+pub fn vocabulary_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/vocabulary")
+            .service(create_word)
+            .service(get_words)
+            .service(update_word)
+            .service(delete_word)
+            .service(add_words_to_cards),
+    );
+}
+
+/// Add word to vocabulary of lesson
+///
+/// Path:
+/// **/api/vocabulary/create**
+#[post("/create")]
+pub async fn create_word(
+    creds: JwtCred,
+    word: web::Json<CreateLessonWord>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "create_word";
+
+    log::info!(
+        "{}: attempting to create word in lesson: {}, user_id: {}",
+        op,
+        word.lesson_id,
+        creds.uid
+    );
+
+    if word.validate().is_err() {
+        log::error!("{}: data is not validated, data: {:?}", op, word);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: String::from("invalid data"),
+        });
+    }
+
+    let course = match find_course_by_lesson(word.lesson_id, &app_data.pool).await {
+        Ok(course) => course,
+        Err(_) => {
+            log::warn!("{}: lesson by id: {} not found", op, word.lesson_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson not found".to_string(),
+            });
+        }
+    };
+
+    if !can_edit_course(&creds, course.id, &app_data.pool).await {
+        log::warn!(
+            "{}: user by id: {} is not owner of course id: {}",
+            op,
+            creds.uid,
+            course.id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    match create_word_db(&word, &app_data.pool).await {
+        Ok(word_id) => {
+            log::info!("{}: word are successfuly created, id: {}", op, word_id);
+
+            HttpResponse::Created().json(word_id)
+        }
+        Err(err) => {
+            log::error!("{}: cannot create word, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: String::from("cannot create word"),
+            })
+        }
+    }
+}
+
+/// Get vocabulary of lesson
+///
+/// Path:
+/// **/api/vocabulary/lesson/*{lesson_id}***
+#[get("/lesson/{lesson_id}")]
+pub async fn get_words(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "get_words";
+
+    let lesson_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to get vocabulary of lesson: {}",
+        op,
+        lesson_id
+    );
+
+    match find_lesson_by_id(lesson_id, &app_data.pool).await {
+        Ok(lesson) if can_read_lesson(&creds, &lesson, &app_data.pool).await => {}
+        _ => {
+            log::warn!("{}: lesson: {} is not available for user", op, lesson_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson not found".to_string(),
+            });
+        }
+    }
+
+    match find_words_in_lesson(lesson_id, &app_data.pool).await {
+        Ok(words) => HttpResponse::Ok().json(words),
+        Err(err) => {
+            log::error!("{}: cannot get vocabulary, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot get vocabulary".to_string(),
+            })
+        }
+    }
+}
+
+/// Update word, cards copied from it are updated too
+///
+/// Path:
+/// **/api/vocabulary/update**
+#[put("/update")]
+pub async fn update_word(
+    creds: JwtCred,
+    new_word: web::Json<UpdateLessonWord>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "update_word";
+
+    log::info!(
+        "{}: attempting to update word: {}, user_id: {}",
+        op,
+        new_word.id,
+        creds.uid
+    );
+
+    if new_word.validate().is_err() {
+        log::error!("{}: data is not valid, data: {:?}", op, new_word);
+
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            message: "data is not valid".to_string(),
+        });
+    }
+
+    let word = match find_word_by_id(new_word.id, &app_data.pool).await {
+        Ok(word) => word,
+        Err(err) => {
+            log::error!(
+                "{}: word by id: {} is not exist, error: {}",
+                op,
+                new_word.id,
+                err
+            );
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "word by id is not exist".to_string(),
+            });
+        }
+    };
+
+    let can_edit = match find_course_by_lesson(word.lesson_id, &app_data.pool).await {
+        Ok(course) => can_edit_course(&creds, course.id, &app_data.pool).await,
+        Err(_) => false,
+    };
+
+    if !can_edit {
+        log::warn!(
+            "{}: user by id: {} can not edit word: {}",
+            op,
+            creds.uid,
+            word.id
+        );
+
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            message: "user is not owner of course".to_string(),
+        });
+    }
+
+    if let Err(err) = update_word_db(&new_word, &app_data.pool).await {
+        log::error!("{}: cannot update word, error: {}", op, err);
+
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            message: "cannot update word".to_string(),
+        });
+    }
+
+    HttpResponse::Ok().json(word.id)
+}
+
+/// Delete word, cards copied from it are deleted too
+///
+/// Path:
+/// **/api/vocabulary/delete/*{id}***
+#[delete("/delete/{id}")]
+pub async fn delete_word(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "delete_word";
+
+    let word_id = path.into_inner();
+
+    log::info!(
+        "{}: attempting to delete word: {}, user_id: {}",
+        op,
+        word_id,
+        creds.uid
+    );
+
+    let word = match find_word_by_id(word_id, &app_data.pool).await {
+        Ok(word) => word,
+        Err(err) => {
+            log::error!(
+                "{}: word by id: {} was not found, error: {}",
+                op,
+                word_id,
+                err
+            );
+
+            return HttpResponse::NotFound();
+        }
+    };
+
+    let can_edit = match find_course_by_lesson(word.lesson_id, &app_data.pool).await {
+        Ok(course) => can_edit_course(&creds, course.id, &app_data.pool).await,
+        Err(_) => false,
+    };
+
+    if !can_edit {
+        log::warn!(
+            "{}: user by id: {} can not delete word: {}",
+            op,
+            creds.uid,
+            word_id
+        );
+
+        return HttpResponse::Forbidden();
+    }
+
+    if let Err(err) = delete_word_db(word_id, &app_data.pool).await {
+        log::error!("{}: cannot delete word, error: {}", op, err);
+
+        return HttpResponse::InternalServerError();
+    }
+
+    HttpResponse::Ok()
+}
+
+/// Copy vocabulary of lesson into cards of user, returns id of group with cards
+///
+/// Group is created in root group of user once, then it is kept in sync
+/// with vocabulary of lesson
+///
+/// Path:
+/// **/api/vocabulary/lesson/*{lesson_id}*/cards**
+#[post("/lesson/{lesson_id}/cards")]
+pub async fn add_words_to_cards(
+    creds: JwtCred,
+    path: web::Path<i32>,
+    app_data: web::Data<AppState>,
+) -> impl Responder {
+    let op = "add_words_to_cards";
+
+    let lesson_id = path.into_inner();
+    let user_id = creds.uid;
+
+    log::info!(
+        "{}: attempting to add vocabulary of lesson: {} to cards of user: {}",
+        op,
+        lesson_id,
+        user_id
+    );
+
+    match find_lesson_by_id(lesson_id, &app_data.pool).await {
+        Ok(lesson) if can_read_lesson(&creds, &lesson, &app_data.pool).await => {}
+        _ => {
+            log::warn!("{}: lesson: {} is not available for user", op, lesson_id);
+
+            return HttpResponse::NotFound().json(ErrorResponse {
+                message: "lesson not found".to_string(),
+            });
+        }
+    }
+
+    match find_vocabulary_group(lesson_id, user_id, &app_data.pool).await {
+        Ok(Some(group_id)) => {
+            log::info!(
+                "{}: vocabulary of lesson: {} is already in group: {}",
+                op,
+                lesson_id,
+                group_id
+            );
+
+            return HttpResponse::Ok().json(group_id);
+        }
+        Ok(None) => {}
+        Err(err) => {
+            log::error!("{}: cannot find group of vocabulary, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot add vocabulary to cards".to_string(),
+            });
+        }
+    }
+
+    let lesson = match find_lesson_by_id(lesson_id, &app_data.pool).await {
+        Ok(lesson) => lesson,
+        Err(err) => {
+            log::error!("{}: cannot get lesson, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot add vocabulary to cards".to_string(),
+            });
+        }
+    };
+
+    let root_id = match find_user_root_group(user_id, &app_data.pool).await {
+        Ok(root_id) => root_id,
+        Err(err) => {
+            log::error!("{}: cannot find root group of user, error: {}", op, err);
+
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot add vocabulary to cards".to_string(),
+            });
+        }
+    };
+
+    let group = CreateGroup {
+        title: lesson.title,
+        group_id: Some(root_id),
+    };
+
+    match create_vocabulary_group(lesson_id, user_id, &group, &app_data.pool).await {
+        Ok(Some(group_id)) => {
+            log::info!(
+                "{}: vocabulary of lesson: {} was copied into group: {}",
+                op,
+                lesson_id,
+                group_id
+            );
+
+            HttpResponse::Created().json(group_id)
+        }
+        Ok(None) => match find_vocabulary_group(lesson_id, user_id, &app_data.pool).await {
+            Ok(Some(group_id)) => {
+                log::info!(
+                    "{}: vocabulary of lesson: {} is already in group: {}",
+                    op,
+                    lesson_id,
+                    group_id
+                );
+
+                HttpResponse::Ok().json(group_id)
+            }
+            Ok(None) => {
+                log::error!("{}: group of vocabulary is not found after conflict", op);
+
+                HttpResponse::InternalServerError().json(ErrorResponse {
+                    message: "cannot add vocabulary to cards".to_string(),
+                })
+            }
+            Err(err) => {
+                log::error!("{}: cannot find group of vocabulary, error: {}", op, err);
+
+                HttpResponse::InternalServerError().json(ErrorResponse {
+                    message: "cannot add vocabulary to cards".to_string(),
+                })
+            }
+        },
+        Err(err) => {
+            log::error!("{}: cannot create group of vocabulary, error: {}", op, err);
+
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                message: "cannot add vocabulary to cards".to_string(),
+            })
+        }
+    }
+}
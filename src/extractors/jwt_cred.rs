@@ -1,17 +1,43 @@
-use actix_web::{dev::Payload, error, http::header, Error, FromRequest, HttpRequest};
-use futures_util::future::ready;
+use actix_web::{dev::Payload, error, http::header, web, Error, FromRequest, HttpRequest};
+use futures_util::future::{ready, FutureExt, LocalBoxFuture};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    get_key,
-    utils::jwt::{scopes, JwtUtil},
+    models::role::{Permission, Role},
+    utils::{denylist::is_denied, jwt::scopes},
+    AppState,
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct JwtCred {
     pub uid: i32,
     pub email: String,
     pub scope: String,
+    /// Id of session which token belongs to
+    #[serde(default)]
+    pub sid: Option<i32>,
+    /// Roles of user at the moment of token issue
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<Role>,
+    /// Permissions granted by roles
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub perms: Vec<Permission>,
+    /// Standard `jti` claim, set after token decoding
+    #[serde(skip)]
+    pub jti: Option<String>,
+    /// Standard `exp` claim in seconds, set after token decoding
+    #[serde(skip)]
+    pub expires_at: Option<u64>,
+}
+
+impl JwtCred {
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.contains(&role)
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.perms.contains(&permission)
+    }
 }
 
 #[derive(Debug)]
@@ -22,31 +48,70 @@ pub enum AuthError {
 
 impl FromRequest for JwtCred {
     type Error = Error;
-    type Future = futures_util::future::Ready<Result<Self, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
-        match get_token_from_req(req.clone()) {
-            Ok(token) => {
-                let jwt_controller = JwtUtil { key: get_key() };
-
-                match jwt_controller.get_claims(&token.as_str(), scopes::ACCESS) {
-                    Some(claims) => return ready(Ok(claims)),
-                    None => {
-                        return ready(Err(error::ErrorUnauthorized(
-                            "authorization header is missing",
-                        )))
-                    }
-                }
+        let app_data = match req.app_data::<web::Data<AppState>>() {
+            Some(app_data) => app_data.clone(),
+            None => {
+                log::error!("jwt_cred: app data is not configured");
+
+                return ready(Err(error::ErrorInternalServerError("app data is missing")))
+                    .boxed_local();
             }
-            Err(err) => match err {
-                AuthError::InvalidToken => {
-                    ready(Err(error::ErrorBadRequest("invalid token format")))
+        };
+
+        let claims = match get_token_from_req(req.clone()) {
+            Ok(token) => match app_data.jwt.get_claims(&token.as_str(), scopes::ACCESS) {
+                Some(claims) => claims,
+                None => {
+                    return ready(Err(error::ErrorUnauthorized(
+                        "authorization header is missing",
+                    )))
+                    .boxed_local()
                 }
-                AuthError::Unauthorized => ready(Err(error::ErrorUnauthorized(
-                    "authorization header is missing",
-                ))),
             },
+            Err(err) => {
+                let err = match err {
+                    AuthError::InvalidToken => error::ErrorBadRequest("invalid token format"),
+                    AuthError::Unauthorized => {
+                        error::ErrorUnauthorized("authorization header is missing")
+                    }
+                };
+
+                return ready(Err(err)).boxed_local();
+            }
+        };
+
+        // redis connection is blocking, so denylist is checked in thread pool
+        async move {
+            let denied = web::block(move || {
+                // connection is still usable after panic of other request
+                let mut redis_conn = app_data
+                    .redis
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+                is_denied(&mut redis_conn, &claims).map(|denied| (denied, claims))
+            })
+            .await;
+
+            match denied {
+                Ok(Ok((false, claims))) => Ok(claims),
+                Ok(Ok((true, _))) => Err(error::ErrorUnauthorized("token is revoked")),
+                Ok(Err(err)) => {
+                    log::error!("jwt_cred: can not check token denylist, error: {}", err);
+
+                    Err(error::ErrorInternalServerError("can not check token"))
+                }
+                Err(err) => {
+                    log::error!("jwt_cred: can not check token denylist, error: {}", err);
+
+                    Err(error::ErrorInternalServerError("can not check token"))
+                }
+            }
         }
+        .boxed_local()
     }
 }
 
@@ -1 +1,2 @@
 pub mod jwt_cred;
+pub mod permission;
@@ -0,0 +1,84 @@
+use std::{marker::PhantomData, ops::Deref};
+
+use actix_web::{dev::Payload, error, Error, FromRequest, HttpRequest};
+use futures_util::future::{FutureExt, LocalBoxFuture};
+
+use crate::models::role::Permission;
+
+use super::jwt_cred::JwtCred;
+
+/// Marker of permission required by handler
+pub trait RequiredPermission {
+    const PERMISSION: Permission;
+}
+
+/// Markers for `Authorized` extractor
+pub mod perms {
+    use super::{Permission, RequiredPermission};
+
+    pub struct CreateCourse;
+    pub struct PublishCourse;
+    pub struct ModerateCourse;
+    pub struct ManageRoles;
+
+    impl RequiredPermission for CreateCourse {
+        const PERMISSION: Permission = Permission::CreateCourse;
+    }
+
+    impl RequiredPermission for PublishCourse {
+        const PERMISSION: Permission = Permission::PublishCourse;
+    }
+
+    impl RequiredPermission for ModerateCourse {
+        const PERMISSION: Permission = Permission::ModerateCourse;
+    }
+
+    impl RequiredPermission for ManageRoles {
+        const PERMISSION: Permission = Permission::ManageRoles;
+    }
+}
+
+/// Credentials of user which has permission `P`, otherwise request is rejected with 403
+///
+/// Example: `creds: Authorized<perms::ManageRoles>`
+pub struct Authorized<P: RequiredPermission> {
+    pub creds: JwtCred,
+    permission: PhantomData<P>,
+}
+
+impl<P: RequiredPermission> Deref for Authorized<P> {
+    type Target = JwtCred;
+
+    fn deref(&self) -> &Self::Target {
+        &self.creds
+    }
+}
+
+impl<P: RequiredPermission + 'static> FromRequest for Authorized<P> {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let creds = JwtCred::from_request(req, payload);
+
+        async move {
+            let creds = creds.await?;
+
+            if !creds.has_permission(P::PERMISSION) {
+                log::warn!(
+                    "authorized: user: {} has no permission: {}",
+                    creds.uid,
+                    P::PERMISSION
+                );
+
+                return Err(error::ErrorForbidden("permission denied"));
+            }
+
+            Ok(Authorized {
+                creds,
+                permission: PhantomData,
+            })
+        }
+        .boxed_local()
+    }
+}
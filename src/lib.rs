@@ -1,28 +1,46 @@
 pub mod controllers;
 pub mod extractors;
+pub mod middlewares;
 pub mod models;
 pub mod services;
 pub mod utils;
 
 extern crate crypto;
 
-use std::{env, sync::Mutex};
+use std::{
+    env,
+    sync::{Arc, Mutex},
+};
 
 use actix_web::web;
 use controllers::{
-    auth::auth_config, book::book_config, card::card_config, course::course_config,
-    group::group_config, image::image_config, language::get_languages, lesson::lesson_config,
-    translator::trasnlator_config,
+    admin::admin_config, auth::auth_config, book::book_config, card::card_config,
+    course::course_config, exercise::exercise_config, group::group_config, image::image_config,
+    jwks::jwks, language::get_languages, lesson::lesson_config, progress::progress_config,
+    translator::trasnlator_config, vocabulary::vocabulary_config,
 };
 use dotenvy::dotenv;
-use jwt_simple::algorithms::HS256Key;
 use sqlx::{Pool, Postgres};
-use utils::jwt::JwtUtil;
+use utils::{
+    gc::GcConfig,
+    jwt::JwtUtil,
+    mailer::{mailer_from_env, Mailer},
+    oidc::OidcConfig,
+    policy::UnverifiedPolicy,
+    rate_limit::RateLimitConfig,
+    storage::{blob_store_from_env, BlobStore},
+};
 
 pub struct AppState {
     pub pool: Pool<Postgres>,
     pub jwt: JwtUtil,
     pub redis: Mutex<redis::Connection>,
+    pub mailer: Arc<dyn Mailer>,
+    pub unverified_policy: UnverifiedPolicy,
+    pub oidc: OidcConfig,
+    pub rate_limit: RateLimitConfig,
+    pub storage: Box<dyn BlobStore>,
+    pub gc: GcConfig,
 }
 
 pub async fn get_db_conn() -> Pool<Postgres> {
@@ -33,12 +51,6 @@ pub async fn get_db_conn() -> Pool<Postgres> {
         .expect("couldn't connected to database")
 }
 
-pub fn get_key() -> HS256Key {
-    dotenv().ok();
-    let key_srt = env::var("JWT_KEY").expect("JWT_KEY is not set in .env file");
-    HS256Key::from_bytes(key_srt.as_bytes())
-}
-
 pub fn get_redis_conn() -> redis::Connection {
     let redis_url = env::var("REDIS_URL").expect("REDIS_URL must be set");
 
@@ -54,8 +66,14 @@ pub fn get_redis_conn() -> redis::Connection {
 pub async fn get_app_data() -> web::Data<AppState> {
     web::Data::new(AppState {
         pool: get_db_conn().await,
-        jwt: JwtUtil { key: get_key() },
+        jwt: JwtUtil::from_env(),
         redis: Mutex::new(get_redis_conn()),
+        mailer: mailer_from_env(),
+        unverified_policy: UnverifiedPolicy::from_env(),
+        oidc: OidcConfig::from_env(),
+        rate_limit: RateLimitConfig::from_env(),
+        storage: blob_store_from_env(),
+        gc: GcConfig::from_env(),
     })
 }
 
@@ -65,11 +83,16 @@ pub fn main_config(cfg: &mut web::ServiceConfig) {
             .configure(auth_config)
             .configure(course_config)
             .configure(lesson_config)
+            .configure(progress_config)
+            .configure(exercise_config)
+            .configure(vocabulary_config)
             .configure(book_config)
             .configure(card_config)
             .configure(group_config)
             .configure(trasnlator_config)
             .configure(image_config)
+            .configure(admin_config)
             .service(get_languages),
-    );
+    )
+    .service(jwks);
 }
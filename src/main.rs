@@ -1,31 +1,76 @@
-use std::env;
+use std::{env, time::Duration};
 
 use actix_cors::Cors;
-use actix_web::{get, middleware::Logger, App, HttpResponse, HttpServer, Responder};
+use actix_web::{get, middleware::Logger, rt, web, App, HttpResponse, HttpServer, Responder};
 use dotenvy::dotenv;
-use rc_api::{get_app_data, main_config};
+use rc_api::{get_app_data, main_config, utils::gc::collect_garbage, AppState};
 
 #[get("/")]
 async fn index() -> impl Responder {
     HttpResponse::Ok().body("It's actix read craft api")
 }
 
+/// Remove unreferenced files once, `gc --dry-run` only lists them
+async fn run_gc(state: web::Data<AppState>, dry_run: bool) -> std::io::Result<()> {
+    let grace = Duration::from_secs(state.gc.grace_secs);
+
+    let report = collect_garbage(state.storage.as_ref(), &state.pool, grace, dry_run)
+        .await
+        .map_err(|err| std::io::Error::other(err.to_string()))?;
+
+    for (kind, key) in report.removed {
+        println!("{}/{}", kind.prefix(), key);
+    }
+
+    Ok(())
+}
+
+/// Periodically remove unreferenced files while server is running
+fn spawn_gc(state: web::Data<AppState>) {
+    if state.gc.interval_secs == 0 {
+        return;
+    }
+
+    rt::spawn(async move {
+        let grace = Duration::from_secs(state.gc.grace_secs);
+        let mut interval = rt::time::interval(Duration::from_secs(state.gc.interval_secs));
+
+        // first tick completes at once, files are not scanned on every start
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) =
+                collect_garbage(state.storage.as_ref(), &state.pool, grace, false).await
+            {
+                log::error!("storage gc: run failed, error: {}", err);
+            }
+        }
+    });
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+
+    let state = get_app_data().await;
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("gc") {
+        return run_gc(state, args.iter().any(|arg| arg == "--dry-run")).await;
+    }
+
     let host = env::var("HOST").expect("HOST is not set in .env file");
     let port = env::var("PORT").expect("PORT is not set in .env file");
     let addrs = format!("{}:{}", host, port);
 
-    let state = get_app_data().await;
-
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    spawn_gc(state.clone());
 
     log::info!("starting HTTP server at http://{}", addrs);
 
-    tokio::fs::create_dir_all("uploads/books").await.unwrap();
-
     HttpServer::new(move || {
         App::new()
             .wrap(Logger::new("%r, status: %s, time taken: %D"))
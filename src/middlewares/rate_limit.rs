@@ -0,0 +1,55 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+
+use crate::{
+    models::common::ErrorResponse,
+    utils::rate_limit::{check_ip, client_ip},
+    AppState,
+};
+
+/// Response for client which has to wait before next request
+pub fn too_many_requests(retry_after: u64) -> HttpResponse {
+    HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", retry_after.to_string()))
+        .json(ErrorResponse {
+            message: String::from("too many requests"),
+        })
+}
+
+/// Limit count of requests from one IP inside sliding window
+///
+/// Requests without known IP are not limited, if redis is unavailable requests are passed
+pub async fn ip_rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let op = "ip_rate_limit";
+
+    let app_data = req.app_data::<web::Data<AppState>>().cloned();
+    let ip = app_data
+        .as_ref()
+        .and_then(|app_data| client_ip(req.request(), &app_data.rate_limit));
+
+    if let (Some(ip), Some(app_data)) = (ip, app_data) {
+        let limited = {
+            let mut redis_conn = app_data.redis.lock().unwrap();
+            check_ip(&mut redis_conn, &app_data.rate_limit, &ip)
+        };
+
+        match limited {
+            Ok(Some(retry_after)) => {
+                log::warn!("{}: too many requests from ip: {}", op, ip);
+
+                return Ok(req.into_response(too_many_requests(retry_after)));
+            }
+            Ok(None) => {}
+            Err(err) => log::error!("{}: can not check rate limit, error: {}", op, err),
+        }
+    }
+
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
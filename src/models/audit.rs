@@ -0,0 +1,23 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Audit record of failed sign in attempt
+#[derive(Clone, Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct FailedLogin {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub email: String,
+    pub reason: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub user_id: Option<i32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CreateFailedLogin {
+    pub email: String,
+    pub reason: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub user_id: Option<i32>,
+}
@@ -33,3 +33,67 @@ pub struct SignUpData {
     #[validate(length(min = 6))]
     pub password: String,
 }
+
+/// JSON scheme for email verification
+#[derive(Serialize, Deserialize, Validate, Debug)]
+pub struct VerifyEmailData {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+/// JSON scheme for password change
+///
+/// New password min length equal 6
+#[derive(Serialize, Deserialize, Validate, Debug)]
+pub struct ChangePasswordData {
+    #[validate(length(min = 1))]
+    pub old_password: String,
+    #[validate(length(min = 6))]
+    pub new_password: String,
+}
+
+/// JSON scheme for account deletion, password is required for confirmation
+#[derive(Serialize, Deserialize, Validate, Debug)]
+pub struct DeleteAccountData {
+    #[validate(length(min = 1))]
+    pub password: String,
+}
+
+/// Public key in JWK format
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Jwk {
+    pub kty: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    pub kid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+/// JWKS document with all public keys
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+/// Login page of external provider for OAuth flow
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OAuthAuthorizeOut {
+    pub url: String,
+}
+
+/// JSON scheme for OAuth callback, `code` and `state` are from provider redirect
+#[derive(Serialize, Deserialize, Validate, Debug)]
+pub struct OAuthCallbackData {
+    #[validate(length(min = 1))]
+    pub code: String,
+    #[validate(length(min = 1))]
+    pub state: String,
+}
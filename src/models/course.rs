@@ -1,10 +1,12 @@
 use crate::services::course::user_is_owner;
 
 use super::language::Language;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Postgres};
 use std::error::Error;
+use strum::{Display, EnumString};
 use validator::Validate;
 
 #[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize, Validate)]
@@ -15,6 +17,11 @@ pub struct Course {
     #[validate(length(min = 1))]
     pub title: String,
     pub language: Language,
+    pub is_public: bool,
+    pub description: String,
+    pub level: Option<CourseLevel>,
+    pub tags: Vec<String>,
+    pub is_template: bool,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Validate)]
@@ -25,6 +32,13 @@ pub struct CourseOut {
     pub language: Language,
     #[serde(rename(serialize = "isOwner", deserialize = "isOwner"))]
     pub is_owner: bool,
+    #[serde(rename(serialize = "isPublic", deserialize = "isPublic"))]
+    pub is_public: bool,
+    pub description: String,
+    pub level: Option<CourseLevel>,
+    pub tags: Vec<String>,
+    #[serde(rename(serialize = "isTemplate", deserialize = "isTemplate"))]
+    pub is_template: bool,
 }
 
 impl CourseOut {
@@ -42,6 +56,11 @@ impl CourseOut {
             title: course.title.clone(),
             language: course.language,
             is_owner,
+            is_public: course.is_public,
+            description: course.description,
+            level: course.level,
+            tags: course.tags,
+            is_template: course.is_template,
         })
     }
 }
@@ -60,3 +79,170 @@ pub struct UpdateCourse {
     pub title: String,
     pub language: Language,
 }
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PublishCourse {
+    pub public: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TemplateCourse {
+    pub template: bool,
+}
+
+/// Copy course with lessons into new course of user, title of original is used by default
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct CloneCourse {
+    #[validate(length(min = 1))]
+    pub title: Option<String>,
+}
+
+/// Difficulty of course
+#[derive(Clone, Copy, Debug, sqlx::Type, Deserialize, Serialize, PartialEq, Eq)]
+#[sqlx(type_name = "course_level", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CourseLevel {
+    Beginner,
+    Intermediate,
+    Advanced,
+}
+
+/// Catalog info of course: description, level and tags
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct UpdateCourseDetails {
+    #[validate(length(max = 2000))]
+    #[serde(default)]
+    pub description: String,
+    pub level: Option<CourseLevel>,
+    #[validate(length(max = 10))]
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CatalogSort {
+    /// Newest courses first
+    #[default]
+    Recent,
+    /// Courses with more students first
+    Popular,
+}
+
+/// Query of catalog, tags are comma separated and course must have all of them
+#[derive(Debug, Default, Deserialize, Serialize, Validate)]
+pub struct CatalogQuery {
+    pub language: Option<Language>,
+    pub level: Option<CourseLevel>,
+    pub tags: Option<String>,
+    /// Full-text search by title and description
+    #[validate(length(min = 1, max = 200))]
+    pub q: Option<String>,
+    #[serde(default)]
+    pub sort: CatalogSort,
+    pub cursor: Option<String>,
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<i64>,
+}
+
+impl CatalogQuery {
+    pub fn tags(&self) -> Vec<String> {
+        self.tags
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(normalize_tag)
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+}
+
+/// Tags are case insensitive
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Position in catalog after the last returned course, position is valid only for its sort
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CatalogCursor {
+    pub sort: CatalogSort,
+    pub created_at: Option<NaiveDateTime>,
+    pub subscribers: Option<i64>,
+    pub id: i32,
+}
+
+impl CatalogCursor {
+    /// Check that cursor was returned for `sort` and has its key
+    pub fn matches(&self, sort: CatalogSort) -> bool {
+        self.sort == sort
+            && match sort {
+                CatalogSort::Recent => self.created_at.is_some(),
+                CatalogSort::Popular => self.subscribers.is_some(),
+            }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap())
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct CatalogCourse {
+    pub id: i32,
+    #[serde(rename(serialize = "createdAt", deserialize = "createdAt"))]
+    pub created_at: NaiveDateTime,
+    pub title: String,
+    pub description: String,
+    pub language: Language,
+    pub level: Option<CourseLevel>,
+    pub tags: Vec<String>,
+    /// Count of students of course
+    pub subscribers: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CatalogPage {
+    pub courses: Vec<CatalogCourse>,
+    #[serde(rename(serialize = "nextCursor", deserialize = "nextCursor"))]
+    pub next_cursor: Option<String>,
+}
+
+/// Role of user in course
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq, Deserialize, Serialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CourseRole {
+    Owner,
+    Editor,
+    Student,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CourseMember {
+    pub user_id: i32,
+    pub username: String,
+    pub email: String,
+    pub role: CourseRole,
+    pub joined_at: NaiveDateTime,
+}
+
+/// Add user to course by email or username
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct AddCourseMember {
+    #[validate(email)]
+    pub email: Option<String>,
+    #[validate(length(min = 1))]
+    pub username: Option<String>,
+    pub role: CourseRole,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TransferCourse {
+    pub user_id: i32,
+}
@@ -0,0 +1,113 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use validator::Validate;
+
+/// Type of exercise with right answers, it is visible only for authors of course
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExerciseContent {
+    /// Choose one or more right options, `answers` are indexes of right options
+    MultipleChoice {
+        options: Vec<String>,
+        answers: Vec<usize>,
+    },
+    /// Text with blanks marked by `___`, every blank has list of accepted answers
+    FillBlank {
+        text: String,
+        answers: Vec<Vec<String>>,
+    },
+    /// Translate sentence, any of `answers` is accepted
+    Translate {
+        sentence: String,
+        answers: Vec<String>,
+    },
+    /// Match items of two columns, `pairs` are right pairs
+    Matching { pairs: Vec<(String, String)> },
+}
+
+/// Exercise as it is shown to student, without answers
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExerciseTask {
+    MultipleChoice {
+        options: Vec<String>,
+        /// More than one option is right
+        multiple: bool,
+    },
+    FillBlank {
+        text: String,
+    },
+    Translate {
+        sentence: String,
+    },
+    /// Right column is shuffled
+    Matching {
+        left: Vec<String>,
+        right: Vec<String>,
+    },
+}
+
+/// Answer of student, kind must be the same as kind of exercise
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExerciseAnswer {
+    MultipleChoice { selected: Vec<usize> },
+    FillBlank { blanks: Vec<String> },
+    Translate { text: String },
+    Matching { pairs: Vec<(String, String)> },
+}
+
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct Exercise {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub position: i32,
+    pub prompt: String,
+    pub content: Json<ExerciseContent>,
+    pub lesson_id: i32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExerciseOut {
+    pub id: i32,
+    pub position: i32,
+    pub prompt: String,
+    pub task: ExerciseTask,
+    pub lesson_id: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct CreateExercise {
+    #[validate(length(min = 1))]
+    pub prompt: String,
+    pub content: ExerciseContent,
+    pub lesson_id: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct UpdateExercise {
+    pub id: i32,
+    #[validate(length(min = 1))]
+    pub prompt: String,
+    pub content: ExerciseContent,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SubmitAnswer {
+    pub answer: ExerciseAnswer,
+}
+
+/// Graded answer of student
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct ExerciseAttempt {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub answer: Json<ExerciseAnswer>,
+    /// Part of right answers from 0 to 1
+    pub score: f64,
+    pub correct: bool,
+    pub exercise_id: i32,
+    pub user_id: i32,
+}
@@ -0,0 +1,136 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Types of images which can be uploaded, type is detected by content of file
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageType {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    /// Sanitized SVG, it is saved without thumbnails
+    Svg,
+}
+
+impl ImageType {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageType::Jpeg => "image/jpeg",
+            ImageType::Png => "image/png",
+            ImageType::Gif => "image/gif",
+            ImageType::Webp => "image/webp",
+            ImageType::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// Uploaded image with its owner
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct Image {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub filename: String,
+    /// Name of file on computer of user
+    pub original_name: Option<String>,
+    /// Type of saved original
+    pub content_type: String,
+    /// Size of saved original in bytes
+    pub size: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub owner_id: Option<i32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CreateImage {
+    pub filename: String,
+    pub original_name: Option<String>,
+    pub content_type: String,
+    pub size: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+/// Size of stored image, thumbnails are fitted into square with side of `max_side`
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageSize {
+    Small,
+    Medium,
+    Large,
+    #[default]
+    Original,
+}
+
+impl ImageSize {
+    /// Sizes of thumbnails generated on upload
+    pub const THUMBNAILS: [ImageSize; 3] = [ImageSize::Small, ImageSize::Medium, ImageSize::Large];
+
+    /// Maximum width and height in pixels, original image is not resized
+    pub fn max_side(&self) -> Option<u32> {
+        match self {
+            ImageSize::Small => Some(160),
+            ImageSize::Medium => Some(480),
+            ImageSize::Large => Some(1280),
+            ImageSize::Original => None,
+        }
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            ImageSize::Small => "_small",
+            ImageSize::Medium => "_medium",
+            ImageSize::Large => "_large",
+            ImageSize::Original => "",
+        }
+    }
+}
+
+/// Encoding of stored image
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageEncoding {
+    /// Format of uploaded image, JPEG or PNG
+    #[default]
+    Original,
+    Webp,
+}
+
+/// Name of file with variant of uploaded image,
+/// original keeps name returned by upload, so old covers are still served
+pub fn variant_filename(filename: &str, size: ImageSize, encoding: ImageEncoding) -> String {
+    match encoding {
+        ImageEncoding::Original => format!("{}{}", filename, size.suffix()),
+        ImageEncoding::Webp => format!("{}{}.webp", filename, size.suffix()),
+    }
+}
+
+/// Name of original image for file of any its variant
+pub fn original_filename(key: &str) -> &str {
+    let key = key.strip_suffix(".webp").unwrap_or(key);
+
+    ImageSize::THUMBNAILS
+        .iter()
+        .find_map(|size| key.strip_suffix(size.suffix()))
+        .unwrap_or(key)
+}
+
+/// Variant of image which is saved on upload
+#[derive(Clone, Debug)]
+pub struct ImageVariant {
+    pub size: ImageSize,
+    pub encoding: ImageEncoding,
+    pub data: Vec<u8>,
+}
+
+/// Image which passed validation, variants are ready to be saved
+#[derive(Clone, Debug)]
+pub struct ProcessedImage {
+    /// Type of saved original
+    pub image_type: ImageType,
+    /// Dimensions of original, SVG has no dimensions
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub variants: Vec<ImageVariant>,
+}
@@ -0,0 +1,43 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct CourseInvite {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub name: Option<String>,
+    pub token: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_by: Option<i32>,
+    pub course_id: i32,
+}
+
+/// Invite link, every field limits usage of link
+#[derive(Debug, Default, Deserialize, Serialize, Validate)]
+pub struct CreateInvite {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    #[validate(range(min = 1))]
+    pub max_uses: Option<i32>,
+}
+
+/// User who joined course by invite link
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InviteMember {
+    pub user_id: i32,
+    pub username: String,
+    pub joined_at: NaiveDateTime,
+}
+
+/// Invite link with users joined by it
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InviteOut {
+    #[serde(flatten)]
+    pub invite: CourseInvite,
+    pub members: Vec<InviteMember>,
+}
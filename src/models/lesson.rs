@@ -12,6 +12,26 @@ pub struct Lesson {
     pub cover_path: Option<String>,
     pub subject: Option<String>,
     pub course_id: Option<i32>,
+    /// Position of lesson in course, lessons are read in this order
+    pub position: i32,
+    pub module_id: Option<i32>,
+    /// Lesson is open for users who are not members of course
+    pub preview: bool,
+}
+
+/// Lesson with links to previous and next lessons of course
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LessonOut {
+    #[serde(flatten)]
+    pub lesson: Lesson,
+    pub prev: Option<LessonLink>,
+    pub next: Option<LessonLink>,
+}
+
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct LessonLink {
+    pub id: i32,
+    pub title: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Validate)]
@@ -21,6 +41,12 @@ pub struct CreateLesson {
     pub cover_path: Option<String>,
     pub subject: Option<String>,
     pub course_id: i32,
+    pub module_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PreviewLesson {
+    pub preview: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Validate)]
@@ -31,3 +57,97 @@ pub struct UpdateLesson {
     pub cover_path: Option<String>,
     pub subject: Option<String>,
 }
+
+/// Section of course which groups lessons
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct CourseModule {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub title: String,
+    pub position: i32,
+    pub course_id: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct CreateModule {
+    #[validate(length(min = 1))]
+    pub title: String,
+    pub course_id: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct UpdateModule {
+    pub id: i32,
+    #[validate(length(min = 1))]
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LessonOrder {
+    pub id: i32,
+    pub module_id: Option<i32>,
+}
+
+/// New order of course, must contain every module and every lesson of course exactly once
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReorderLessons {
+    pub modules: Vec<i32>,
+    pub lessons: Vec<LessonOrder>,
+}
+
+/// Saved version of lesson text
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct LessonRevision {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub content: String,
+    pub author_id: Option<i32>,
+    pub lesson_id: i32,
+}
+
+/// Revision of lesson without text
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct RevisionInfo {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub author_id: Option<i32>,
+    pub lesson_id: i32,
+    /// Text of revision is shown to students
+    pub published: bool,
+    /// Revision is newer than published one
+    pub draft: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffTag {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// Line of diff between two revisions
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DiffLine {
+    pub tag: DiffTag,
+    pub text: String,
+}
+
+/// Format of lesson text in response
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextFormat {
+    #[default]
+    Markdown,
+    /// Sanitized HTML rendered from markdown
+    Html,
+}
+
+/// Lesson which is copied into another course with new files of text and cover
+#[derive(Clone, Debug)]
+pub struct LessonCopy {
+    pub lesson: Lesson,
+    pub content_path: String,
+    pub cover_path: Option<String>,
+}
@@ -1,9 +1,18 @@
+pub mod audit;
 pub mod auth;
 pub mod book;
 pub mod card;
 pub mod common;
 pub mod course;
+pub mod exercise;
+pub mod image;
+pub mod invite;
 pub mod language;
 pub mod lesson;
+pub mod progress;
+pub mod role;
+pub mod session;
 pub mod translator;
+pub mod two_factor;
 pub mod user;
+pub mod vocabulary;
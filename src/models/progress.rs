@@ -0,0 +1,73 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::lesson::LessonLink;
+
+/// Progress of user in one lesson
+#[derive(Clone, Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct LessonProgress {
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub time_spent_secs: i32,
+    /// Position where user stopped reading, it is set by client
+    pub last_position: i32,
+    pub user_id: i32,
+    pub lesson_id: i32,
+}
+
+/// Report of user activity in lesson
+#[derive(Debug, Default, Deserialize, Serialize, Validate)]
+pub struct UpdateLessonProgress {
+    /// Mark lesson as completed or not completed, unchanged if it is not set
+    pub completed: Option<bool>,
+    /// Seconds spent since previous report, it is added to total time
+    #[validate(range(min = 0, max = 3600))]
+    #[serde(default)]
+    pub time_spent_secs: i32,
+    #[validate(range(min = 0))]
+    pub last_position: Option<i32>,
+}
+
+/// Progress of user in course
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CourseProgress {
+    pub course_id: i32,
+    pub total_lessons: i64,
+    pub completed_lessons: i64,
+    pub percent: f64,
+    pub time_spent_secs: i64,
+    /// First lesson of course which is not completed
+    pub next_lesson: Option<LessonLink>,
+    pub lessons: Vec<LessonProgress>,
+}
+
+/// Progress of one student for course dashboard
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StudentProgress {
+    pub user_id: i32,
+    pub username: String,
+    pub completed_lessons: i64,
+    pub percent: f64,
+    pub time_spent_secs: i64,
+    pub last_activity: Option<NaiveDateTime>,
+}
+
+/// Progress of all students of course
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CourseDashboard {
+    pub course_id: i32,
+    pub total_lessons: i64,
+    pub average_percent: f64,
+    pub students: Vec<StudentProgress>,
+}
+
+/// Percent of completed lessons, course without lessons is not completed
+pub fn percent(completed: i64, total: i64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    (completed as f64 * 100.0 / total as f64 * 10.0).round() / 10.0
+}
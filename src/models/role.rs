@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use validator::Validate;
+
+/// Role of user, roles are stored in database with their permissions
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq, Deserialize, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    Teacher,
+    Student,
+}
+
+/// Action which is granted to role
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq, Deserialize, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    CreateCourse,
+    PublishCourse,
+    ModerateCourse,
+    ManageRoles,
+}
+
+/// Roles of user and all permissions granted by them
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct UserAccess {
+    pub roles: Vec<Role>,
+    pub permissions: Vec<Permission>,
+}
+
+/// JSON scheme for replacing roles of user
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct UpdateRolesData {
+    #[validate(length(min = 1))]
+    pub roles: Vec<Role>,
+}
@@ -0,0 +1,49 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct Session {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub device_id: String,
+    pub refresh_token_hash: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub user_id: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct CreateSession {
+    pub device_id: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub user_id: i32,
+}
+
+/// Session info for user, without refresh token hash
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SessionOut {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub device_id: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    #[serde(rename(serialize = "isCurrent", deserialize = "isCurrent"))]
+    pub is_current: bool,
+}
+
+impl SessionOut {
+    pub fn from_session(session: Session, current_id: Option<i32>) -> SessionOut {
+        SessionOut {
+            id: session.id,
+            created_at: session.created_at,
+            last_used_at: session.last_used_at,
+            device_id: session.device_id,
+            user_agent: session.user_agent,
+            ip: session.ip,
+            is_current: Some(session.id) == current_id,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// TOTP secret of user, 2FA is active only after enrollment is confirmed
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct TotpCredential {
+    pub user_id: i32,
+    pub created_at: NaiveDateTime,
+    pub secret: String,
+    pub enabled: bool,
+    pub last_used_step: Option<i64>,
+}
+
+/// Secret for authenticator app
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TotpEnrollOut {
+    pub secret: String,
+    pub otpauth_url: String,
+}
+
+/// JSON scheme with TOTP code or recovery code
+#[derive(Serialize, Deserialize, Validate, Debug)]
+pub struct TotpCodeData {
+    #[validate(length(min = 6))]
+    pub code: String,
+}
+
+/// One time recovery codes, are shown only once
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecoveryCodesOut {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Returned by sign in instead of tokens when 2FA is enabled
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TwoFactorChallenge {
+    pub challenge: String,
+}
+
+/// JSON scheme for second step of sign in
+#[derive(Serialize, Deserialize, Validate, Debug)]
+pub struct TwoFactorVerifyData {
+    #[validate(length(min = 1))]
+    pub challenge: String,
+    #[validate(length(min = 6))]
+    pub code: String,
+}
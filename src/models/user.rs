@@ -8,7 +8,7 @@ pub struct User {
     pub email: String,
     pub username: String,
     pub password_hash: String,
-    pub refresh_token_hash: Option<String>,
+    pub email_verified: bool,
 }
 
 #[derive(Debug)]
@@ -16,5 +16,4 @@ pub struct CreateUser {
     pub email: String,
     pub username: String,
     pub password_hash: String,
-    pub refresh_token_hash: Option<String>,
 }
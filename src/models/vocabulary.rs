@@ -0,0 +1,36 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Word taught in lesson, subscribers can copy vocabulary of lesson into their cards
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct LessonWord {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub position: i32,
+    pub word: String,
+    pub translation: String,
+    pub example: Option<String>,
+    pub lesson_id: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct CreateLessonWord {
+    #[validate(length(min = 1))]
+    pub word: String,
+    #[validate(length(min = 1))]
+    pub translation: String,
+    pub example: Option<String>,
+    pub lesson_id: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct UpdateLessonWord {
+    pub id: i32,
+    #[validate(length(min = 1))]
+    pub word: String,
+    #[validate(length(min = 1))]
+    pub translation: String,
+    pub example: Option<String>,
+}
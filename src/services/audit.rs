@@ -0,0 +1,42 @@
+use sqlx::Postgres;
+use std::error::Error;
+
+use crate::models::audit::{CreateFailedLogin, FailedLogin};
+
+/// Save failed sign in attempt
+pub async fn record_failed_login(
+    attempt: &CreateFailedLogin,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        r#"
+        INSERT INTO failed_logins (email, reason, ip, user_agent, user_id)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        attempt.email,
+        attempt.reason,
+        attempt.ip,
+        attempt.user_agent,
+        attempt.user_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Get failed sign in attempts for email, newest first
+pub async fn find_failed_logins(
+    email: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<FailedLogin>, Box<dyn Error>> {
+    let attempts = sqlx::query_as!(
+        FailedLogin,
+        "SELECT * FROM failed_logins WHERE email = $1 ORDER BY created_at DESC",
+        email
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(attempts)
+}
@@ -1,14 +1,19 @@
-use std::error::Error;
+use std::{collections::HashMap, error::Error, str::FromStr};
 
 use chrono::Utc;
 use sqlx::Postgres;
-use uuid::Uuid;
 
 use crate::models::{
-    course::{Course, CreateCourse, UpdateCourse},
+    course::{
+        normalize_tag, CatalogCourse, CatalogCursor, CatalogQuery, CatalogSort, Course,
+        CourseLevel, CourseMember, CourseRole, CreateCourse, UpdateCourse, UpdateCourseDetails,
+    },
     language::Language,
+    lesson::LessonCopy,
 };
 
+use super::lesson::save_revision_images;
+
 /// Create the course in db
 pub async fn create_course_db(
     user_id: i32,
@@ -24,10 +29,10 @@ pub async fn create_course_db(
     .await?
     .id;
 
-    // set owned for user
+    // set owner role for user
     sqlx::query!(
-        "INSERT INTO course_user(owned, course_id, user_id) VALUES($1, $2, $3)",
-        true,
+        "INSERT INTO course_user(role, course_id, user_id) VALUES($1, $2, $3)",
+        CourseRole::Owner.to_string(),
         new_course_id,
         user_id
     )
@@ -46,7 +51,8 @@ pub async fn find_course_by_id(
         Course,
         r#"
             SELECT
-            id, created_at, updated_at, title, language as "language!: Language"
+            id, created_at, updated_at, title, language as "language!: Language", is_public,
+            description, level as "level: CourseLevel", tags, is_template
             FROM courses
             WHERE id=$1
         "#,
@@ -58,6 +64,29 @@ pub async fn find_course_by_id(
     Ok(course)
 }
 
+/// Find course which contains the lesson
+pub async fn find_course_by_lesson(
+    lesson_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Course, Box<dyn Error>> {
+    let course = sqlx::query_as!(
+        Course,
+        r#"
+        SELECT
+            c.id, c.created_at, c.updated_at, c.title, c.language as "language!: Language",
+            c.is_public, c.description, c.level as "level: CourseLevel", c.tags, c.is_template
+        FROM courses c
+        JOIN lessons l ON l.course_id = c.id
+        WHERE l.id = $1
+        "#,
+        lesson_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(course)
+}
+
 /// Update course in database
 pub async fn update_course_db(
     new_course: UpdateCourse,
@@ -88,15 +117,22 @@ pub async fn delete_course_db(
     Ok(())
 }
 
-/// get all courses from database
-pub async fn get_courses_db(pool: &sqlx::Pool<Postgres>) -> Result<Vec<Course>, Box<dyn Error>> {
+/// get all courses which user can see: public courses and courses where user is member
+pub async fn get_courses_db(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<Course>, Box<dyn Error>> {
     let courses = sqlx::query_as!(
         Course,
         r#"
             SELECT
-            id, created_at, updated_at, title, language as "language!: Language"
+            id, created_at, updated_at, title, language as "language!: Language", is_public,
+            description, level as "level: CourseLevel", tags, is_template
             FROM courses
-        "#
+            WHERE is_public
+            OR id IN (SELECT course_id FROM course_user WHERE user_id = $1)
+        "#,
+        user_id
     )
     .fetch_all(pool)
     .await?;
@@ -110,26 +146,83 @@ pub async fn user_is_owner(
     course_id: i32,
     pool: &sqlx::Pool<Postgres>,
 ) -> Result<bool, Box<dyn Error>> {
-    let is_owner = sqlx::query!(
-        r#"SELECT * FROM course_user WHERE course_id=$1 AND user_id=$2"#,
+    let role = sqlx::query!(
+        r#"SELECT role FROM course_user WHERE course_id=$1 AND user_id=$2"#,
         course_id,
         user_id
     )
     .fetch_one(pool)
+    .await?
+    .role;
+
+    Ok(CourseRole::from_str(&role)? == CourseRole::Owner)
+}
+
+/// Get role of user in course, None if user is not member of course
+pub async fn find_member_role(
+    user_id: i32,
+    course_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Option<CourseRole>, Box<dyn Error>> {
+    let role = sqlx::query!(
+        "SELECT role FROM course_user WHERE course_id = $1 AND user_id = $2",
+        course_id,
+        user_id
+    )
+    .fetch_optional(pool)
     .await?;
 
-    Ok(is_owner.owned)
+    match role {
+        Some(rec) => Ok(Some(CourseRole::from_str(&rec.role)?)),
+        None => Ok(None),
+    }
 }
 
-/// user are subscribe to course
-pub async fn subscribe_to_course(
+/// Get all members of course with their roles
+pub async fn find_course_members(
+    course_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<CourseMember>, Box<dyn Error>> {
+    let members = sqlx::query!(
+        r#"
+        SELECT u.id, u.username, u.email, cu.role, cu.created_at
+        FROM course_user cu
+        JOIN users u ON u.id = cu.user_id
+        WHERE cu.course_id = $1
+        ORDER BY cu.created_at
+        "#,
+        course_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|rec| {
+        Some(CourseMember {
+            user_id: rec.id,
+            username: rec.username,
+            email: rec.email,
+            role: CourseRole::from_str(&rec.role).ok()?,
+            joined_at: rec.created_at,
+        })
+    })
+    .collect();
+
+    Ok(members)
+}
+
+/// Add user to course with role, role of existing member is replaced
+pub async fn set_course_member(
     user_id: i32,
     course_id: i32,
+    role: CourseRole,
     pool: &sqlx::Pool<Postgres>,
 ) -> Result<(), Box<dyn Error>> {
     sqlx::query!(
-        "INSERT INTO course_user(owned, course_id, user_id) VALUES($1, $2, $3)",
-        false,
+        r#"
+        INSERT INTO course_user (role, course_id, user_id) VALUES ($1, $2, $3)
+        ON CONFLICT (course_id, user_id) DO UPDATE SET role = $1
+        "#,
+        role.to_string(),
         course_id,
         user_id
     )
@@ -139,6 +232,60 @@ pub async fn subscribe_to_course(
     Ok(())
 }
 
+/// Make user the only owner of course, previous owners become editors
+pub async fn transfer_course(
+    user_id: i32,
+    course_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE course_user SET role = $2 WHERE course_id = $1 AND role = $3",
+        course_id,
+        CourseRole::Editor.to_string(),
+        CourseRole::Owner.to_string(),
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO course_user (role, course_id, user_id) VALUES ($1, $2, $3)
+        ON CONFLICT (course_id, user_id) DO UPDATE SET role = $1
+        "#,
+        CourseRole::Owner.to_string(),
+        course_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// user are subscribe to course, invite_id is the link which was used for joining
+pub async fn subscribe_to_course(
+    user_id: i32,
+    course_id: i32,
+    invite_id: Option<i32>,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "INSERT INTO course_user(role, course_id, user_id, invite_id) VALUES($1, $2, $3, $4)",
+        CourseRole::Student.to_string(),
+        course_id,
+        user_id,
+        invite_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 /// Check if user already subscribed to course
 pub async fn is_user_already_subscribed(
     user_id: i32,
@@ -201,42 +348,338 @@ pub async fn get_subscribed(
     Ok(courses)
 }
 
-/// Generate the invite link for course
-pub async fn generate_link(
+/// Set visibility of course in catalog
+pub async fn set_course_public(
     course_id: i32,
+    is_public: bool,
     pool: &sqlx::Pool<Postgres>,
-) -> Result<String, Box<dyn Error>> {
-    let invite_link = Uuid::new_v4().to_string();
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "UPDATE courses SET is_public = $2, updated_at = $3 WHERE id = $1",
+        course_id,
+        is_public,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
 
+/// Mark course as template which can be cloned by everyone who can see it
+pub async fn set_course_template(
+    course_id: i32,
+    is_template: bool,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
     sqlx::query!(
-        "UPDATE courses SET invite_link = $2, updated_at = $3 WHERE id = $1",
+        "UPDATE courses SET is_template = $2, updated_at = $3 WHERE id = $1",
         course_id,
-        Some(&invite_link),
+        is_template,
         Utc::now().naive_utc(),
     )
     .execute(pool)
     .await?;
 
-    Ok(String::from(invite_link))
+    Ok(())
 }
 
-/// Find the course by invite link
-pub async fn find_course_by_invite_link(
-    link: &str,
+/// Get templates available for user: public templates and templates where user is member
+pub async fn find_template_courses(
+    user_id: i32,
     pool: &sqlx::Pool<Postgres>,
-) -> Result<Course, Box<dyn Error>> {
-    let course = sqlx::query_as!(
+) -> Result<Vec<Course>, Box<dyn Error>> {
+    let courses = sqlx::query_as!(
         Course,
         r#"
             SELECT
-            id, created_at, updated_at, title, language as "language!: Language"
+            id, created_at, updated_at, title, language as "language!: Language", is_public,
+            description, level as "level: CourseLevel", tags, is_template
             FROM courses
-            WHERE invite_link=$1
+            WHERE is_template
+            AND (is_public OR id IN (SELECT course_id FROM course_user WHERE user_id = $1))
+            ORDER BY title ASC, id ASC
         "#,
-        link
+        user_id
     )
-    .fetch_one(pool)
+    .fetch_all(pool)
     .await?;
 
-    Ok(course)
+    Ok(courses)
+}
+
+/// Deep copy of course into new private course owned by user
+///
+/// Modules, lessons, published text and vocabulary are copied, drafts and exercises
+/// with answers are copied only `with_drafts`, files of lessons must be copied before
+/// to paths of `lessons`
+pub async fn clone_course_db(
+    course: &Course,
+    title: &str,
+    owner_id: i32,
+    lessons: &[LessonCopy],
+    with_drafts: bool,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<i32, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    let new_course_id = sqlx::query!(
+        r#"
+        INSERT INTO courses (title, language, description, level, tags)
+        SELECT $2, language, description, level, tags FROM courses WHERE id = $1
+        RETURNING id
+        "#,
+        course.id,
+        title,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+
+    sqlx::query!(
+        "INSERT INTO course_user(role, course_id, user_id) VALUES($1, $2, $3)",
+        CourseRole::Owner.to_string(),
+        new_course_id,
+        owner_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let modules = sqlx::query!(
+        "SELECT id, title, position FROM course_modules WHERE course_id = $1",
+        course.id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut module_ids = HashMap::new();
+
+    for module in modules {
+        let new_module_id = sqlx::query!(
+            "INSERT INTO course_modules (title, position, course_id) VALUES ($1, $2, $3) RETURNING id",
+            module.title,
+            module.position,
+            new_course_id,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .id;
+
+        module_ids.insert(module.id, new_module_id);
+    }
+
+    for copy in lessons {
+        let lesson = &copy.lesson;
+
+        let new_lesson_id = sqlx::query!(
+            r#"
+            INSERT INTO lessons
+                (title, cover_path, subject, content_path, course_id, module_id, position, preview)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id
+            "#,
+            lesson.title,
+            copy.cover_path,
+            lesson.subject,
+            copy.content_path,
+            new_course_id,
+            lesson.module_id.and_then(|id| module_ids.get(&id).copied()),
+            lesson.position,
+            lesson.preview,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .id;
+
+        let published = sqlx::query!(
+            r#"
+            INSERT INTO lesson_revisions (lesson_id, author_id, content)
+            SELECT $2, r.author_id, r.content
+            FROM lesson_revisions r
+            JOIN lessons l ON l.published_revision_id = r.id
+            WHERE l.id = $1
+            RETURNING id, content
+            "#,
+            lesson.id,
+            new_lesson_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(revision) = &published {
+            save_revision_images(revision.id, &revision.content, &mut tx).await?;
+        }
+
+        let published_revision_id = published.map(|revision| revision.id);
+
+        sqlx::query!(
+            "UPDATE lessons SET published_revision_id = $2 WHERE id = $1",
+            new_lesson_id,
+            published_revision_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if with_drafts {
+            // the newest draft after published text
+            let draft = sqlx::query!(
+                r#"
+                INSERT INTO lesson_revisions (lesson_id, author_id, content)
+                SELECT $2, r.author_id, r.content
+                FROM lesson_revisions r
+                JOIN lessons l ON l.id = r.lesson_id
+                WHERE r.lesson_id = $1
+                AND (l.published_revision_id IS NULL OR r.id > l.published_revision_id)
+                ORDER BY r.id DESC
+                LIMIT 1
+                RETURNING id, content
+                "#,
+                lesson.id,
+                new_lesson_id,
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if let Some(revision) = draft {
+                save_revision_images(revision.id, &revision.content, &mut tx).await?;
+            }
+
+            sqlx::query!(
+                r#"
+                INSERT INTO exercises (position, prompt, content, lesson_id)
+                SELECT position, prompt, content, $2 FROM exercises WHERE lesson_id = $1
+                "#,
+                lesson.id,
+                new_lesson_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO lesson_words (position, word, translation, example, lesson_id)
+            SELECT position, word, translation, example, $2 FROM lesson_words WHERE lesson_id = $1
+            "#,
+            lesson.id,
+            new_lesson_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(new_course_id)
+}
+
+/// Update description, level and tags of course
+pub async fn update_course_details(
+    course_id: i32,
+    details: &UpdateCourseDetails,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    let mut tags: Vec<String> = details
+        .tags
+        .iter()
+        .map(|tag| normalize_tag(tag))
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+
+    sqlx::query!(
+        r#"
+        UPDATE courses SET description = $2, level = $3, tags = $4, updated_at = $5
+        WHERE id = $1
+        "#,
+        course_id,
+        details.description,
+        details.level as Option<CourseLevel>,
+        &tags,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Get page of public courses for catalog
+///
+/// Return `limit` courses after cursor, sorted by creation time or count of students
+pub async fn find_catalog_courses(
+    query: &CatalogQuery,
+    cursor: Option<&CatalogCursor>,
+    limit: i64,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<CatalogCourse>, Box<dyn Error>> {
+    let tags = query.tags();
+    let search = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    let cursor_id = cursor.map(|cursor| cursor.id);
+
+    let courses = match query.sort {
+        CatalogSort::Recent => {
+            sqlx::query_as!(
+                CatalogCourse,
+                r#"
+                SELECT
+                c.id, c.created_at, c.title, c.description, c.language as "language!: Language",
+                c.level as "level: CourseLevel", c.tags, COUNT(cu.user_id) as "subscribers!"
+                FROM courses c
+                LEFT JOIN course_user cu ON cu.course_id = c.id AND cu.role = 'student'
+                WHERE c.is_public
+                AND ($1::language IS NULL OR c.language = $1)
+                AND ($2::course_level IS NULL OR c.level = $2)
+                AND c.tags @> $3
+                AND ($4::text IS NULL OR c.search @@ websearch_to_tsquery('simple', $4))
+                AND ($5::timestamp IS NULL OR (c.created_at, c.id) < ($5, $6))
+                GROUP BY c.id
+                ORDER BY c.created_at DESC, c.id DESC
+                LIMIT $7
+                "#,
+                query.language as Option<Language>,
+                query.level as Option<CourseLevel>,
+                &tags,
+                search,
+                cursor.and_then(|cursor| cursor.created_at),
+                cursor_id,
+                limit,
+            )
+            .fetch_all(pool)
+            .await?
+        }
+        CatalogSort::Popular => {
+            sqlx::query_as!(
+                CatalogCourse,
+                r#"
+                SELECT
+                c.id, c.created_at, c.title, c.description, c.language as "language!: Language",
+                c.level as "level: CourseLevel", c.tags, COUNT(cu.user_id) as "subscribers!"
+                FROM courses c
+                LEFT JOIN course_user cu ON cu.course_id = c.id AND cu.role = 'student'
+                WHERE c.is_public
+                AND ($1::language IS NULL OR c.language = $1)
+                AND ($2::course_level IS NULL OR c.level = $2)
+                AND c.tags @> $3
+                AND ($4::text IS NULL OR c.search @@ websearch_to_tsquery('simple', $4))
+                GROUP BY c.id
+                HAVING $5::bigint IS NULL OR (COUNT(cu.user_id), c.id) < ($5, $6)
+                ORDER BY COUNT(cu.user_id) DESC, c.id DESC
+                LIMIT $7
+                "#,
+                query.language as Option<Language>,
+                query.level as Option<CourseLevel>,
+                &tags,
+                search,
+                cursor.and_then(|cursor| cursor.subscribers),
+                cursor_id,
+                limit,
+            )
+            .fetch_all(pool)
+            .await?
+        }
+    };
+
+    Ok(courses)
 }
@@ -0,0 +1,174 @@
+use std::error::Error;
+
+use chrono::Utc;
+use sqlx::{types::Json, Postgres};
+
+use crate::models::exercise::{
+    CreateExercise, Exercise, ExerciseAnswer, ExerciseAttempt, ExerciseContent, UpdateExercise,
+};
+
+/// Create exercise at the end of lesson
+pub async fn create_exercise_db(
+    exercise: &CreateExercise,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<i32, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    // lesson is locked, so parallel requests do not take the same position
+    sqlx::query!(
+        "SELECT id FROM lessons WHERE id = $1 FOR UPDATE",
+        exercise.lesson_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let new_exercise_id = sqlx::query!(
+        r#"
+        INSERT INTO exercises (prompt, content, lesson_id, position)
+        VALUES (
+            $1, $2, $3,
+            (SELECT COALESCE(MAX(position) + 1, 0) FROM exercises WHERE lesson_id = $3)
+        )
+        RETURNING id
+        "#,
+        exercise.prompt,
+        Json(&exercise.content) as _,
+        exercise.lesson_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+
+    tx.commit().await?;
+
+    Ok(new_exercise_id)
+}
+
+/// Find the exercise by id
+pub async fn find_exercise_by_id(
+    id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Exercise, Box<dyn Error>> {
+    let exercise = sqlx::query_as!(
+        Exercise,
+        r#"
+        SELECT
+            id, created_at, updated_at, position, prompt,
+            content as "content!: Json<ExerciseContent>", lesson_id
+        FROM exercises
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exercise)
+}
+
+/// Get exercises of lesson in order
+pub async fn find_exercises_in_lesson(
+    lesson_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<Exercise>, Box<dyn Error>> {
+    let exercises = sqlx::query_as!(
+        Exercise,
+        r#"
+        SELECT
+            id, created_at, updated_at, position, prompt,
+            content as "content!: Json<ExerciseContent>", lesson_id
+        FROM exercises
+        WHERE lesson_id = $1
+        ORDER BY position ASC, id ASC
+        "#,
+        lesson_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(exercises)
+}
+
+/// Update prompt and content of exercise
+pub async fn update_exercise_db(
+    exercise: &UpdateExercise,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "UPDATE exercises SET prompt = $2, content = $3, updated_at = $4 WHERE id = $1",
+        exercise.id,
+        exercise.prompt,
+        Json(&exercise.content) as _,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Delete exercise with all attempts
+pub async fn delete_exercise_db(
+    id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!("DELETE FROM exercises WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Save graded answer of student
+pub async fn create_attempt(
+    exercise_id: i32,
+    user_id: i32,
+    answer: &ExerciseAnswer,
+    score: f64,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<ExerciseAttempt, Box<dyn Error>> {
+    let attempt = sqlx::query_as!(
+        ExerciseAttempt,
+        r#"
+        INSERT INTO exercise_attempts (exercise_id, user_id, answer, score, correct)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING
+            id, created_at, answer as "answer!: Json<ExerciseAnswer>", score, correct,
+            exercise_id, user_id
+        "#,
+        exercise_id,
+        user_id,
+        Json(answer) as _,
+        score,
+        score >= 1.0,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(attempt)
+}
+
+/// Get attempts of user for exercise, newest first
+pub async fn find_attempts(
+    exercise_id: i32,
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<ExerciseAttempt>, Box<dyn Error>> {
+    let attempts = sqlx::query_as!(
+        ExerciseAttempt,
+        r#"
+        SELECT
+            id, created_at, answer as "answer!: Json<ExerciseAnswer>", score, correct,
+            exercise_id, user_id
+        FROM exercise_attempts
+        WHERE exercise_id = $1 AND user_id = $2
+        ORDER BY created_at DESC, id DESC
+        "#,
+        exercise_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(attempts)
+}
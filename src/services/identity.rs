@@ -0,0 +1,41 @@
+use sqlx::Postgres;
+use std::error::Error;
+
+/// Find id of user linked to external identity
+pub async fn find_identity_user(
+    provider: &str,
+    subject: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Option<i32>, Box<dyn Error>> {
+    let user_id = sqlx::query!(
+        "SELECT user_id FROM user_identities WHERE provider = $1 AND subject = $2",
+        provider,
+        subject,
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|rec| rec.user_id);
+
+    Ok(user_id)
+}
+
+/// Link external identity to user
+pub async fn create_identity(
+    provider: &str,
+    subject: &str,
+    email: Option<&str>,
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "INSERT INTO user_identities (provider, subject, email, user_id) VALUES ($1, $2, $3, $4)",
+        provider,
+        subject,
+        email,
+        user_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use std::error::Error;
+
+use sqlx::Postgres;
+
+use crate::{
+    models::image::{
+        variant_filename, CreateImage, Image, ImageEncoding, ImageSize, ProcessedImage,
+    },
+    utils::storage::{BlobKind, BlobStore},
+};
+
+/// Save files of processed image under `filename` and its metadata
+pub async fn save_image(
+    filename: &str,
+    original_name: Option<String>,
+    image: ProcessedImage,
+    owner_id: i32,
+    store: &dyn BlobStore,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<i32, Box<dyn Error>> {
+    let original_size = image
+        .variants
+        .iter()
+        .find(|variant| {
+            variant.size == ImageSize::Original && variant.encoding == ImageEncoding::Original
+        })
+        .map(|variant| variant.data.len())
+        .unwrap_or_default();
+
+    for variant in image.variants {
+        let key = variant_filename(filename, variant.size, variant.encoding);
+
+        store.put(BlobKind::Image, &key, variant.data).await?;
+    }
+
+    let metadata = CreateImage {
+        filename: filename.to_string(),
+        original_name,
+        content_type: image.image_type.mime_type().to_string(),
+        size: original_size as i64,
+        width: image.width.map(|width| width as i32),
+        height: image.height.map(|height| height as i32),
+    };
+
+    create_image_db(&metadata, owner_id, pool).await
+}
+
+/// Save metadata of uploaded image
+pub async fn create_image_db(
+    image: &CreateImage,
+    owner_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<i32, Box<dyn Error>> {
+    let new_image_id = sqlx::query!(
+        r#"
+        INSERT INTO images (filename, original_name, content_type, size, width, height, owner_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+        image.filename,
+        image.original_name,
+        image.content_type,
+        image.size,
+        image.width,
+        image.height,
+        owner_id,
+    )
+    .fetch_one(pool)
+    .await?
+    .id;
+
+    Ok(new_image_id)
+}
+
+/// Save metadata of copy of image for new owner, images uploaded before metadata
+/// have no row, so their copies are not tracked too
+pub async fn copy_image_db(
+    from: &str,
+    to: &str,
+    owner_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        r#"
+        INSERT INTO images (filename, original_name, content_type, size, width, height, owner_id)
+        SELECT $2, original_name, content_type, size, width, height, $3
+        FROM images
+        WHERE filename = $1
+        "#,
+        from,
+        to,
+        owner_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Find metadata of image by name of its file
+pub async fn find_image_by_filename(
+    filename: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Image, Box<dyn Error>> {
+    let image = sqlx::query_as!(
+        Image,
+        r#"
+        SELECT
+            id, created_at, filename, original_name, content_type, size, width, height, owner_id
+        FROM images
+        WHERE filename = $1
+        "#,
+        filename
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(image)
+}
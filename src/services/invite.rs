@@ -0,0 +1,171 @@
+use std::error::Error;
+
+use chrono::Utc;
+use sqlx::Postgres;
+use uuid::Uuid;
+
+use crate::models::{
+    course::CourseRole,
+    invite::{CourseInvite, CreateInvite, InviteMember, InviteOut},
+};
+
+/// Create invite link for course
+pub async fn create_invite(
+    course_id: i32,
+    created_by: i32,
+    invite: &CreateInvite,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<CourseInvite, Box<dyn Error>> {
+    let invite = sqlx::query_as!(
+        CourseInvite,
+        r#"
+        INSERT INTO course_invites (name, token, expires_at, max_uses, created_by, course_id)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+        invite.name,
+        Uuid::new_v4().to_string(),
+        invite.expires_at,
+        invite.max_uses,
+        created_by,
+        course_id,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(invite)
+}
+
+/// Find invite link which can be used now: not revoked, not expired and not exhausted
+pub async fn find_active_invite(
+    token: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Option<CourseInvite>, Box<dyn Error>> {
+    let invite = sqlx::query_as!(
+        CourseInvite,
+        r#"
+        SELECT * FROM course_invites
+        WHERE token = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > $2)
+          AND (max_uses IS NULL OR uses < max_uses)
+        "#,
+        token,
+        Utc::now().naive_utc(),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(invite)
+}
+
+/// Subscribe user to course by invite link, return false if link can not be used anymore
+///
+/// Usage is counted by guarded update in one transaction with subscription,
+/// so link is not used more than `max_uses` and use is not lost on failed subscription
+pub async fn use_invite(
+    invite_id: i32,
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    let invite = sqlx::query!(
+        r#"
+        UPDATE course_invites SET uses = uses + 1
+        WHERE id = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > $2)
+          AND (max_uses IS NULL OR uses < max_uses)
+        RETURNING course_id
+        "#,
+        invite_id,
+        Utc::now().naive_utc(),
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(invite) = invite else {
+        return Ok(false);
+    };
+
+    sqlx::query!(
+        "INSERT INTO course_user(role, course_id, user_id, invite_id) VALUES($1, $2, $3, $4)",
+        CourseRole::Student.to_string(),
+        invite.course_id,
+        user_id,
+        invite_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Revoke invite link of course, return false if link is not found
+pub async fn revoke_invite(
+    course_id: i32,
+    invite_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    let res = sqlx::query!(
+        r#"
+        UPDATE course_invites SET revoked_at = $3
+        WHERE id = $1 AND course_id = $2 AND revoked_at IS NULL
+        "#,
+        invite_id,
+        course_id,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Get all invite links of course with users who joined by them
+pub async fn find_course_invites(
+    course_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<InviteOut>, Box<dyn Error>> {
+    let invites = sqlx::query_as!(
+        CourseInvite,
+        "SELECT * FROM course_invites WHERE course_id = $1 ORDER BY created_at DESC",
+        course_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let members = sqlx::query!(
+        r#"
+        SELECT cu.invite_id as "invite_id!", u.id, u.username, cu.created_at
+        FROM course_user cu
+        JOIN users u ON u.id = cu.user_id
+        WHERE cu.course_id = $1 AND cu.invite_id IS NOT NULL
+        ORDER BY cu.created_at
+        "#,
+        course_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let invites = invites
+        .into_iter()
+        .map(|invite| InviteOut {
+            members: members
+                .iter()
+                .filter(|rec| rec.invite_id == invite.id)
+                .map(|rec| InviteMember {
+                    user_id: rec.id,
+                    username: rec.username.clone(),
+                    joined_at: rec.created_at,
+                })
+                .collect(),
+            invite,
+        })
+        .collect();
+
+    Ok(invites)
+}
@@ -1,9 +1,19 @@
 use std::error::Error;
 
 use ::chrono::Utc;
-use sqlx::Postgres;
+use sqlx::{PgConnection, Postgres};
+use uuid::Uuid;
 
-use crate::models::lesson::{CreateLesson, Lesson, UpdateLesson};
+use crate::{
+    models::{
+        image::original_filename,
+        lesson::{
+            CourseModule, CreateLesson, CreateModule, Lesson, LessonLink, LessonRevision,
+            ReorderLessons, RevisionInfo, UpdateLesson, UpdateModule,
+        },
+    },
+    utils::{import::ImportedLesson, markdown::media_references},
+};
 
 /// Create the lesson in database
 pub async fn create_lesson_db(
@@ -11,16 +21,38 @@ pub async fn create_lesson_db(
     content_path: &str,
     pool: &sqlx::Pool<Postgres>,
 ) -> Result<i32, Box<dyn Error>> {
-    let new_lesson_id = sqlx::query!("INSERT INTO lessons (title, cover_path, subject, content_path, course_id) VALUES ($1, $2, $3, $4, $5) RETURNING id",
-    lesson.title,
-    lesson.cover_path,
-    lesson.subject,
-    content_path,
-    lesson.course_id,
-)
-        .fetch_one(pool)
-        .await?
-        .id;
+    let mut tx = pool.begin().await?;
+
+    // course is locked, so parallel requests do not take the same position
+    sqlx::query!(
+        "SELECT id FROM courses WHERE id = $1 FOR UPDATE",
+        lesson.course_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    // new lesson is placed at the end of course
+    let new_lesson_id = sqlx::query!(
+        r#"
+        INSERT INTO lessons (title, cover_path, subject, content_path, course_id, module_id, position)
+        VALUES (
+            $1, $2, $3, $4, $5, $6,
+            (SELECT COALESCE(MAX(position) + 1, 0) FROM lessons WHERE course_id = $5)
+        )
+        RETURNING id
+        "#,
+        lesson.title,
+        lesson.cover_path,
+        lesson.subject,
+        content_path,
+        lesson.course_id,
+        lesson.module_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+
+    tx.commit().await?;
 
     Ok(new_lesson_id)
 }
@@ -34,7 +66,8 @@ pub async fn find_lesson_by_id(
         Lesson,
         r#"
         SELECT
-            id, created_at, updated_at, title, content_path, cover_path, subject, course_id
+            id, created_at, updated_at, title, content_path, cover_path, subject, course_id,
+            position, module_id, preview
         FROM
             lessons
         WHERE id = $1
@@ -47,17 +80,25 @@ pub async fn find_lesson_by_id(
     Ok(lesson)
 }
 
-/// Find all lessons in database
-pub async fn find_all_lessons(pool: &sqlx::Pool<Postgres>) -> Result<Vec<Lesson>, Box<dyn Error>> {
+/// Find lessons available for user: lessons of courses where user is member
+/// and preview lessons
+pub async fn find_available_lessons(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<Lesson>, Box<dyn Error>> {
     let lessons = sqlx::query_as!(
         Lesson,
         r#"
         SELECT
-            id, created_at, updated_at, title, content_path, cover_path, subject, course_id
+            id, created_at, updated_at, title, content_path, cover_path, subject, course_id,
+            position, module_id, preview
         FROM
             lessons
+        WHERE preview
+            OR course_id IN (SELECT course_id FROM course_user WHERE user_id = $1)
         ORDER BY created_at ASC
-        "#
+        "#,
+        user_id
     )
     .fetch_all(pool)
     .await?;
@@ -74,12 +115,13 @@ pub async fn find_lessons_in_course(
         Lesson,
         r#"
         SELECT
-            id, created_at, updated_at, title, content_path, cover_path, subject, course_id
+            id, created_at, updated_at, title, content_path, cover_path, subject, course_id,
+            position, module_id, preview
         FROM
             lessons
         WHERE
             course_id = $1
-        ORDER BY created_at ASC
+        ORDER BY position ASC, id ASC
         "#,
         course_id
     )
@@ -116,3 +158,478 @@ pub async fn update_lesson_db(
 
     Ok(())
 }
+
+/// Open lesson for users who are not members of course or close it
+pub async fn set_lesson_preview(
+    lesson_id: i32,
+    preview: bool,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "UPDATE lessons SET preview = $2, updated_at = $3 WHERE id = $1",
+        lesson_id,
+        preview,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Find previous and next lessons of course by position
+pub async fn find_lesson_neighbours(
+    lesson: &Lesson,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(Option<LessonLink>, Option<LessonLink>), Box<dyn Error>> {
+    let prev = sqlx::query_as!(
+        LessonLink,
+        r#"
+        SELECT id, title FROM lessons
+        WHERE course_id = $1 AND (position, id) < ($2, $3)
+        ORDER BY position DESC, id DESC
+        LIMIT 1
+        "#,
+        lesson.course_id,
+        lesson.position,
+        lesson.id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let next = sqlx::query_as!(
+        LessonLink,
+        r#"
+        SELECT id, title FROM lessons
+        WHERE course_id = $1 AND (position, id) > ($2, $3)
+        ORDER BY position ASC, id ASC
+        LIMIT 1
+        "#,
+        lesson.course_id,
+        lesson.position,
+        lesson.id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok((prev, next))
+}
+
+/// Change order of modules and lessons in course
+///
+/// All positions are changed in one transaction. Return false if new order
+/// does not contain every module and lesson of course exactly once
+pub async fn reorder_lessons(
+    course_id: i32,
+    order: &ReorderLessons,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    // lock course and rows, so lessons are not added or removed while order is changed
+    sqlx::query!("SELECT id FROM courses WHERE id = $1 FOR UPDATE", course_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    let mut module_ids: Vec<i32> = sqlx::query!(
+        "SELECT id FROM course_modules WHERE course_id = $1 FOR UPDATE",
+        course_id
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|rec| rec.id)
+    .collect();
+
+    let mut lesson_ids: Vec<i32> = sqlx::query!(
+        "SELECT id FROM lessons WHERE course_id = $1 FOR UPDATE",
+        course_id
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|rec| rec.id)
+    .collect();
+
+    let mut new_module_ids = order.modules.clone();
+    let mut new_lesson_ids: Vec<i32> = order.lessons.iter().map(|lesson| lesson.id).collect();
+
+    module_ids.sort();
+    lesson_ids.sort();
+    new_module_ids.sort();
+    new_lesson_ids.sort();
+
+    let modules_exist = order
+        .lessons
+        .iter()
+        .filter_map(|lesson| lesson.module_id)
+        .all(|module_id| module_ids.binary_search(&module_id).is_ok());
+
+    if module_ids != new_module_ids || lesson_ids != new_lesson_ids || !modules_exist {
+        return Ok(false);
+    }
+
+    let positions: Vec<i32> = (0..order.modules.len() as i32).collect();
+
+    sqlx::query!(
+        r#"
+        UPDATE course_modules m SET position = o.position, updated_at = $3
+        FROM UNNEST($1::int[], $2::int[]) AS o(id, position)
+        WHERE m.id = o.id
+        "#,
+        &order.modules,
+        &positions,
+        Utc::now().naive_utc(),
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let ids: Vec<i32> = order.lessons.iter().map(|lesson| lesson.id).collect();
+    let modules: Vec<Option<i32>> = order
+        .lessons
+        .iter()
+        .map(|lesson| lesson.module_id)
+        .collect();
+    let positions: Vec<i32> = (0..order.lessons.len() as i32).collect();
+
+    sqlx::query!(
+        r#"
+        UPDATE lessons l SET position = o.position, module_id = o.module_id, updated_at = $4
+        FROM UNNEST($1::int[], $2::int[], $3::int[]) AS o(id, position, module_id)
+        WHERE l.id = o.id
+        "#,
+        &ids,
+        &positions,
+        &modules as &[Option<i32>],
+        Utc::now().naive_utc(),
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Create module at the end of course
+pub async fn create_module_db(
+    module: &CreateModule,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<i32, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    // course is locked, so parallel requests do not take the same position
+    sqlx::query!(
+        "SELECT id FROM courses WHERE id = $1 FOR UPDATE",
+        module.course_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let new_module_id = sqlx::query!(
+        r#"
+        INSERT INTO course_modules (title, course_id, position)
+        VALUES (
+            $1, $2,
+            (SELECT COALESCE(MAX(position) + 1, 0) FROM course_modules WHERE course_id = $2)
+        )
+        RETURNING id
+        "#,
+        module.title,
+        module.course_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+
+    tx.commit().await?;
+
+    Ok(new_module_id)
+}
+
+/// Find the module by id in database
+pub async fn find_module_by_id(
+    id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<CourseModule, Box<dyn Error>> {
+    let module = sqlx::query_as!(
+        CourseModule,
+        "SELECT * FROM course_modules WHERE id = $1",
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(module)
+}
+
+/// Get all modules of course in order
+pub async fn find_modules_in_course(
+    course_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<CourseModule>, Box<dyn Error>> {
+    let modules = sqlx::query_as!(
+        CourseModule,
+        "SELECT * FROM course_modules WHERE course_id = $1 ORDER BY position ASC",
+        course_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(modules)
+}
+
+/// Update title of module
+pub async fn update_module_db(
+    module: &UpdateModule,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "UPDATE course_modules SET title = $2, updated_at = $3 WHERE id = $1",
+        module.id,
+        module.title,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Delete module, lessons of module stay in course without module
+pub async fn delete_module_db(id: i32, pool: &sqlx::Pool<Postgres>) -> Result<(), Box<dyn Error>> {
+    sqlx::query!("DELETE FROM course_modules WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Save new revision of lesson text, it is draft until it is published
+pub async fn create_revision_db(
+    lesson_id: i32,
+    author_id: i32,
+    content: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<i32, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    let new_revision_id = sqlx::query!(
+        r#"
+        INSERT INTO lesson_revisions (lesson_id, author_id, content)
+        VALUES ($1, $2, $3)
+        RETURNING id
+        "#,
+        lesson_id,
+        author_id,
+        content,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+
+    save_revision_images(new_revision_id, content, &mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(new_revision_id)
+}
+
+/// Save names of images used by text of revision, so storage gc does not remove them
+pub(crate) async fn save_revision_images(
+    revision_id: i32,
+    content: &str,
+    conn: &mut PgConnection,
+) -> Result<(), Box<dyn Error>> {
+    let mut filenames: Vec<String> = media_references(content)
+        .iter()
+        .map(|filename| original_filename(filename).to_string())
+        .collect();
+
+    filenames.sort();
+    filenames.dedup();
+
+    if filenames.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO revision_images (revision_id, filename)
+        SELECT $1, UNNEST($2::text[])
+        ON CONFLICT DO NOTHING
+        "#,
+        revision_id,
+        &filenames,
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Create lessons of imported document at the end of course with their texts as drafts
+///
+/// Lessons are created in one transaction, so failed import does not leave part of document
+pub async fn import_lessons_db(
+    course_id: i32,
+    author_id: i32,
+    lessons: &[ImportedLesson],
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<i32>, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+    let mut lesson_ids = Vec::with_capacity(lessons.len());
+
+    // course is locked, so parallel requests do not take the same position
+    sqlx::query!("SELECT id FROM courses WHERE id = $1 FOR UPDATE", course_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    for lesson in lessons {
+        let content_path = format!("{}.md", Uuid::new_v4());
+
+        let lesson_id = sqlx::query!(
+            r#"
+            INSERT INTO lessons (title, content_path, course_id, position)
+            VALUES (
+                $1, $2, $3,
+                (SELECT COALESCE(MAX(position) + 1, 0) FROM lessons WHERE course_id = $3)
+            )
+            RETURNING id
+            "#,
+            lesson.title,
+            content_path,
+            course_id,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .id;
+
+        let revision_id = sqlx::query!(
+            r#"
+            INSERT INTO lesson_revisions (lesson_id, author_id, content)
+            VALUES ($1, $2, $3)
+            RETURNING id
+            "#,
+            lesson_id,
+            author_id,
+            lesson.text,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .id;
+
+        save_revision_images(revision_id, &lesson.text, &mut tx).await?;
+
+        lesson_ids.push(lesson_id);
+    }
+
+    tx.commit().await?;
+
+    Ok(lesson_ids)
+}
+
+/// Find the revision by id
+pub async fn find_revision_by_id(
+    id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<LessonRevision, Box<dyn Error>> {
+    let revision = sqlx::query_as!(
+        LessonRevision,
+        r#"
+        SELECT id, created_at, content, author_id, lesson_id
+        FROM lesson_revisions
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(revision)
+}
+
+/// Find the newest revision of lesson, it is draft if lesson was not published after it
+pub async fn find_latest_revision(
+    lesson_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Option<LessonRevision>, Box<dyn Error>> {
+    let revision = sqlx::query_as!(
+        LessonRevision,
+        r#"
+        SELECT id, created_at, content, author_id, lesson_id
+        FROM lesson_revisions
+        WHERE lesson_id = $1
+        ORDER BY id DESC
+        LIMIT 1
+        "#,
+        lesson_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(revision)
+}
+
+/// Get published revision of lesson, None if lesson was never published by revision
+pub async fn find_published_revision(
+    lesson_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Option<LessonRevision>, Box<dyn Error>> {
+    let revision = sqlx::query_as!(
+        LessonRevision,
+        r#"
+        SELECT r.id, r.created_at, r.content, r.author_id, r.lesson_id
+        FROM lesson_revisions r
+        JOIN lessons l ON l.published_revision_id = r.id
+        WHERE l.id = $1
+        "#,
+        lesson_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(revision)
+}
+
+/// Get history of lesson text, newest first
+pub async fn find_lesson_revisions(
+    lesson_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<RevisionInfo>, Box<dyn Error>> {
+    let revisions = sqlx::query_as!(
+        RevisionInfo,
+        r#"
+        SELECT
+            r.id, r.created_at, r.author_id, r.lesson_id,
+            (r.id = l.published_revision_id) IS TRUE as "published!",
+            (l.published_revision_id IS NULL OR r.id > l.published_revision_id) as "draft!"
+        FROM lesson_revisions r
+        JOIN lessons l ON l.id = r.lesson_id
+        WHERE r.lesson_id = $1
+        ORDER BY r.id DESC
+        "#,
+        lesson_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(revisions)
+}
+
+/// Mark revision as published text of lesson
+pub async fn publish_revision_db(
+    revision: &LessonRevision,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "UPDATE lessons SET published_revision_id = $2, updated_at = $3 WHERE id = $1",
+        revision.lesson_id,
+        revision.id,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -1,5 +1,16 @@
+pub mod audit;
 pub mod book;
 pub mod card;
 pub mod course;
+pub mod exercise;
+pub mod identity;
+pub mod image;
+pub mod invite;
 pub mod lesson;
+pub mod progress;
+pub mod role;
+pub mod session;
+pub mod storage;
+pub mod two_factor;
 pub mod user;
+pub mod vocabulary;
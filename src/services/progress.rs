@@ -0,0 +1,173 @@
+use std::error::Error;
+
+use chrono::Utc;
+use sqlx::Postgres;
+
+use crate::models::{
+    lesson::LessonLink,
+    progress::{
+        percent, CourseDashboard, CourseProgress, LessonProgress, StudentProgress,
+        UpdateLessonProgress,
+    },
+};
+
+/// Save activity of user in lesson, time spent is added to previous time
+pub async fn upsert_lesson_progress(
+    user_id: i32,
+    lesson_id: i32,
+    progress: &UpdateLessonProgress,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<LessonProgress, Box<dyn Error>> {
+    let now = Utc::now().naive_utc();
+
+    // completion time is kept when lesson is completed again
+    let lesson_progress = sqlx::query_as!(
+        LessonProgress,
+        r#"
+        INSERT INTO lesson_progress (user_id, lesson_id, completed_at, time_spent_secs, last_position)
+        VALUES ($1, $2, CASE WHEN $3::boolean THEN $6::timestamp END, $4, COALESCE($5, 0))
+        ON CONFLICT (user_id, lesson_id) DO UPDATE SET
+            completed_at = CASE
+                WHEN $3::boolean IS NULL THEN lesson_progress.completed_at
+                WHEN $3::boolean THEN COALESCE(lesson_progress.completed_at, $6)
+            END,
+            time_spent_secs = lesson_progress.time_spent_secs + $4,
+            last_position = COALESCE($5, lesson_progress.last_position),
+            updated_at = $6
+        RETURNING *
+        "#,
+        user_id,
+        lesson_id,
+        progress.completed,
+        progress.time_spent_secs,
+        progress.last_position,
+        now,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(lesson_progress)
+}
+
+/// Get progress of user in course with first not completed lesson
+pub async fn find_course_progress(
+    user_id: i32,
+    course_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<CourseProgress, Box<dyn Error>> {
+    let total_lessons = sqlx::query!(
+        r#"SELECT COUNT(*) as "count!" FROM lessons WHERE course_id = $1"#,
+        course_id
+    )
+    .fetch_one(pool)
+    .await?
+    .count;
+
+    let lessons = sqlx::query_as!(
+        LessonProgress,
+        r#"
+        SELECT p.* FROM lesson_progress p
+        JOIN lessons l ON l.id = p.lesson_id
+        WHERE p.user_id = $1 AND l.course_id = $2
+        ORDER BY l.position ASC, l.id ASC
+        "#,
+        user_id,
+        course_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let next_lesson = sqlx::query_as!(
+        LessonLink,
+        r#"
+        SELECT l.id, l.title FROM lessons l
+        LEFT JOIN lesson_progress p ON p.lesson_id = l.id AND p.user_id = $1
+        WHERE l.course_id = $2 AND p.completed_at IS NULL
+        ORDER BY l.position ASC, l.id ASC
+        LIMIT 1
+        "#,
+        user_id,
+        course_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let completed_lessons = lessons
+        .iter()
+        .filter(|lesson| lesson.completed_at.is_some())
+        .count() as i64;
+
+    let time_spent_secs = lessons
+        .iter()
+        .map(|lesson| lesson.time_spent_secs as i64)
+        .sum();
+
+    Ok(CourseProgress {
+        course_id,
+        total_lessons,
+        completed_lessons,
+        percent: percent(completed_lessons, total_lessons),
+        time_spent_secs,
+        next_lesson,
+        lessons,
+    })
+}
+
+/// Get progress of all students of course
+pub async fn find_course_dashboard(
+    course_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<CourseDashboard, Box<dyn Error>> {
+    let total_lessons = sqlx::query!(
+        r#"SELECT COUNT(*) as "count!" FROM lessons WHERE course_id = $1"#,
+        course_id
+    )
+    .fetch_one(pool)
+    .await?
+    .count;
+
+    let students: Vec<StudentProgress> = sqlx::query!(
+        r#"
+        SELECT
+            u.id, u.username,
+            COUNT(p.completed_at) as "completed_lessons!",
+            COALESCE(SUM(p.time_spent_secs), 0) as "time_spent_secs!",
+            MAX(p.updated_at) as last_activity
+        FROM course_user cu
+        JOIN users u ON u.id = cu.user_id
+        LEFT JOIN lessons l ON l.course_id = cu.course_id
+        LEFT JOIN lesson_progress p ON p.lesson_id = l.id AND p.user_id = cu.user_id
+        WHERE cu.course_id = $1 AND cu.role = 'student'
+        GROUP BY u.id, u.username
+        ORDER BY u.username
+        "#,
+        course_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|rec| StudentProgress {
+        user_id: rec.id,
+        username: rec.username,
+        completed_lessons: rec.completed_lessons,
+        percent: percent(rec.completed_lessons, total_lessons),
+        time_spent_secs: rec.time_spent_secs,
+        last_activity: rec.last_activity,
+    })
+    .collect();
+
+    let average_percent = if students.is_empty() {
+        0.0
+    } else {
+        let sum: f64 = students.iter().map(|student| student.percent).sum();
+
+        (sum / students.len() as f64 * 10.0).round() / 10.0
+    };
+
+    Ok(CourseDashboard {
+        course_id,
+        total_lessons,
+        average_percent,
+        students,
+    })
+}
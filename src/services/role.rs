@@ -0,0 +1,97 @@
+use std::{error::Error, str::FromStr};
+
+use sqlx::Postgres;
+
+use crate::models::role::{Permission, Role, UserAccess};
+
+/// Get roles of user and permissions granted by them
+///
+/// Names which are unknown for this version of server are skipped
+pub async fn find_user_access(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<UserAccess, Box<dyn Error>> {
+    let roles = sqlx::query!(
+        r#"
+        SELECT r.name FROM roles r
+        JOIN user_roles ur ON ur.role_id = r.id
+        WHERE ur.user_id = $1
+        ORDER BY r.id
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|rec| Role::from_str(&rec.name).ok())
+    .collect();
+
+    let permissions = sqlx::query!(
+        r#"
+        SELECT DISTINCT p.id, p.name FROM permissions p
+        JOIN role_permissions rp ON rp.permission_id = p.id
+        JOIN user_roles ur ON ur.role_id = rp.role_id
+        WHERE ur.user_id = $1
+        ORDER BY p.id
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|rec| Permission::from_str(&rec.name).ok())
+    .collect();
+
+    Ok(UserAccess { roles, permissions })
+}
+
+/// Give role to user, nothing happens if user already has it
+pub async fn assign_role(
+    user_id: i32,
+    role: Role,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_roles (user_id, role_id)
+        SELECT $1, id FROM roles WHERE name = $2
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        role.to_string(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Replace all roles of user
+pub async fn set_user_roles(
+    user_id: i32,
+    roles: &[Role],
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    let names: Vec<String> = roles.iter().map(|role| role.to_string()).collect();
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM user_roles WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_roles (user_id, role_id)
+        SELECT $1, id FROM roles WHERE name = ANY($2)
+        "#,
+        user_id,
+        &names,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use std::error::Error;
+
+use chrono::Utc;
+use crypto::{digest::Digest, sha2::Sha256};
+use sqlx::Postgres;
+
+use crate::models::session::{CreateSession, Session};
+
+/// Create session for device of user, or reuse existing session of this device
+pub async fn upsert_session(
+    session: &CreateSession,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<i32, Box<dyn Error>> {
+    let session_id = sqlx::query!(
+        r#"
+        INSERT INTO sessions (device_id, user_agent, ip, user_id)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id, device_id) DO UPDATE
+        SET user_agent = $2, ip = $3, last_used_at = $5
+        RETURNING id
+        "#,
+        session.device_id,
+        session.user_agent,
+        session.ip,
+        session.user_id,
+        Utc::now().naive_utc(),
+    )
+    .fetch_one(pool)
+    .await?
+    .id;
+
+    Ok(session_id)
+}
+
+/// Find session by id
+pub async fn find_session_by_id(
+    id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Session, Box<dyn Error>> {
+    let session = sqlx::query_as!(Session, "SELECT * FROM sessions WHERE id = $1", id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(session)
+}
+
+/// Get all sessions of user
+pub async fn find_user_sessions(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<Session>, Box<dyn Error>> {
+    let sessions = sqlx::query_as!(
+        Session,
+        "SELECT * FROM sessions WHERE user_id = $1 ORDER BY last_used_at DESC",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions)
+}
+
+/// Update refresh token of session and mark session as used
+pub async fn update_session_token(
+    id: i32,
+    token: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "UPDATE sessions SET refresh_token_hash = $2, last_used_at = $3 WHERE id = $1",
+        id,
+        hash_token(token),
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Replace refresh token of session only if `old_token` is still its current token,
+/// return false if token was already rotated
+///
+/// Check and update are done by one query, so token can not be used by two requests
+pub async fn rotate_session_token(
+    id: i32,
+    old_token: &str,
+    new_token: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    let session = sqlx::query!(
+        r#"
+        UPDATE sessions SET refresh_token_hash = $3, last_used_at = $4
+        WHERE id = $1 AND refresh_token_hash = $2
+        RETURNING id
+        "#,
+        id,
+        hash_token(old_token),
+        hash_token(new_token),
+        Utc::now().naive_utc(),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(session.is_some())
+}
+
+/// Delete session of user, return false if session is not found
+pub async fn delete_user_session(
+    user_id: i32,
+    id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    let res = sqlx::query!(
+        "DELETE FROM sessions WHERE id = $1 AND user_id = $2",
+        id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Delete all sessions of user, return ids of deleted sessions
+pub async fn delete_user_sessions(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<i32>, Box<dyn Error>> {
+    let ids = sqlx::query!(
+        "DELETE FROM sessions WHERE user_id = $1 RETURNING id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|rec| rec.id)
+    .collect();
+
+    Ok(ids)
+}
+
+/// Hash refresh token for storing in database
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input_str(token);
+
+    hasher.result_str()
+}
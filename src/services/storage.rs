@@ -0,0 +1,83 @@
+use std::{collections::HashSet, error::Error};
+
+use sqlx::Postgres;
+
+/// Names of files of all books
+pub async fn find_book_files(
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<HashSet<String>, Box<dyn Error>> {
+    let files = sqlx::query!("SELECT filename FROM books")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|book| book.filename)
+        .collect();
+
+    Ok(files)
+}
+
+/// Names of files with published text of all lessons
+pub async fn find_lesson_files(
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<HashSet<String>, Box<dyn Error>> {
+    let files = sqlx::query!("SELECT content_path FROM lessons")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|lesson| lesson.content_path)
+        .collect();
+
+    Ok(files)
+}
+
+/// Names of uploaded images which are not covers and are not used by any revision
+/// of lesson text, images uploaded before metadata are not tracked and never returned
+pub async fn find_unused_images(
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<HashSet<String>, Box<dyn Error>> {
+    let images = sqlx::query!(
+        r#"
+        SELECT filename FROM images i
+        WHERE NOT EXISTS (SELECT 1 FROM lessons WHERE cover_path = i.filename)
+          AND NOT EXISTS (SELECT 1 FROM books WHERE cover_path = i.filename)
+          AND NOT EXISTS (SELECT 1 FROM revision_images WHERE filename = i.filename)
+        "#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|image| image.filename)
+    .collect();
+
+    Ok(images)
+}
+
+/// Check if file of book is used by any book except `book_id`
+pub async fn book_file_is_shared(
+    filename: &str,
+    book_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    let shared = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM books WHERE filename = $1 AND id <> $2) AS "exists!""#,
+        filename,
+        book_id
+    )
+    .fetch_one(pool)
+    .await?
+    .exists;
+
+    Ok(shared)
+}
+
+/// Remove metadata of images which files were deleted
+pub async fn delete_images_db(
+    filenames: &[String],
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!("DELETE FROM images WHERE filename = ANY($1)", filenames)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
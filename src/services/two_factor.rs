@@ -0,0 +1,156 @@
+use chrono::Utc;
+use sqlx::Postgres;
+use std::error::Error;
+
+use crate::models::two_factor::TotpCredential;
+
+use super::session::hash_token;
+
+/// Save new not confirmed TOTP secret, previous not confirmed secret is replaced
+pub async fn upsert_totp_secret(
+    user_id: i32,
+    secret: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        r#"
+        INSERT INTO totp_credentials (user_id, secret)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = $2, enabled = FALSE, last_used_step = NULL, created_at = $3
+        "#,
+        user_id,
+        secret,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Find TOTP secret of user
+pub async fn find_totp(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Option<TotpCredential>, Box<dyn Error>> {
+    let totp = sqlx::query_as!(
+        TotpCredential,
+        "SELECT * FROM totp_credentials WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(totp)
+}
+
+/// Return true if user has confirmed 2FA
+pub async fn is_totp_enabled(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    Ok(find_totp(user_id, pool)
+        .await?
+        .is_some_and(|totp| totp.enabled))
+}
+
+/// Activate 2FA and remember used time step
+pub async fn enable_totp(
+    user_id: i32,
+    step: i64,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "UPDATE totp_credentials SET enabled = TRUE, last_used_step = $2 WHERE user_id = $1",
+        user_id,
+        step,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remember used time step, so code can not be used twice
+///
+/// Return false if the same or later step was already used
+pub async fn update_totp_step(
+    user_id: i32,
+    step: i64,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    let res = sqlx::query!(
+        r#"
+        UPDATE totp_credentials SET last_used_step = $2
+        WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+        "#,
+        user_id,
+        step,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Turn off 2FA, secret and recovery codes are removed
+pub async fn delete_totp(user_id: i32, pool: &sqlx::Pool<Postgres>) -> Result<(), Box<dyn Error>> {
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(pool)
+        .await?;
+
+    sqlx::query!("DELETE FROM totp_credentials WHERE user_id = $1", user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Replace all recovery codes of user, only hashes are stored
+pub async fn replace_recovery_codes(
+    user_id: i32,
+    codes: &[String],
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for code in codes {
+        sqlx::query!(
+            "INSERT INTO recovery_codes (code_hash, user_id) VALUES ($1, $2)",
+            hash_token(code),
+            user_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Mark recovery code as used, return false if code is unknown or already used
+pub async fn use_recovery_code(
+    user_id: i32,
+    code: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    let res = sqlx::query!(
+        r#"
+        UPDATE recovery_codes SET used_at = $3
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+        user_id,
+        hash_token(code),
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
@@ -1,26 +1,32 @@
-use chrono::Utc;
-use crypto::{digest::Digest, sha2::Sha256};
+use chrono::{Duration, Utc};
 use sqlx::Postgres;
 use std::error::Error;
+use uuid::Uuid;
 
 use crate::models::{
     card::CreateGroup,
+    role::Role,
     user::{CreateUser, User},
 };
 
-use super::card::create_group_db;
+use super::{
+    card::{create_group_db, find_user_root_group},
+    role::assign_role,
+    session::hash_token,
+};
 
 /// create user function in database
+///
+/// New user get root card group and student role
 pub async fn create_user(
     user: &CreateUser,
     pool: &sqlx::Pool<Postgres>,
 ) -> Result<i32, Box<dyn Error>> {
     let user_id = sqlx::query!(
-        "INSERT INTO users(email, username, password_hash, refresh_token_hash) VALUES ($1, $2, $3, $4) RETURNING id",
+        "INSERT INTO users(email, username, password_hash) VALUES ($1, $2, $3) RETURNING id",
         user.email,
         user.username,
         user.password_hash,
-        user.refresh_token_hash
     ).fetch_one(pool).await?.id;
 
     let group_id = create_group_db(
@@ -40,6 +46,8 @@ pub async fn create_user(
     .execute(pool)
     .await?;
 
+    assign_role(user_id, Role::Student, pool).await?;
+
     Ok(user_id)
 }
 
@@ -64,31 +72,136 @@ pub async fn find_user_by_email(
     Ok(user)
 }
 
-impl User {
-    /// update refresh token for user
-    pub async fn update_refresh_token(
-        &self,
-        token: Option<&str>,
-        pool: &sqlx::Pool<Postgres>,
-    ) -> Result<(), Box<dyn Error>> {
-        let token = match token {
-            Some(token) => {
-                let mut hasher = Sha256::new();
-                hasher.input_str(token);
-                Some(hasher.result_str())
-            }
-            None => None,
-        };
-
-        sqlx::query!(
-            "UPDATE users SET refresh_token_hash=$2, updated_at = $3 WHERE id=$1",
-            self.id,
-            token,
-            Utc::now().naive_utc(),
-        )
+/// find users by username, usernames are not unique
+pub async fn find_users_by_username(
+    username: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<User>, Box<dyn Error>> {
+    let users = sqlx::query_as!(User, "SELECT * FROM users WHERE username=$1", username)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(users)
+}
+
+/// Lifetime of email verification token
+const EMAIL_VERIFICATION_HOURS: i64 = 24;
+
+/// Create email verification token for user and return it
+///
+/// Only hash of token is stored in database
+pub async fn create_email_verification(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<String, Box<dyn Error>> {
+    let token = Uuid::new_v4().to_string();
+
+    sqlx::query!(
+        "INSERT INTO email_verifications (token_hash, expires_at, user_id) VALUES ($1, $2, $3)",
+        hash_token(&token),
+        Utc::now().naive_utc() + Duration::hours(EMAIL_VERIFICATION_HOURS),
+        user_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(token)
+}
+
+/// Mark email of user as verified by token, return id of user
+///
+/// All verification tokens of user are removed after success
+pub async fn verify_email(token: &str, pool: &sqlx::Pool<Postgres>) -> Result<i32, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    let user_id = sqlx::query!(
+        "DELETE FROM email_verifications WHERE token_hash = $1 AND expires_at > $2 RETURNING user_id",
+        hash_token(token),
+        Utc::now().naive_utc(),
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .user_id;
+
+    sqlx::query!(
+        "UPDATE users SET email_verified = TRUE, updated_at = $2 WHERE id = $1",
+        user_id,
+        Utc::now().naive_utc(),
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM email_verifications WHERE user_id = $1",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(user_id)
+}
+
+/// Mark email of user as verified
+pub async fn set_email_verified(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "UPDATE users SET email_verified = TRUE, updated_at = $2 WHERE id = $1",
+        user_id,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Return true if user already verify the email
+pub async fn is_email_verified(
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<bool, Box<dyn Error>> {
+    let verified = sqlx::query!("SELECT email_verified FROM users WHERE id = $1", user_id)
+        .fetch_one(pool)
+        .await?
+        .email_verified;
+
+    Ok(verified)
+}
+
+/// Set new password hash for user
+pub async fn update_password(
+    user_id: i32,
+    password_hash: &str,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    sqlx::query!(
+        "UPDATE users SET password_hash = $2, updated_at = $3 WHERE id = $1",
+        user_id,
+        password_hash,
+        Utc::now().naive_utc(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Delete user from database with all owned rows
+pub async fn delete_user(user_id: i32, pool: &sqlx::Pool<Postgres>) -> Result<(), Box<dyn Error>> {
+    let root_id = find_user_root_group(user_id, pool).await?;
+
+    sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+        .execute(pool)
+        .await?;
+
+    // root group is linked to user through group_user, so it is not deleted by cascade
+    sqlx::query!("DELETE FROM card_group WHERE id = $1", root_id)
         .execute(pool)
         .await?;
 
-        Ok(())
-    }
+    Ok(())
 }
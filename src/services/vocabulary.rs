@@ -0,0 +1,238 @@
+use std::error::Error;
+
+use chrono::Utc;
+use sqlx::Postgres;
+use uuid::Uuid;
+
+use crate::models::{
+    card::CreateGroup,
+    vocabulary::{CreateLessonWord, LessonWord, UpdateLessonWord},
+};
+
+/// Create word at the end of vocabulary of lesson
+///
+/// Word is copied into every group of cards created from this lesson
+pub async fn create_word_db(
+    word: &CreateLessonWord,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<i32, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    // lesson is locked, so parallel requests do not take the same position
+    sqlx::query!(
+        "SELECT id FROM lessons WHERE id = $1 FOR UPDATE",
+        word.lesson_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let new_word_id = sqlx::query!(
+        r#"
+        INSERT INTO lesson_words (word, translation, example, lesson_id, position)
+        VALUES (
+            $1, $2, $3, $4,
+            (SELECT COALESCE(MAX(position) + 1, 0) FROM lesson_words WHERE lesson_id = $4)
+        )
+        RETURNING id
+        "#,
+        word.word,
+        word.translation,
+        word.example,
+        word.lesson_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO cards (word, translation, group_id, lesson_word_id)
+        SELECT $1, $2, group_id, $3
+        FROM lesson_word_groups
+        WHERE lesson_id = $4
+        "#,
+        word.word,
+        word.translation,
+        new_word_id,
+        word.lesson_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(new_word_id)
+}
+
+/// Find the word by id
+pub async fn find_word_by_id(
+    id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<LessonWord, Box<dyn Error>> {
+    let word = sqlx::query_as!(
+        LessonWord,
+        r#"
+        SELECT id, created_at, updated_at, position, word, translation, example, lesson_id
+        FROM lesson_words
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(word)
+}
+
+/// Get vocabulary of lesson in order
+pub async fn find_words_in_lesson(
+    lesson_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Vec<LessonWord>, Box<dyn Error>> {
+    let words = sqlx::query_as!(
+        LessonWord,
+        r#"
+        SELECT id, created_at, updated_at, position, word, translation, example, lesson_id
+        FROM lesson_words
+        WHERE lesson_id = $1
+        ORDER BY position ASC, id ASC
+        "#,
+        lesson_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(words)
+}
+
+/// Update word and cards copied from it
+pub async fn update_word_db(
+    word: &UpdateLessonWord,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<(), Box<dyn Error>> {
+    let now = Utc::now().naive_utc();
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE lesson_words
+        SET word = $2, translation = $3, example = $4, updated_at = $5
+        WHERE id = $1
+        "#,
+        word.id,
+        word.word,
+        word.translation,
+        word.example,
+        now,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE cards
+        SET word = $2, translation = $3, updated_at = $4
+        WHERE lesson_word_id = $1
+        "#,
+        word.id,
+        word.word,
+        word.translation,
+        now,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Delete word, cards copied from it are deleted by cascade
+pub async fn delete_word_db(id: i32, pool: &sqlx::Pool<Postgres>) -> Result<(), Box<dyn Error>> {
+    sqlx::query!("DELETE FROM lesson_words WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Find group of cards created by user from vocabulary of lesson
+pub async fn find_vocabulary_group(
+    lesson_id: i32,
+    user_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Option<i32>, Box<dyn Error>> {
+    let group_id = sqlx::query!(
+        "SELECT group_id FROM lesson_word_groups WHERE lesson_id = $1 AND user_id = $2",
+        lesson_id,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|row| row.group_id);
+
+    Ok(group_id)
+}
+
+/// Create group in `parent_id` with cards from vocabulary of lesson
+///
+/// The group is linked to lesson, so changes of vocabulary are applied to its cards.
+/// Return `None` if group of user was already created by parallel request
+pub async fn create_vocabulary_group(
+    lesson_id: i32,
+    user_id: i32,
+    group: &CreateGroup,
+    pool: &sqlx::Pool<Postgres>,
+) -> Result<Option<i32>, Box<dyn Error>> {
+    let invite_code = Uuid::new_v4().to_string();
+
+    let mut tx = pool.begin().await?;
+
+    let group_id = sqlx::query!(
+        "INSERT INTO card_group (title, group_id, invite_code) VALUES ($1, $2, $3) RETURNING id",
+        group.title,
+        group.group_id,
+        invite_code,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+
+    let linked = sqlx::query!(
+        r#"
+        INSERT INTO lesson_word_groups (lesson_id, user_id, group_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (lesson_id, user_id) DO NOTHING
+        RETURNING group_id
+        "#,
+        lesson_id,
+        user_id,
+        group_id,
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    // new group is removed by rollback of transaction
+    if linked.is_none() {
+        return Ok(None);
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO cards (word, translation, group_id, lesson_word_id)
+        SELECT word, translation, $2, id
+        FROM lesson_words
+        WHERE lesson_id = $1
+        ORDER BY position ASC, id ASC
+        "#,
+        lesson_id,
+        group_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(group_id))
+}
@@ -0,0 +1,103 @@
+use sqlx::Postgres;
+
+use crate::{
+    extractors::jwt_cred::JwtCred,
+    models::{
+        course::{Course, CourseRole},
+        lesson::Lesson,
+        role::Permission,
+    },
+    services::course::{find_member_role, user_is_owner},
+};
+
+/// Return true if user owns the course or can moderate any course
+pub async fn can_manage_course(
+    creds: &JwtCred,
+    course_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> bool {
+    if creds.has_permission(Permission::ModerateCourse) {
+        return true;
+    }
+
+    user_is_owner(creds.uid, course_id, pool)
+        .await
+        .unwrap_or(false)
+}
+
+/// Return true if user can change content of the course: owner, editor or moderator
+pub async fn can_edit_course(creds: &JwtCred, course_id: i32, pool: &sqlx::Pool<Postgres>) -> bool {
+    if creds.has_permission(Permission::ModerateCourse) {
+        return true;
+    }
+
+    matches!(
+        find_member_role(creds.uid, course_id, pool).await,
+        Ok(Some(CourseRole::Owner | CourseRole::Editor))
+    )
+}
+
+/// Return true if user can change the lesson, lesson without course can be changed
+/// only by moderator
+pub async fn can_edit_lesson(
+    creds: &JwtCred,
+    lesson: &Lesson,
+    pool: &sqlx::Pool<Postgres>,
+) -> bool {
+    match lesson.course_id {
+        Some(course_id) => can_edit_course(creds, course_id, pool).await,
+        None => creds.has_permission(Permission::ModerateCourse),
+    }
+}
+
+/// Return true if user can see the course: course is public, user is member or moderator
+///
+/// Private courses are available only for members, who joined by invite
+pub async fn can_view_course(
+    creds: &JwtCred,
+    course: &Course,
+    pool: &sqlx::Pool<Postgres>,
+) -> bool {
+    if course.is_public || creds.has_permission(Permission::ModerateCourse) {
+        return true;
+    }
+
+    matches!(
+        find_member_role(creds.uid, course.id, pool).await,
+        Ok(Some(_))
+    )
+}
+
+/// Return true if user can study the course: user is member or moderator
+///
+/// Public course is only listed in catalog, its lessons are not open without joining
+pub async fn can_study_course(
+    creds: &JwtCred,
+    course_id: i32,
+    pool: &sqlx::Pool<Postgres>,
+) -> bool {
+    if creds.has_permission(Permission::ModerateCourse) {
+        return true;
+    }
+
+    matches!(
+        find_member_role(creds.uid, course_id, pool).await,
+        Ok(Some(_))
+    )
+}
+
+/// Return true if user can read the lesson: lesson is preview or user can study its course
+pub async fn can_read_lesson(
+    creds: &JwtCred,
+    lesson: &Lesson,
+    pool: &sqlx::Pool<Postgres>,
+) -> bool {
+    if lesson.preview {
+        return true;
+    }
+
+    match lesson.course_id {
+        Some(course_id) => can_study_course(creds, course_id, pool).await,
+        None => creds.has_permission(Permission::ModerateCourse),
+    }
+}
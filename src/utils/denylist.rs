@@ -0,0 +1,67 @@
+use chrono::Utc;
+use redis::{Commands, Connection, RedisResult};
+
+use crate::{extractors::jwt_cred::JwtCred, utils::jwt::ACCESS_DURATION_MIN};
+
+/// Access tokens of session can not live longer than this
+const SESSION_DENY_SECS: u64 = ACCESS_DURATION_MIN * 60;
+
+fn token_key(jti: &str) -> String {
+    format!("denylist:jti:{}", jti)
+}
+
+fn session_key(session_id: i32) -> String {
+    format!("denylist:session:{}", session_id)
+}
+
+/// Deny single token until it expires
+pub fn deny_token(conn: &mut Connection, claims: &JwtCred) -> RedisResult<()> {
+    let jti = match &claims.jti {
+        Some(jti) => jti,
+        None => return Ok(()),
+    };
+
+    let now = Utc::now().timestamp() as u64;
+    let ttl = claims
+        .expires_at
+        .map(|exp| exp.saturating_sub(now))
+        .unwrap_or(SESSION_DENY_SECS)
+        .max(1);
+
+    conn.set_ex(token_key(jti), 1, ttl)
+}
+
+/// Deny all access tokens issued for revoked sessions
+///
+/// Session ids are never reused, so the key only has to outlive access tokens
+pub fn deny_sessions(conn: &mut Connection, session_ids: &[i32]) -> RedisResult<()> {
+    if session_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut pipe = redis::pipe();
+
+    for session_id in session_ids {
+        pipe.set_ex(session_key(*session_id), 1, SESSION_DENY_SECS)
+            .ignore();
+    }
+
+    pipe.query(conn)
+}
+
+/// Return true if token or its session is in denylist
+pub fn is_denied(conn: &mut Connection, claims: &JwtCred) -> RedisResult<bool> {
+    if let Some(jti) = &claims.jti {
+        if conn.exists(token_key(jti))? {
+            return Ok(true);
+        }
+    }
+
+    if let Some(session_id) = claims.sid {
+        if conn.exists(session_key(session_id))? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
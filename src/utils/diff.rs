@@ -0,0 +1,18 @@
+use similar::{ChangeTag, TextDiff};
+
+use crate::models::lesson::{DiffLine, DiffTag};
+
+/// Diff texts line by line
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            tag: match change.tag() {
+                ChangeTag::Equal => DiffTag::Equal,
+                ChangeTag::Insert => DiffTag::Insert,
+                ChangeTag::Delete => DiffTag::Delete,
+            },
+            text: change.to_string_lossy().trim_end_matches('\n').to_string(),
+        })
+        .collect()
+}
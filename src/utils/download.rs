@@ -0,0 +1,162 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use actix_web::{
+    body::SizedStream,
+    http::{
+        header::{
+            self, Charset, ContentDisposition, ContentRange, ContentRangeSpec, DispositionParam,
+            DispositionType, EntityTag, ExtendedValue, HttpDate, IfModifiedSince, IfNoneMatch,
+            IfRange, LastModified, Range,
+        },
+        StatusCode,
+    },
+    HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+
+use super::storage::{BlobKind, BlobMeta, BlobStore, ByteRange};
+
+/// Time of last change in whole seconds, as it is sent in headers
+fn modified_secs(meta: &BlobMeta) -> u64 {
+    meta.modified
+        .duration_since(UNIX_EPOCH)
+        .map(|modified| modified.as_secs())
+        .unwrap_or_default()
+}
+
+/// Weak validator of stored file, size and time in seconds do not prove that bytes
+/// are the same, so it is not used for ranges
+pub fn blob_etag(meta: &BlobMeta) -> EntityTag {
+    EntityTag::new_weak(format!("{:x}-{:x}", meta.size, modified_secs(meta)))
+}
+
+/// `Content-Disposition` of downloaded file, non-ASCII name is sent by RFC 5987
+/// with ASCII fallback for old clients
+pub fn attachment(filename: &str) -> ContentDisposition {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            ' ' => c,
+            '"' | '\\' => '_',
+            c if c.is_ascii_graphic() => c,
+            _ => '_',
+        })
+        .collect();
+
+    let mut parameters = vec![DispositionParam::Filename(fallback)];
+
+    if !filename.is_ascii() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_string()),
+            language_tag: None,
+            value: filename.as_bytes().to_vec(),
+        }));
+    }
+
+    ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters,
+    }
+}
+
+/// Check `If-None-Match` and `If-Modified-Since`, date is used only without tags
+fn is_not_modified(req: &HttpRequest, etag: &EntityTag, modified: HttpDate) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        None => match req.get_header::<IfModifiedSince>() {
+            Some(IfModifiedSince(since)) => modified <= since,
+            None => false,
+        },
+    }
+}
+
+/// Requested part of file, `Err` means that range is outside of file
+///
+/// Several ranges and ranges of changed file (`If-Range`) are served as whole file,
+/// tag of `If-Range` must be strong, so only date is checked there
+fn requested_range(
+    req: &HttpRequest,
+    etag: &EntityTag,
+    modified: HttpDate,
+    size: u64,
+) -> Result<Option<ByteRange>, ()> {
+    let specs = match req.get_header::<Range>() {
+        Some(Range::Bytes(specs)) if specs.len() == 1 => specs,
+        _ => return Ok(None),
+    };
+
+    let fresh = match req.get_header::<IfRange>() {
+        // weak tag never matches strongly
+        Some(IfRange::EntityTag(tag)) => tag.strong_eq(etag),
+        Some(IfRange::Date(date)) => date == modified,
+        None => true,
+    };
+
+    if !fresh {
+        return Ok(None);
+    }
+
+    match specs[0].to_satisfiable_range(size) {
+        Some((start, end)) => Ok(Some(ByteRange { start, end })),
+        None => Err(()),
+    }
+}
+
+/// Stream stored file with support of conditional and range requests
+///
+/// `res` has headers of file like `Content-Type` or `Cache-Control`,
+/// validators, `Content-Length` and status are set here
+pub async fn stream_blob(
+    req: &HttpRequest,
+    store: &dyn BlobStore,
+    kind: BlobKind,
+    key: &str,
+    meta: BlobMeta,
+    mut res: HttpResponseBuilder,
+) -> HttpResponse {
+    let op = "stream_blob";
+
+    let etag = blob_etag(&meta);
+    let modified = HttpDate::from(UNIX_EPOCH + Duration::from_secs(modified_secs(&meta)));
+
+    res.insert_header(header::ETag(etag.clone()))
+        .insert_header(LastModified(modified))
+        .insert_header((header::ACCEPT_RANGES, "bytes"));
+
+    if is_not_modified(req, &etag, modified) {
+        return res.status(StatusCode::NOT_MODIFIED).finish();
+    }
+
+    let range = match requested_range(req, &etag, modified, meta.size) {
+        Ok(range) => range,
+        Err(_) => {
+            return res
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                    range: None,
+                    instance_length: Some(meta.size),
+                }))
+                .finish();
+        }
+    };
+
+    let stream = match store.stream(kind, key, range).await {
+        Ok(stream) => stream,
+        Err(err) => {
+            log::error!("{}: cannot read file: {}, error: {}", op, key, err);
+
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    match range {
+        Some(range) => res
+            .status(StatusCode::PARTIAL_CONTENT)
+            .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                range: Some((range.start, range.end)),
+                instance_length: Some(meta.size),
+            }))
+            .body(SizedStream::new(range.size(), stream)),
+        None => res.body(SizedStream::new(meta.size, stream)),
+    }
+}
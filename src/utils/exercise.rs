@@ -0,0 +1,132 @@
+use rand::seq::SliceRandom;
+
+use crate::models::exercise::{ExerciseAnswer, ExerciseContent, ExerciseTask};
+
+/// Mark of blank in text of fill-in-the-blank exercise
+pub const BLANK: &str = "___";
+
+/// Check that exercise can be solved: options and answers are not empty
+/// and answers refer to existing options and blanks
+pub fn is_valid_content(content: &ExerciseContent) -> bool {
+    match content {
+        ExerciseContent::MultipleChoice { options, answers } => {
+            !options.is_empty()
+                && !answers.is_empty()
+                && answers.iter().all(|answer| *answer < options.len())
+        }
+        ExerciseContent::FillBlank { text, answers } => {
+            let blanks = text.matches(BLANK).count();
+
+            blanks > 0
+                && blanks == answers.len()
+                && answers.iter().all(|accepted| !accepted.is_empty())
+        }
+        ExerciseContent::Translate { sentence, answers } => {
+            !sentence.trim().is_empty() && !answers.is_empty()
+        }
+        ExerciseContent::Matching { pairs } => pairs.len() > 1,
+    }
+}
+
+/// Hide answers of exercise, right column of matching is shuffled
+pub fn to_task(content: &ExerciseContent) -> ExerciseTask {
+    match content {
+        ExerciseContent::MultipleChoice { options, answers } => ExerciseTask::MultipleChoice {
+            options: options.clone(),
+            multiple: answers.len() > 1,
+        },
+        ExerciseContent::FillBlank { text, .. } => ExerciseTask::FillBlank { text: text.clone() },
+        ExerciseContent::Translate { sentence, .. } => ExerciseTask::Translate {
+            sentence: sentence.clone(),
+        },
+        ExerciseContent::Matching { pairs } => {
+            let left = pairs.iter().map(|(left, _)| left.clone()).collect();
+            let mut right: Vec<String> = pairs.iter().map(|(_, right)| right.clone()).collect();
+            right.shuffle(&mut rand::thread_rng());
+
+            ExerciseTask::Matching { left, right }
+        }
+    }
+}
+
+/// Lowercase text without punctuation and extra spaces
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|ch| ch.is_alphanumeric() || *ch == '\'' || *ch == '-')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn is_accepted(answer: &str, accepted: &[String]) -> bool {
+    let answer = normalize(answer);
+
+    accepted
+        .iter()
+        .any(|accepted| normalize(accepted) == answer)
+}
+
+/// Grade answer of student, return score from 0 to 1
+///
+/// Return `None` if kind of answer does not match kind of exercise
+pub fn grade(content: &ExerciseContent, answer: &ExerciseAnswer) -> Option<f64> {
+    let score = match (content, answer) {
+        (
+            ExerciseContent::MultipleChoice { answers, .. },
+            ExerciseAnswer::MultipleChoice { selected },
+        ) => {
+            let mut answers = answers.clone();
+            let mut selected = selected.clone();
+            answers.sort_unstable();
+            answers.dedup();
+            selected.sort_unstable();
+            selected.dedup();
+
+            if answers == selected {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        (ExerciseContent::FillBlank { answers, .. }, ExerciseAnswer::FillBlank { blanks }) => {
+            let right = answers
+                .iter()
+                .zip(blanks)
+                .filter(|(accepted, blank)| is_accepted(blank, accepted))
+                .count();
+
+            right as f64 / answers.len().max(1) as f64
+        }
+        (ExerciseContent::Translate { answers, .. }, ExerciseAnswer::Translate { text }) => {
+            if is_accepted(text, answers) {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        (ExerciseContent::Matching { pairs }, ExerciseAnswer::Matching { pairs: answer }) => {
+            let right = pairs
+                .iter()
+                .filter(|(left, right)| {
+                    answer.iter().any(|(answer_left, answer_right)| {
+                        normalize(answer_left) == normalize(left)
+                            && normalize(answer_right) == normalize(right)
+                    })
+                })
+                .count();
+
+            // every extra pair is mistake
+            let wrong = answer.len().saturating_sub(right);
+
+            right.saturating_sub(wrong) as f64 / pairs.len().max(1) as f64
+        }
+        _ => return None,
+    };
+
+    Some(score)
+}
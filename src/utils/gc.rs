@@ -0,0 +1,165 @@
+use std::{
+    collections::HashSet,
+    env,
+    error::Error,
+    time::{Duration, SystemTime},
+};
+
+use dotenvy::dotenv;
+use sqlx::Postgres;
+
+use crate::{
+    models::image::original_filename,
+    services::storage::{delete_images_db, find_book_files, find_lesson_files, find_unused_images},
+    utils::storage::{BlobKind, BlobStore},
+};
+
+/// Schedule of removing stored files which are not used anymore
+#[derive(Clone, Debug)]
+pub struct GcConfig {
+    /// Pause between runs of background job, `0` disables it
+    pub interval_secs: u64,
+    /// Files changed later than this are kept, upload is attached to book or lesson
+    /// by separate request
+    pub grace_secs: u64,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        GcConfig {
+            interval_secs: 3600,
+            grace_secs: 24 * 3600,
+        }
+    }
+}
+
+impl GcConfig {
+    /// Read schedule from `STORAGE_GC_INTERVAL_SECS` and `STORAGE_GC_GRACE_SECS`
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let default = GcConfig::default();
+        let var = |key: &str, default: u64| {
+            env::var(key)
+                .map(|value| {
+                    value
+                        .parse()
+                        .unwrap_or_else(|_| panic!("{} must be a number", key))
+                })
+                .unwrap_or(default)
+        };
+
+        GcConfig {
+            interval_secs: var("STORAGE_GC_INTERVAL_SECS", default.interval_secs),
+            grace_secs: var("STORAGE_GC_GRACE_SECS", default.grace_secs),
+        }
+    }
+}
+
+/// Result of one run of garbage collection
+#[derive(Clone, Debug, Default)]
+pub struct GcReport {
+    /// Keys of unreferenced files by kind of file
+    pub removed: Vec<(BlobKind, String)>,
+    pub freed_bytes: u64,
+}
+
+/// Names of stored files which can be removed
+enum Garbage {
+    /// Every file except used ones, names of used files
+    Except(HashSet<String>),
+    /// Only listed files, files which are not tracked in database are kept
+    Only(HashSet<String>),
+}
+
+impl Garbage {
+    fn contains(&self, filename: &str) -> bool {
+        match self {
+            Garbage::Except(used) => !used.contains(filename),
+            Garbage::Only(unused) => unused.contains(filename),
+        }
+    }
+}
+
+/// Delete stored files which are older than `grace` and are not referenced
+/// from database, with `dry_run` files are only reported
+pub async fn collect_garbage(
+    store: &dyn BlobStore,
+    pool: &sqlx::Pool<Postgres>,
+    grace: Duration,
+    dry_run: bool,
+) -> Result<GcReport, Box<dyn Error>> {
+    let op = "collect_garbage";
+
+    let mut report = GcReport::default();
+    let deadline = SystemTime::now()
+        .checked_sub(grace)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    for kind in [BlobKind::Book, BlobKind::Lesson, BlobKind::Image] {
+        // files are listed before references, so file saved together with its
+        // reference after listing is never removed
+        let blobs = store.list(kind).await?;
+        let garbage = match kind {
+            BlobKind::Book => Garbage::Except(find_book_files(pool).await?),
+            BlobKind::Lesson => Garbage::Except(find_lesson_files(pool).await?),
+            BlobKind::Image => Garbage::Only(find_unused_images(pool).await?),
+        };
+
+        let mut removed_images = Vec::new();
+
+        for blob in blobs {
+            if blob.meta.modified > deadline {
+                continue;
+            }
+
+            let original = match kind {
+                BlobKind::Image => original_filename(&blob.key),
+                _ => blob.key.as_str(),
+            };
+
+            if !garbage.contains(original) {
+                continue;
+            }
+
+            if !dry_run {
+                match store.delete(kind, &blob.key).await {
+                    Ok(_) => {}
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => {
+                        log::error!(
+                            "{}: cannot delete file: {} of {}, error: {}",
+                            op,
+                            blob.key,
+                            kind.prefix(),
+                            err
+                        );
+
+                        continue;
+                    }
+                }
+            }
+
+            if kind == BlobKind::Image && original == blob.key {
+                removed_images.push(blob.key.clone());
+            }
+
+            report.freed_bytes += blob.meta.size;
+            report.removed.push((kind, blob.key));
+        }
+
+        if !dry_run && !removed_images.is_empty() {
+            delete_images_db(&removed_images, pool).await?;
+        }
+    }
+
+    log::info!(
+        "{}: {} unreferenced files, {} bytes{}",
+        op,
+        report.removed.len(),
+        report.freed_bytes,
+        if dry_run { " (dry run)" } else { "" }
+    );
+
+    Ok(report)
+}
@@ -0,0 +1,341 @@
+use std::{collections::HashSet, fmt, io::Cursor};
+
+use image::{
+    codecs::{gif::GifDecoder, jpeg::JpegEncoder, webp::WebPEncoder},
+    AnimationDecoder, DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader, Limits,
+};
+
+use crate::models::image::{ImageEncoding, ImageSize, ImageType, ImageVariant, ProcessedImage};
+
+/// Maximum size of uploaded image in bytes
+pub const MAX_IMAGE_SIZE: usize = 20 * 1024 * 1024;
+
+/// Maximum width and height of uploaded image in pixels
+pub const MAX_IMAGE_SIDE: u32 = 8192;
+
+/// Quality of JPEG images and thumbnails
+const JPEG_QUALITY: u8 = 85;
+
+/// Elements of SVG which can not run scripts or load other documents
+const SVG_TAGS: [&str; 27] = [
+    "svg",
+    "g",
+    "defs",
+    "title",
+    "desc",
+    "symbol",
+    "use",
+    "path",
+    "rect",
+    "circle",
+    "ellipse",
+    "line",
+    "polyline",
+    "polygon",
+    "text",
+    "tspan",
+    "textPath",
+    "linearGradient",
+    "radialGradient",
+    "stop",
+    "clipPath",
+    "mask",
+    "pattern",
+    "filter",
+    "feGaussianBlur",
+    "feOffset",
+    "feBlend",
+];
+
+/// Geometry and presentation attributes of SVG, event handlers and `style` are removed
+const SVG_ATTRIBUTES: [&str; 61] = [
+    "id",
+    "class",
+    "width",
+    "height",
+    "viewBox",
+    "preserveAspectRatio",
+    "version",
+    "x",
+    "y",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "fx",
+    "fy",
+    "d",
+    "points",
+    "pathLength",
+    "transform",
+    "href",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "clip-rule",
+    "clip-path",
+    "mask",
+    "filter",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-miterlimit",
+    "opacity",
+    "color",
+    "display",
+    "visibility",
+    "offset",
+    "stop-color",
+    "stop-opacity",
+    "gradientUnits",
+    "gradientTransform",
+    "spreadMethod",
+    "patternUnits",
+    "patternTransform",
+    "clipPathUnits",
+    "maskUnits",
+    "font-family",
+    "font-size",
+    "font-weight",
+    "text-anchor",
+    "dx",
+    "dy",
+    "stdDeviation",
+];
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// Reason why uploaded file is rejected
+#[derive(Debug)]
+pub enum InvalidImage {
+    /// Type of file is not in allow-list
+    Unsupported,
+    /// Width or height is greater than `MAX_IMAGE_SIDE`
+    TooLarge,
+    /// GIF with several frames, frames are not kept by re-encoding
+    Animated,
+    Corrupted(ImageError),
+}
+
+impl fmt::Display for InvalidImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidImage::Unsupported => write!(f, "unsupported type of image"),
+            InvalidImage::TooLarge => write!(f, "image is too large"),
+            InvalidImage::Animated => write!(f, "animated images are not supported"),
+            InvalidImage::Corrupted(err) => write!(f, "image is corrupted: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InvalidImage {}
+
+impl From<ImageError> for InvalidImage {
+    fn from(err: ImageError) -> Self {
+        match err {
+            ImageError::Limits(_) => InvalidImage::TooLarge,
+            err => InvalidImage::Corrupted(err),
+        }
+    }
+}
+
+/// Detect type of image by magic bytes, name and declared type of file are not trusted
+pub fn detect_image_type(data: &[u8]) -> Option<ImageType> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(ImageType::Jpeg);
+    }
+
+    if data.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return Some(ImageType::Png);
+    }
+
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some(ImageType::Gif);
+    }
+
+    if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        return Some(ImageType::Webp);
+    }
+
+    // data can be only beginning of file with cut character at the end
+    let text = String::from_utf8_lossy(data);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+
+    if text.starts_with('<') && text.contains("<svg") {
+        return Some(ImageType::Svg);
+    }
+
+    None
+}
+
+/// MIME type of stored image by its content
+pub fn content_type(data: &[u8]) -> &'static str {
+    match detect_image_type(data) {
+        Some(image_type) => image_type.mime_type(),
+        None => "application/octet-stream",
+    }
+}
+
+/// Validate uploaded image and prepare files to save
+///
+/// Raster images are decoded and encoded again with thumbnails and WebP variants,
+/// SVG is sanitized and saved without variants
+pub fn process_image(data: &[u8]) -> Result<ProcessedImage, InvalidImage> {
+    match detect_image_type(data) {
+        Some(ImageType::Svg) => {
+            let text = std::str::from_utf8(data).map_err(|_| InvalidImage::Unsupported)?;
+
+            Ok(ProcessedImage {
+                image_type: ImageType::Svg,
+                width: None,
+                height: None,
+                variants: vec![ImageVariant {
+                    size: ImageSize::Original,
+                    encoding: ImageEncoding::Original,
+                    data: sanitize_svg(text)?.into_bytes(),
+                }],
+            })
+        }
+        Some(image_type) => process_raster(data, image_type),
+        None => Err(InvalidImage::Unsupported),
+    }
+}
+
+/// Remove scripts, event handlers and links to other documents from SVG
+fn sanitize_svg(text: &str) -> Result<String, InvalidImage> {
+    let clean = ammonia::Builder::empty()
+        .tags(HashSet::from(SVG_TAGS))
+        .generic_attributes(HashSet::from(SVG_ATTRIBUTES))
+        .clean_content_tags(HashSet::from(["script", "style", "foreignObject"]))
+        .url_schemes(HashSet::new())
+        .link_rel(None)
+        .strip_comments(true)
+        .clean(text)
+        .to_string();
+
+    let Some(rest) = clean.trim().strip_prefix("<svg") else {
+        return Err(InvalidImage::Unsupported);
+    };
+
+    // namespace is dropped by sanitizer, but browsers render only SVG with namespace
+    Ok(format!("<svg xmlns=\"{}\"{}", SVG_NAMESPACE, rest))
+}
+
+/// Decode raster image and encode original and thumbnails in original format and WebP
+///
+/// Images are encoded from decoded pixels, so EXIF and other metadata are not saved,
+/// orientation from EXIF is applied to pixels before
+fn process_raster(data: &[u8], image_type: ImageType) -> Result<ProcessedImage, InvalidImage> {
+    let format = match image_type {
+        ImageType::Jpeg => ImageFormat::Jpeg,
+        ImageType::Png => ImageFormat::Png,
+        ImageType::Gif => ImageFormat::Gif,
+        ImageType::Webp => ImageFormat::WebP,
+        ImageType::Svg => return Err(InvalidImage::Unsupported),
+    };
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_SIDE);
+    limits.max_image_height = Some(MAX_IMAGE_SIDE);
+
+    if image_type == ImageType::Gif && is_animated_gif(data, limits.clone())? {
+        return Err(InvalidImage::Animated);
+    }
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    // photos stay in JPEG, GIF keeps its palette, everything else is saved without loss
+    let (image_type, format) = match image_type {
+        ImageType::Jpeg => (ImageType::Jpeg, ImageFormat::Jpeg),
+        ImageType::Gif => (ImageType::Gif, ImageFormat::Gif),
+        _ => (ImageType::Png, ImageFormat::Png),
+    };
+
+    let mut variants = Vec::with_capacity((ImageSize::THUMBNAILS.len() + 1) * 2);
+
+    for size in [ImageSize::Original]
+        .into_iter()
+        .chain(ImageSize::THUMBNAILS)
+    {
+        let resized = match size.max_side() {
+            Some(side) if image.width() > side || image.height() > side => {
+                image.thumbnail(side, side)
+            }
+            _ => image.clone(),
+        };
+
+        variants.push(ImageVariant {
+            size,
+            encoding: ImageEncoding::Original,
+            data: encode(&resized, format)?,
+        });
+        variants.push(ImageVariant {
+            size,
+            encoding: ImageEncoding::Webp,
+            data: encode(&resized, ImageFormat::WebP)?,
+        });
+    }
+
+    Ok(ProcessedImage {
+        image_type,
+        width: Some(image.width()),
+        height: Some(image.height()),
+        variants,
+    })
+}
+
+/// Check if GIF has more than one frame, only two frames are decoded
+fn is_animated_gif(data: &[u8], limits: Limits) -> Result<bool, InvalidImage> {
+    let mut decoder = GifDecoder::new(Cursor::new(data))?;
+    decoder.set_limits(limits)?;
+
+    let mut frames = decoder.into_frames();
+    frames.next().transpose()?;
+
+    Ok(frames.next().transpose()?.is_some())
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, ImageError> {
+    let mut buffer = Vec::new();
+
+    match format {
+        ImageFormat::Jpeg => {
+            // JPEG has no alpha channel
+            JpegEncoder::new_with_quality(&mut buffer, JPEG_QUALITY)
+                .encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))?;
+        }
+        ImageFormat::Gif => {
+            // GIF encoder takes only 8-bit pixels
+            DynamicImage::ImageRgba8(image.to_rgba8())
+                .write_to(&mut Cursor::new(&mut buffer), ImageFormat::Gif)?;
+        }
+        ImageFormat::WebP => {
+            let image = if image.color().has_alpha() {
+                DynamicImage::ImageRgba8(image.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+
+            image.write_with_encoder(WebPEncoder::new_lossless(&mut buffer))?;
+        }
+        format => image.write_to(&mut Cursor::new(&mut buffer), format)?,
+    }
+
+    Ok(buffer)
+}
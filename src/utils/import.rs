@@ -0,0 +1,505 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    io::{self, Cursor, Read},
+    net::IpAddr,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use epub::doc::{EpubDoc, NavPoint};
+use url::Url;
+use uuid::Uuid;
+use zip::ZipArchive;
+
+use crate::{
+    models::image::ProcessedImage,
+    utils::image::{process_image, MAX_IMAGE_SIZE},
+};
+
+pub type ImportError = Box<dyn Error + Send + Sync>;
+
+/// Maximal size of unpacked files of imported document
+pub const MAX_IMPORT_SIZE: u64 = 50 * 1024 * 1024;
+
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "svg", "webp"];
+
+/// Timeout of downloading of one image of web article
+const IMAGE_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximal number of images of web article which are downloaded
+const MAX_REMOTE_IMAGES: usize = 50;
+
+/// Total time of downloading of all images of web article
+const IMAGES_DOWNLOAD_TIME: Duration = Duration::from_secs(60);
+
+/// Kind of imported document, it is chosen by extension of file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportFormat {
+    /// Every chapter of book is lesson
+    Epub,
+    /// Zip of markdown files with images, every file is lesson in order of file names
+    MarkdownZip,
+    /// Web article is one lesson
+    Html,
+}
+
+impl ImportFormat {
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        match extension(filename).as_str() {
+            "epub" => Some(ImportFormat::Epub),
+            "zip" => Some(ImportFormat::MarkdownZip),
+            "html" | "htm" | "xhtml" => Some(ImportFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ImportedLesson {
+    pub title: String,
+    /// Markdown text, images refer to image store by `image:` prefix
+    pub text: String,
+}
+
+/// Validated image which must be saved into image store by `filename`
+#[derive(Debug)]
+pub struct ImportedImage {
+    pub filename: String,
+    /// Path of image in document or its URL
+    pub original_name: String,
+    pub image: ProcessedImage,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportedDocument {
+    pub lessons: Vec<ImportedLesson>,
+    pub images: Vec<ImportedImage>,
+}
+
+impl ImportedDocument {
+    /// Keep image to move into image store, returns reference to it for markdown
+    ///
+    /// Image is checked like uploaded one, invalid image is skipped and its link is kept
+    fn add_image(&mut self, data: &[u8], original_name: &str) -> Option<String> {
+        let image = match process_image(data) {
+            Ok(image) => image,
+            Err(err) => {
+                log::warn!(
+                    "import: image: {} is skipped, error: {}",
+                    original_name,
+                    err
+                );
+
+                return None;
+            }
+        };
+
+        let filename = Uuid::new_v4().to_string();
+        let reference = format!("image:{}", filename);
+
+        self.images.push(ImportedImage {
+            filename,
+            original_name: original_name.to_string(),
+            image,
+        });
+
+        Some(reference)
+    }
+
+    fn add_lesson(&mut self, title: Option<String>, text: String, fallback_title: String) {
+        if text.trim().is_empty() {
+            return;
+        }
+
+        let title = title
+            .filter(|title| !title.trim().is_empty())
+            .or_else(|| first_heading(&text))
+            .unwrap_or(fallback_title);
+
+        self.lessons.push(ImportedLesson {
+            title: title.trim().to_string(),
+            text,
+        });
+    }
+}
+
+fn extension(filename: &str) -> String {
+    Path::new(filename)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn file_stem(filename: &str) -> String {
+    Path::new(filename)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn is_image(filename: &str) -> bool {
+    IMAGE_EXTENSIONS.contains(&extension(filename).as_str())
+}
+
+/// Directory of file inside of archive
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/')
+        .map(|(dir, _)| dir)
+        .unwrap_or_default()
+}
+
+/// Resolve link relative to directory of document: `../images/a.png`
+fn resolve_path(dir: &str, link: &str) -> String {
+    let link = link.split(['#', '?']).next().unwrap_or_default();
+
+    let mut parts: Vec<&str> = if link.starts_with('/') {
+        Vec::new()
+    } else {
+        dir.split('/').filter(|part| !part.is_empty()).collect()
+    };
+
+    for part in link.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    parts.join("/")
+}
+
+/// Replace links of markdown images `![alt](link)` by result of `replace`
+fn rewrite_images(text: &str, mut replace: impl FnMut(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("![") {
+        let Some(link_start) = rest[start..].find("](").map(|pos| start + pos + 2) else {
+            break;
+        };
+        let Some(link_end) = rest[link_start..].find(')').map(|pos| link_start + pos) else {
+            break;
+        };
+
+        // link can be followed by title: ![alt](link "title")
+        let inner = &rest[link_start..link_end];
+        let link = inner.split_whitespace().next().unwrap_or_default();
+        let link_start = link_start + inner.find(link).unwrap_or(0);
+
+        result.push_str(&rest[..link_start]);
+        match replace(link) {
+            Some(new_link) => result.push_str(&new_link),
+            None => result.push_str(link),
+        }
+
+        rest = &rest[link_start + link.len()..];
+    }
+
+    result.push_str(rest);
+
+    result
+}
+
+fn first_heading(text: &str) -> Option<String> {
+    text.lines()
+        .map(str::trim)
+        .find(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#').trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+fn html_title(html: &str) -> Option<String> {
+    // offsets of ASCII lowercase are the same as offsets of original
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title>")?;
+
+    Some(html[start..end].trim().to_string())
+}
+
+/// Titles of chapters from table of contents by path of chapter
+fn collect_titles(toc: &[NavPoint], titles: &mut HashMap<String, String>) {
+    for nav in toc {
+        let path = nav.content.to_string_lossy().replace('\\', "/");
+        let path = path.split('#').next().unwrap_or_default().to_string();
+
+        titles.entry(path).or_insert_with(|| nav.label.clone());
+        collect_titles(&nav.children, titles);
+    }
+}
+
+/// Read file of archive, `unpacked_size` is real size of files read before
+///
+/// Declared size of file is not trusted, reading stops after limit is exceeded
+fn read_limited(file: impl Read, unpacked_size: &mut u64) -> Result<Vec<u8>, ImportError> {
+    let mut data = Vec::new();
+    file.take(MAX_IMPORT_SIZE - *unpacked_size + 1)
+        .read_to_end(&mut data)?;
+
+    *unpacked_size += data.len() as u64;
+    if *unpacked_size > MAX_IMPORT_SIZE {
+        return Err("archive is too large".into());
+    }
+
+    Ok(data)
+}
+
+/// Check real size of all files of archive before it is opened by other reader
+fn check_unpacked_size(data: &[u8]) -> Result<(), ImportError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let mut unpacked_size = 0;
+
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+
+        unpacked_size += io::copy(
+            &mut file.take(MAX_IMPORT_SIZE - unpacked_size + 1),
+            &mut io::sink(),
+        )?;
+        if unpacked_size > MAX_IMPORT_SIZE {
+            return Err("archive is too large".into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Split EPUB by chapters of spine, images of book are moved into image store
+fn import_epub(data: Vec<u8>) -> Result<ImportedDocument, ImportError> {
+    check_unpacked_size(&data)?;
+
+    let mut doc = EpubDoc::from_reader(Cursor::new(data))?;
+    let mut document = ImportedDocument::default();
+
+    let mut images = HashMap::new();
+    let image_ids: Vec<String> = doc
+        .resources
+        .iter()
+        .filter(|(_, resource)| resource.mime.starts_with("image/"))
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    for id in image_ids {
+        let path = doc.resources[&id].path.to_string_lossy().replace('\\', "/");
+        if let Some(reference) = doc
+            .get_resource(&id)
+            .and_then(|(data, _)| document.add_image(&data, &path))
+        {
+            images.insert(path, reference);
+        }
+    }
+
+    let mut titles = HashMap::new();
+    collect_titles(&doc.toc, &mut titles);
+
+    for (number, item) in doc.spine.clone().iter().enumerate() {
+        let Some(resource) = doc.resources.get(&item.idref).cloned() else {
+            continue;
+        };
+        let Some((html, _)) = doc.get_resource_str(&item.idref) else {
+            continue;
+        };
+
+        let path = resource.path.to_string_lossy().replace('\\', "/");
+        let dir = parent_dir(&path);
+
+        let text = rewrite_images(&html2md::parse_html(&html), |link| {
+            images.get(&resolve_path(dir, link)).cloned()
+        });
+
+        document.add_lesson(
+            titles.get(&path).cloned().or_else(|| html_title(&html)),
+            text,
+            format!("Chapter {}", number + 1),
+        );
+    }
+
+    Ok(document)
+}
+
+/// Every markdown file of archive is lesson, images are moved into image store
+fn import_markdown_zip(data: Vec<u8>) -> Result<ImportedDocument, ImportError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let mut document = ImportedDocument::default();
+
+    let mut images = HashMap::new();
+    let mut texts = Vec::new();
+    let mut unpacked_size = 0;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+
+        if file.is_dir() {
+            continue;
+        }
+
+        let name = file.name().replace('\\', "/");
+        let is_markdown = matches!(extension(&name).as_str(), "md" | "markdown");
+
+        if !is_markdown && !is_image(&name) {
+            continue;
+        }
+
+        let data = read_limited(&mut file, &mut unpacked_size)?;
+
+        if is_markdown {
+            texts.push((name, String::from_utf8(data)?));
+        } else if let Some(reference) = document.add_image(&data, &name) {
+            images.insert(name, reference);
+        }
+    }
+
+    texts.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+    for (name, text) in texts {
+        let dir = parent_dir(&name);
+
+        let text = rewrite_images(&text, |link| images.get(&resolve_path(dir, link)).cloned());
+
+        document.add_lesson(None, text, file_stem(&name));
+    }
+
+    Ok(document)
+}
+
+/// Check that address of image is on public network, so import can not
+/// be used to read services inside of our network
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || ip.octets()[0] == 0
+                || ip.octets()[0] >= 240
+                // shared address space 100.64.0.0/10
+                || (ip.octets()[0] == 100 && ip.octets()[1] & 0xC0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // unique local fc00::/7 and link local fe80::/10
+                    || first & 0xFE00 == 0xFC00
+                    || first & 0xFFC0 == 0xFE80
+                    // NAT64 64:ff9b::/96 translates to IPv4 addresses
+                    || ip.segments()[..6] == [0x64, 0xFF9B, 0, 0, 0, 0])
+            }
+        },
+    }
+}
+
+/// Read image of web article from `data:` URI or download it by absolute URL,
+/// relative links of uploaded file can not be resolved, download is not started after deadline
+fn fetch_image(link: &str, deadline: Instant) -> Option<Vec<u8>> {
+    if let Some(rest) = link.strip_prefix("data:") {
+        let (_, encoded) = rest.split_once(";base64,")?;
+
+        return STANDARD.decode(encoded.trim()).ok();
+    }
+
+    let url = Url::parse(link).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+
+    let remaining = deadline.checked_duration_since(Instant::now())?;
+
+    let addrs: Vec<_> = url.socket_addrs(|| None).ok()?;
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public_address(addr.ip())) {
+        log::warn!("import: image: {} is not on public network", link);
+
+        return None;
+    }
+
+    // address is checked once, so redirects to other hosts are not followed
+    let response = ureq::AgentBuilder::new()
+        .timeout(IMAGE_DOWNLOAD_TIMEOUT.min(remaining))
+        .redirects(0)
+        .resolver(move |_: &str| Ok(addrs.clone()))
+        .build()
+        .get(url.as_str())
+        .call()
+        .map_err(|err| log::warn!("import: cannot download image: {}, error: {}", link, err))
+        .ok()?;
+
+    let mut data = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_IMAGE_SIZE as u64 + 1)
+        .read_to_end(&mut data)
+        .ok()?;
+
+    if data.len() > MAX_IMAGE_SIZE {
+        return None;
+    }
+
+    Some(data)
+}
+
+/// Web article is imported as one lesson, its images are downloaded into image store
+fn import_html(data: Vec<u8>, filename: &str) -> Result<ImportedDocument, ImportError> {
+    let html = String::from_utf8(data)?;
+    let mut document = ImportedDocument::default();
+
+    let mut images: HashMap<String, Option<String>> = HashMap::new();
+    let mut unpacked_size = html.len() as u64;
+    let mut remote_images = 0;
+    let deadline = Instant::now() + IMAGES_DOWNLOAD_TIME;
+
+    let text = rewrite_images(&html2md::parse_html(&html), |link| {
+        if let Some(reference) = images.get(link) {
+            return reference.clone();
+        }
+
+        if !link.starts_with("data:") {
+            if remote_images >= MAX_REMOTE_IMAGES {
+                return None;
+            }
+            remote_images += 1;
+        }
+
+        let reference = match fetch_image(link, deadline) {
+            Some(data) if unpacked_size + data.len() as u64 <= MAX_IMPORT_SIZE => {
+                unpacked_size += data.len() as u64;
+                document.add_image(&data, link)
+            }
+            _ => None,
+        };
+
+        images.insert(link.to_string(), reference.clone());
+
+        reference
+    });
+
+    document.add_lesson(html_title(&html), text, file_stem(filename));
+
+    Ok(document)
+}
+
+/// Convert document into markdown lessons
+pub fn import_document(
+    format: ImportFormat,
+    filename: &str,
+    data: Vec<u8>,
+) -> Result<ImportedDocument, ImportError> {
+    match format {
+        ImportFormat::Epub => import_epub(data),
+        ImportFormat::MarkdownZip => import_markdown_zip(data),
+        ImportFormat::Html => import_html(data, filename),
+    }
+}
@@ -1,18 +1,123 @@
+use std::{env, fs};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use dotenvy::dotenv;
 use jwt_simple::prelude::{Duration, VerificationOptions, *};
+use uuid::Uuid;
 
-use crate::extractors::jwt_cred::JwtCred;
+use crate::{
+    extractors::jwt_cred::JwtCred,
+    models::auth::{Jwk, JwkSet},
+};
 
 pub mod scopes {
     pub const ACCESS: &str = "access";
     pub const REFRESH: &str = "refresh";
+    pub const TWO_FACTOR: &str = "2fa";
+}
+
+pub const ACCESS_DURATION_MIN: u64 = 20;
+pub const REFRESH_DURATION_DAY: u64 = 14;
+
+/// Signing key with its public part for verification
+pub enum JwtKey {
+    HS256(HS256Key),
+    RS256(RS256KeyPair, RS256PublicKey),
+    EdDSA(Ed25519KeyPair, Ed25519PublicKey),
+}
+
+/// Key identified by `kid` header of token
+pub struct KeyEntry {
+    pub kid: String,
+    pub key: JwtKey,
 }
 
-#[derive(Clone, Debug)]
+/// Sign tokens with active key and verify them with any known key
+///
+/// Keys are loaded once on start, several keys allow to rotate them
+/// without invalidating tokens signed by previous key
 pub struct JwtUtil {
-    pub key: HS256Key,
+    pub keys: Vec<KeyEntry>,
+    pub active_kid: String,
 }
 
 impl JwtUtil {
+    /// Load keys from environment
+    ///
+    /// `JWT_ALG=HS256` (default) use secret from `JWT_KEY`
+    ///
+    /// `JWT_ALG=RS256` or `JWT_ALG=EdDSA` load every `{kid}.pem` private key
+    /// from `JWT_KEYS_DIR`, tokens are signed by `JWT_ACTIVE_KID` key
+    /// or by the last key in alphabetical order
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let alg = env::var("JWT_ALG").unwrap_or("HS256".to_string());
+
+        if alg == "HS256" {
+            let key_srt = env::var("JWT_KEY").expect("JWT_KEY is not set in .env file");
+            let kid = env::var("JWT_ACTIVE_KID").unwrap_or("default".to_string());
+
+            return JwtUtil {
+                keys: vec![KeyEntry {
+                    kid: kid.clone(),
+                    key: JwtKey::HS256(HS256Key::from_bytes(key_srt.as_bytes()).with_key_id(&kid)),
+                }],
+                active_kid: kid,
+            };
+        }
+
+        let dir = env::var("JWT_KEYS_DIR").expect("JWT_KEYS_DIR is not set in .env file");
+
+        let mut keys: Vec<KeyEntry> = fs::read_dir(&dir)
+            .expect("can not read JWT_KEYS_DIR")
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "pem"))
+            .map(|path| {
+                let kid = path.file_stem().unwrap().to_string_lossy().to_string();
+                let pem = fs::read_to_string(&path).expect("can not read JWT key");
+
+                let key = match alg.as_str() {
+                    "RS256" => {
+                        let pair = RS256KeyPair::from_pem(&pem)
+                            .expect("invalid RS256 key")
+                            .with_key_id(&kid);
+                        let public = pair.public_key().with_key_id(&kid);
+
+                        JwtKey::RS256(pair, public)
+                    }
+                    "EdDSA" => {
+                        let pair = Ed25519KeyPair::from_pem(&pem)
+                            .expect("invalid EdDSA key")
+                            .with_key_id(&kid);
+                        let public = pair.public_key().with_key_id(&kid);
+
+                        JwtKey::EdDSA(pair, public)
+                    }
+                    _ => panic!("unsupported JWT_ALG: {}", alg),
+                };
+
+                KeyEntry { kid, key }
+            })
+            .collect();
+
+        keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+
+        let active_kid = env::var("JWT_ACTIVE_KID")
+            .ok()
+            .or(keys.last().map(|entry| entry.kid.clone()))
+            .expect("JWT_KEYS_DIR does not contain keys");
+
+        if !keys.iter().any(|entry| entry.kid == active_kid) {
+            panic!(
+                "JWT_ACTIVE_KID: {} is not found in JWT_KEYS_DIR",
+                active_kid
+            );
+        }
+
+        JwtUtil { keys, active_kid }
+    }
+
     pub fn get_claims(&self, token: &str, scope_check: &str) -> Option<JwtCred> {
         match self.decode_token(token) {
             Ok(claims) => {
@@ -26,24 +131,90 @@ impl JwtUtil {
         }
     }
 
+    /// Sign the claims by active key, every token get unique `jti`
     pub fn encode_token(
         &self,
         claims: JwtCred,
         duration: Duration,
     ) -> Result<String, jwt_simple::Error> {
-        let claims = Claims::with_custom_claims(claims, duration);
+        let claims =
+            Claims::with_custom_claims(claims, duration).with_jwt_id(Uuid::new_v4().to_string());
+
+        let entry = self
+            .find_key(&self.active_kid)
+            .ok_or(jwt_simple::Error::msg("active key is not found"))?;
 
-        self.key.authenticate(claims)
+        match &entry.key {
+            JwtKey::HS256(key) => key.authenticate(claims),
+            JwtKey::RS256(pair, _) => pair.sign(claims),
+            JwtKey::EdDSA(pair, _) => pair.sign(claims),
+        }
     }
 
+    /// Verify token by key from `kid` header,
+    /// tokens without `kid` are verified by active key
     pub fn decode_token(&self, token: &str) -> Result<JwtCred, jwt_simple::Error> {
-        match self
-            .key
-            .verify_token::<JwtCred>(token, Some(self.get_options()))
-        {
-            Ok(data) => Ok(data.custom),
-            Err(err) => Err(err),
-        }
+        let metadata = Token::decode_metadata(token)?;
+        let kid = metadata.key_id().unwrap_or(&self.active_kid);
+
+        let entry = self
+            .find_key(kid)
+            .ok_or(jwt_simple::Error::msg("unknown key id"))?;
+
+        let options = Some(self.get_options());
+
+        let data = match &entry.key {
+            JwtKey::HS256(key) => key.verify_token::<JwtCred>(token, options),
+            JwtKey::RS256(_, public) => public.verify_token::<JwtCred>(token, options),
+            JwtKey::EdDSA(_, public) => public.verify_token::<JwtCred>(token, options),
+        }?;
+
+        let mut claims = data.custom;
+        claims.jti = data.jwt_id;
+        claims.expires_at = data.expires_at.map(|exp| exp.as_secs());
+
+        Ok(claims)
+    }
+
+    /// Public keys in JWKS format, symmetric keys are never published
+    pub fn jwks(&self) -> JwkSet {
+        let keys = self
+            .keys
+            .iter()
+            .filter_map(|entry| match &entry.key {
+                JwtKey::HS256(_) => None,
+                JwtKey::RS256(_, public) => {
+                    let components = public.to_components();
+
+                    Some(Jwk {
+                        kty: "RSA".to_string(),
+                        alg: "RS256".to_string(),
+                        key_use: "sig".to_string(),
+                        kid: entry.kid.clone(),
+                        n: Some(URL_SAFE_NO_PAD.encode(components.n)),
+                        e: Some(URL_SAFE_NO_PAD.encode(components.e)),
+                        crv: None,
+                        x: None,
+                    })
+                }
+                JwtKey::EdDSA(_, public) => Some(Jwk {
+                    kty: "OKP".to_string(),
+                    alg: "EdDSA".to_string(),
+                    key_use: "sig".to_string(),
+                    kid: entry.kid.clone(),
+                    n: None,
+                    e: None,
+                    crv: Some("Ed25519".to_string()),
+                    x: Some(URL_SAFE_NO_PAD.encode(public.to_bytes())),
+                }),
+            })
+            .collect();
+
+        JwkSet { keys }
+    }
+
+    fn find_key(&self, kid: &str) -> Option<&KeyEntry> {
+        self.keys.iter().find(|entry| entry.kid == kid)
     }
 
     fn get_options(&self) -> VerificationOptions {
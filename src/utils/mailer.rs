@@ -0,0 +1,121 @@
+use std::{env, error::Error, fs, path::PathBuf, sync::Arc};
+
+use actix_web::web;
+use chrono::Utc;
+use dotenvy::dotenv;
+use lettre::{transport::smtp::authentication::Credentials, Message, SmtpTransport, Transport};
+use uuid::Uuid;
+
+/// Email that should be delivered to the user
+#[derive(Clone, Debug)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Transport for outgoing emails
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: &Mail) -> Result<(), Box<dyn Error>>;
+}
+
+/// Send email in thread pool, transports are blocking and must not stop async workers
+pub async fn send_mail(mailer: &Arc<dyn Mailer>, mail: Mail) -> Result<(), Box<dyn Error>> {
+    let mailer = Arc::clone(mailer);
+
+    web::block(move || mailer.send(&mail).map_err(|err| err.to_string())).await??;
+
+    Ok(())
+}
+
+/// Send emails through SMTP server
+pub struct SmtpMailer {
+    pub from: String,
+    pub transport: SmtpTransport,
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, mail: &Mail) -> Result<(), Box<dyn Error>> {
+        let message = Message::builder()
+            .from(self.from.parse()?)
+            .to(mail.to.parse()?)
+            .subject(mail.subject.clone())
+            .body(mail.body.clone())?;
+
+        self.transport.send(&message)?;
+
+        Ok(())
+    }
+}
+
+/// Local stand-in for SMTP, write every email into file in directory
+pub struct FileMailer {
+    pub from: String,
+    pub dir: PathBuf,
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, mail: &Mail) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.dir)?;
+
+        let filename = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%d%H%M%S"),
+            Uuid::new_v4()
+        );
+
+        let content = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\n\r\n{}\r\n",
+            self.from,
+            mail.to,
+            mail.subject,
+            Utc::now().to_rfc2822(),
+            mail.body
+        );
+
+        fs::write(self.dir.join(filename), content)?;
+
+        Ok(())
+    }
+}
+
+/// Build mailer from environment
+///
+/// `MAIL_TRANSPORT=smtp` use `SMTP_HOST`, `SMTP_PORT`, `SMTP_USER`, `SMTP_PASSWORD`
+/// and `SMTP_TLS`, otherwise emails are written into `MAIL_DIR`
+pub fn mailer_from_env() -> Arc<dyn Mailer> {
+    dotenv().ok();
+
+    let from = env::var("MAIL_FROM").unwrap_or("ReadCraft <noreply@readcraft.local>".to_string());
+
+    match env::var("MAIL_TRANSPORT").unwrap_or_default().as_str() {
+        "smtp" => {
+            let host = env::var("SMTP_HOST").expect("SMTP_HOST must be set");
+            let port: u16 = env::var("SMTP_PORT")
+                .unwrap_or("25".to_string())
+                .parse()
+                .expect("SMTP_PORT must be a number");
+            let tls = env::var("SMTP_TLS").unwrap_or_default() == "true";
+
+            let builder = if tls {
+                SmtpTransport::relay(&host).expect("invalid SMTP_HOST")
+            } else {
+                SmtpTransport::builder_dangerous(&host)
+            };
+
+            let builder = match (env::var("SMTP_USER"), env::var("SMTP_PASSWORD")) {
+                (Ok(user), Ok(password)) => builder.credentials(Credentials::new(user, password)),
+                _ => builder,
+            };
+
+            Arc::new(SmtpMailer {
+                from,
+                transport: builder.port(port).build(),
+            })
+        }
+        _ => Arc::new(FileMailer {
+            from,
+            dir: PathBuf::from(env::var("MAIL_DIR").unwrap_or("./uploads/mail".to_string())),
+        }),
+    }
+}
@@ -0,0 +1,215 @@
+use pulldown_cmark::{
+    escape::{escape_href, escape_html},
+    html, Event, Options, Parser, Tag,
+};
+
+use crate::models::vocabulary::LessonWord;
+
+/// Reference to file in image store: `![alt](image:filename)`
+const IMAGE_PREFIX: &str = "image:";
+/// Audio embed from image store: `![](audio:filename "caption")`
+const AUDIO_PREFIX: &str = "audio:";
+/// Path of files in image store
+const IMAGE_PATH: &str = "/api/image/";
+const AUDIO_EXTENSIONS: [&str; 4] = [".mp3", ".ogg", ".wav", ".m4a"];
+
+enum Media {
+    Image(String),
+    Audio(String),
+}
+
+/// Resolve references to image store, links to audio files are embedded as player
+fn resolve_media(url: &str) -> Media {
+    if let Some(filename) = url.strip_prefix(AUDIO_PREFIX) {
+        return Media::Audio(format!("{}{}", IMAGE_PATH, filename));
+    }
+
+    if let Some(filename) = url.strip_prefix(IMAGE_PREFIX) {
+        return Media::Image(format!("{}{}", IMAGE_PATH, filename));
+    }
+
+    let path = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    if AUDIO_EXTENSIONS.iter().any(|ext| path.ends_with(ext)) {
+        Media::Audio(url.to_string())
+    } else {
+        Media::Image(url.to_string())
+    }
+}
+
+/// Names of files from image store which are used in lesson text
+///
+/// Paths of image store are searched in whole text, so images of raw HTML
+/// and absolute URLs are found too
+pub fn media_references(text: &str) -> Vec<String> {
+    let mut references: Vec<String> = Parser::new(text)
+        .filter_map(|event| match event {
+            Event::Start(Tag::Image(_, url, _) | Tag::Link(_, url, _)) => url
+                .strip_prefix(IMAGE_PREFIX)
+                .or_else(|| url.strip_prefix(AUDIO_PREFIX))
+                .and_then(media_filename),
+            _ => None,
+        })
+        .collect();
+
+    references.extend(
+        text.match_indices(IMAGE_PATH)
+            .filter_map(|(start, _)| media_filename(&text[start + IMAGE_PATH.len()..])),
+    );
+
+    references
+}
+
+/// Name of file at the beginning of reference, query and end of sentence are cut
+fn media_filename(reference: &str) -> Option<String> {
+    let end = reference
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(reference.len());
+
+    Some(reference[..end].trim_end_matches('.'))
+        .filter(|filename| !filename.is_empty())
+        .map(|filename| filename.to_string())
+}
+
+fn push_escaped(html: &mut String, text: &str) {
+    // writing into string can not fail
+    escape_html(html, text).unwrap();
+}
+
+fn audio_html(url: &str, title: &str) -> String {
+    let mut html = String::from("<audio controls src=\"");
+    escape_href(&mut html, url).unwrap();
+    html.push('"');
+
+    if !title.is_empty() {
+        html.push_str(" title=\"");
+        push_escaped(&mut html, title);
+        html.push('"');
+    }
+
+    html.push_str("></audio>");
+
+    html
+}
+
+/// Highlight words written as `[[word]]`, translation is taken from vocabulary of lesson
+fn highlight_vocabulary<'a>(text: String, vocabulary: &[LessonWord]) -> Event<'a> {
+    if !text.contains("[[") {
+        return Event::Text(text.into());
+    }
+
+    let mut html = String::new();
+    let mut rest = text.as_str();
+
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let word = &rest[start + 2..start + 2 + len];
+
+        push_escaped(&mut html, &rest[..start]);
+        html.push_str("<mark class=\"vocabulary\"");
+
+        let translation = vocabulary
+            .iter()
+            .find(|lesson_word| {
+                lesson_word.word.trim().to_lowercase() == word.trim().to_lowercase()
+            })
+            .map(|lesson_word| &lesson_word.translation);
+
+        if let Some(translation) = translation {
+            html.push_str(" title=\"");
+            push_escaped(&mut html, translation);
+            html.push('"');
+        }
+
+        html.push('>');
+        push_escaped(&mut html, word);
+        html.push_str("</mark>");
+
+        rest = &rest[start + len + 4..];
+    }
+
+    push_escaped(&mut html, rest);
+
+    Event::Html(html.into())
+}
+
+/// Render lesson text to sanitized HTML
+///
+/// CommonMark with tables and footnotes, `[[word]]` highlights word of vocabulary,
+/// images and audio can refer to image store by `image:` and `audio:` prefixes
+pub fn render_markdown(text: &str, vocabulary: &[LessonWord]) -> String {
+    let parser = Parser::new_ext(text, Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES);
+
+    let mut events = Vec::new();
+    // text is split by parser on brackets, so it is collected before highlighting
+    let mut text_buf = String::new();
+    let mut in_code_block = false;
+    let mut in_audio = false;
+
+    for event in parser {
+        // alt text of audio is not rendered
+        if in_audio {
+            if let Event::End(Tag::Image(..)) = event {
+                in_audio = false;
+            }
+            continue;
+        }
+
+        if let Event::Text(part) = &event {
+            if !in_code_block {
+                text_buf.push_str(part);
+                continue;
+            }
+        }
+
+        if !text_buf.is_empty() {
+            events.push(highlight_vocabulary(
+                std::mem::take(&mut text_buf),
+                vocabulary,
+            ));
+        }
+
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                events.push(event);
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                in_code_block = false;
+                events.push(event);
+            }
+            Event::Start(Tag::Image(link_type, url, title)) => match resolve_media(&url) {
+                Media::Image(url) => {
+                    events.push(Event::Start(Tag::Image(link_type, url.into(), title)));
+                }
+                Media::Audio(url) => {
+                    events.push(Event::Html(audio_html(&url, &title).into()));
+                    in_audio = true;
+                }
+            },
+            event => events.push(event),
+        }
+    }
+
+    if !text_buf.is_empty() {
+        events.push(highlight_vocabulary(text_buf, vocabulary));
+    }
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events.into_iter());
+
+    ammonia::Builder::default()
+        .add_tags(&["audio", "mark"])
+        .add_tag_attributes("audio", &["controls", "src"])
+        .add_tag_attributes("mark", &["class"])
+        .add_tag_attributes("sup", &["class"])
+        .add_tag_attributes("div", &["class", "id"])
+        .clean(&unsafe_html)
+        .to_string()
+}
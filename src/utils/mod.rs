@@ -1 +1,16 @@
+pub mod access;
+pub mod denylist;
+pub mod diff;
+pub mod download;
+pub mod exercise;
+pub mod gc;
+pub mod image;
+pub mod import;
 pub mod jwt;
+pub mod mailer;
+pub mod markdown;
+pub mod oidc;
+pub mod policy;
+pub mod rate_limit;
+pub mod storage;
+pub mod totp;
@@ -0,0 +1,204 @@
+use std::{env, error::Error, time::Duration};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use crypto::{digest::Digest, sha2::Sha256};
+use dotenvy::dotenv;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+use url::Url;
+
+/// Lifetime of authorization request (state and PKCE verifier) in seconds
+pub const AUTH_REQUEST_TTL_SEC: u64 = 600;
+
+/// Time of whole request to provider, including connection
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// External identity provider with authorization-code flow
+#[derive(Clone, Debug)]
+pub struct OidcProvider {
+    pub name: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub authorize_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    pub redirect_uri: String,
+    pub scopes: String,
+    /// Treat email from provider as verified even if `email_verified` claim is missing
+    pub trust_email: bool,
+    agent: ureq::Agent,
+}
+
+/// User returned by userinfo endpoint of provider
+#[derive(Clone, Debug)]
+pub struct ExternalUser {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+impl OidcProvider {
+    /// Build URL of provider login page with state and PKCE challenge
+    pub fn authorize_url(&self, state: &str, challenge: &str) -> Result<String, url::ParseError> {
+        let url = Url::parse_with_params(
+            &self.authorize_url,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.client_id),
+                ("redirect_uri", &self.redirect_uri),
+                ("scope", &self.scopes),
+                ("state", state),
+                ("code_challenge", challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )?;
+
+        Ok(url.to_string())
+    }
+
+    /// Exchange authorization code to access token of provider
+    pub fn exchange_code(&self, code: &str, verifier: &str) -> Result<String, Box<dyn Error>> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.redirect_uri),
+            ("client_id", &self.client_id),
+            ("code_verifier", verifier),
+        ];
+
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let res: TokenResponse = self
+            .agent
+            .post(&self.token_url)
+            .set("Accept", "application/json")
+            .send_form(&form)?
+            .into_json()?;
+
+        Ok(res.access_token)
+    }
+
+    /// Get user from userinfo endpoint
+    ///
+    /// Support OIDC claims (`sub`, `email_verified`, `name`)
+    /// and GitHub like responses (`id`, `login`)
+    pub fn user_info(&self, access_token: &str) -> Result<ExternalUser, Box<dyn Error>> {
+        let info: serde_json::Value = self
+            .agent
+            .get(&self.userinfo_url)
+            .set("Authorization", &format!("Bearer {}", access_token))
+            .set("Accept", "application/json")
+            .call()?
+            .into_json()?;
+
+        let subject = match info.get("sub").or(info.get("id")) {
+            Some(serde_json::Value::String(sub)) => sub.clone(),
+            Some(serde_json::Value::Number(id)) => id.to_string(),
+            _ => return Err("userinfo does not contain subject".into()),
+        };
+
+        let email = info
+            .get("email")
+            .and_then(|email| email.as_str())
+            .map(|email| email.to_string());
+
+        let email_verified = info
+            .get("email_verified")
+            .and_then(|verified| verified.as_bool())
+            .unwrap_or(self.trust_email);
+
+        let name = ["preferred_username", "login", "name"]
+            .iter()
+            .find_map(|key| info.get(*key).and_then(|name| name.as_str()))
+            .map(|name| name.to_string());
+
+        Ok(ExternalUser {
+            subject,
+            email,
+            email_verified,
+            name,
+        })
+    }
+}
+
+/// All configured providers
+#[derive(Clone, Debug, Default)]
+pub struct OidcConfig {
+    pub providers: Vec<OidcProvider>,
+}
+
+impl OidcConfig {
+    /// Read comma separated list of providers from `OIDC_PROVIDERS`
+    ///
+    /// Every provider is configured by `OIDC_{NAME}_CLIENT_ID`, `OIDC_{NAME}_CLIENT_SECRET`,
+    /// `OIDC_{NAME}_AUTHORIZE_URL`, `OIDC_{NAME}_TOKEN_URL`, `OIDC_{NAME}_USERINFO_URL`,
+    /// `OIDC_{NAME}_REDIRECT_URI`, `OIDC_{NAME}_SCOPES` and `OIDC_{NAME}_TRUST_EMAIL`
+    ///
+    /// Example: `OIDC_PROVIDERS=google,github`
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let providers = env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let prefix = format!("OIDC_{}", name.to_uppercase());
+                let var = |key: &str| {
+                    env::var(format!("{}_{}", prefix, key))
+                        .unwrap_or_else(|_| panic!("{}_{} must be set", prefix, key))
+                };
+
+                OidcProvider {
+                    client_id: var("CLIENT_ID"),
+                    client_secret: env::var(format!("{}_CLIENT_SECRET", prefix)).ok(),
+                    authorize_url: var("AUTHORIZE_URL"),
+                    token_url: var("TOKEN_URL"),
+                    userinfo_url: var("USERINFO_URL"),
+                    redirect_uri: var("REDIRECT_URI"),
+                    scopes: env::var(format!("{}_SCOPES", prefix))
+                        .unwrap_or("openid email profile".to_string()),
+                    trust_email: env::var(format!("{}_TRUST_EMAIL", prefix)).unwrap_or_default()
+                        == "true",
+                    name,
+                    agent: ureq::AgentBuilder::new().timeout(PROVIDER_TIMEOUT).build(),
+                }
+            })
+            .collect();
+
+        OidcConfig { providers }
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&OidcProvider> {
+        self.providers.iter().find(|provider| provider.name == name)
+    }
+}
+
+/// Random string for `state` parameter and PKCE verifier
+pub fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// PKCE S256 challenge for verifier
+pub fn pkce_challenge(verifier: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input_str(verifier);
+
+    let mut digest = [0u8; 32];
+    hasher.result(&mut digest);
+
+    URL_SAFE_NO_PAD.encode(digest)
+}
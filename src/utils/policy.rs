@@ -0,0 +1,63 @@
+use std::{env, str::FromStr};
+
+use dotenvy::dotenv;
+use sqlx::Postgres;
+use strum::{Display, EnumString};
+
+use crate::services::user::is_email_verified;
+
+/// Actions that can be forbidden for users with unverified email
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq)]
+#[strum(serialize_all = "snake_case")]
+pub enum UnverifiedAction {
+    CreateCourse,
+    InviteLink,
+    PublishCourse,
+}
+
+/// List of actions which are not available until user verify email
+#[derive(Clone, Debug, Default)]
+pub struct UnverifiedPolicy {
+    pub restricted: Vec<UnverifiedAction>,
+}
+
+impl UnverifiedPolicy {
+    /// Read comma separated list of actions from `UNVERIFIED_RESTRICTIONS`
+    ///
+    /// Example: `UNVERIFIED_RESTRICTIONS=create_course,invite_link,publish_course`
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let restricted = env::var("UNVERIFIED_RESTRICTIONS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|action| action.trim())
+            .filter(|action| !action.is_empty())
+            .map(|action| {
+                UnverifiedAction::from_str(action)
+                    .unwrap_or_else(|_| panic!("unknown unverified restriction: {}", action))
+            })
+            .collect();
+
+        UnverifiedPolicy { restricted }
+    }
+
+    pub fn restricts(&self, action: UnverifiedAction) -> bool {
+        self.restricted.contains(&action)
+    }
+
+    /// Return true if user can do the action
+    /// false if action is restricted and user email is not verified or if get some errors
+    pub async fn allows(
+        &self,
+        action: UnverifiedAction,
+        user_id: i32,
+        pool: &sqlx::Pool<Postgres>,
+    ) -> bool {
+        if !self.restricts(action) {
+            return true;
+        }
+
+        is_email_verified(user_id, pool).await.unwrap_or(false)
+    }
+}
@@ -0,0 +1,242 @@
+use std::{env, net::IpAddr};
+
+use actix_web::{http::header, HttpRequest};
+use chrono::Utc;
+use dotenvy::dotenv;
+use redis::{Commands, Connection, RedisResult};
+use uuid::Uuid;
+
+/// Limits for auth endpoints
+#[derive(Clone, Debug)]
+pub struct RateLimitConfig {
+    /// Max requests from one IP inside window
+    pub ip_limit: u64,
+    pub ip_window_secs: u64,
+    /// Failed sign in attempts for one account inside window before lockout
+    pub lockout_threshold: u64,
+    pub lockout_window_secs: u64,
+    /// First lockout duration, every next lockout is twice longer
+    pub lockout_base_secs: u64,
+    pub lockout_max_secs: u64,
+    /// Reverse proxies which are trusted to append client address to `X-Forwarded-For`
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            ip_limit: 20,
+            ip_window_secs: 60,
+            lockout_threshold: 5,
+            lockout_window_secs: 900,
+            lockout_base_secs: 60,
+            lockout_max_secs: 3600,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Read limits from `AUTH_IP_LIMIT`, `AUTH_IP_WINDOW_SECS`, `AUTH_LOCKOUT_THRESHOLD`,
+    /// `AUTH_LOCKOUT_WINDOW_SECS`, `AUTH_LOCKOUT_BASE_SECS` and `AUTH_LOCKOUT_MAX_SECS`,
+    /// comma separated addresses of proxies from `TRUSTED_PROXIES`
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let default = RateLimitConfig::default();
+        let var = |key: &str, default: u64| {
+            env::var(key)
+                .map(|value| {
+                    value
+                        .parse()
+                        .unwrap_or_else(|_| panic!("{} must be a number", key))
+                })
+                .unwrap_or(default)
+        };
+
+        RateLimitConfig {
+            ip_limit: var("AUTH_IP_LIMIT", default.ip_limit),
+            ip_window_secs: var("AUTH_IP_WINDOW_SECS", default.ip_window_secs),
+            lockout_threshold: var("AUTH_LOCKOUT_THRESHOLD", default.lockout_threshold),
+            lockout_window_secs: var("AUTH_LOCKOUT_WINDOW_SECS", default.lockout_window_secs),
+            lockout_base_secs: var("AUTH_LOCKOUT_BASE_SECS", default.lockout_base_secs),
+            lockout_max_secs: var("AUTH_LOCKOUT_MAX_SECS", default.lockout_max_secs),
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .map(|ip| ip.trim())
+                .filter(|ip| !ip.is_empty())
+                .map(|ip| {
+                    ip.parse()
+                        .unwrap_or_else(|_| panic!("invalid address in TRUSTED_PROXIES: {}", ip))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// IP of client, `X-Forwarded-For` is used only when peer is trusted proxy,
+/// otherwise every request could pretend to come from new address
+///
+/// Header is read from the right, because client can put anything to the left part of it,
+/// first address which is not trusted proxy is the client
+pub fn client_ip(req: &HttpRequest, config: &RateLimitConfig) -> Option<String> {
+    let mut client = req.peer_addr()?.ip();
+
+    if !config.trusted_proxies.contains(&client) {
+        return Some(client.to_string());
+    }
+
+    let hops: Vec<&str> = req
+        .headers()
+        .get_all(header::X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|hop| hop.trim())
+        .collect();
+
+    for hop in hops.iter().rev() {
+        // broken entry can come only from client, so the last proxy is used
+        let Ok(ip) = hop.parse::<IpAddr>() else {
+            break;
+        };
+
+        client = ip;
+
+        if !config.trusted_proxies.contains(&ip) {
+            break;
+        }
+    }
+
+    Some(client.to_string())
+}
+
+/// How many previous lockouts are remembered for progressive duration
+const LOCKOUT_LEVEL_SECS: u64 = 86400;
+
+fn ip_key(ip: &str) -> String {
+    format!("ratelimit:ip:{}", ip)
+}
+
+fn fails_key(account: &str) -> String {
+    format!("lockout:fails:{}", account.to_lowercase())
+}
+
+fn level_key(account: &str) -> String {
+    format!("lockout:level:{}", account.to_lowercase())
+}
+
+fn until_key(account: &str) -> String {
+    format!("lockout:until:{}", account.to_lowercase())
+}
+
+fn challenge_key(jti: &str) -> String {
+    format!("lockout:challenge:{}", jti)
+}
+
+/// Add hit into sliding window and return count of hits inside window
+fn hit_window(conn: &mut Connection, key: &str, window_secs: u64) -> RedisResult<u64> {
+    let now = Utc::now().timestamp_millis();
+    let window_start = now - (window_secs * 1000) as i64;
+
+    let (count,): (u64,) = redis::pipe()
+        .zrembyscore(key, "-inf", window_start)
+        .ignore()
+        .zadd(key, Uuid::new_v4().to_string(), now)
+        .ignore()
+        .zcard(key)
+        .expire(key, window_secs as i64)
+        .ignore()
+        .query(conn)?;
+
+    Ok(count)
+}
+
+/// Count request from IP, return seconds to wait if limit is exceeded
+pub fn check_ip(
+    conn: &mut Connection,
+    config: &RateLimitConfig,
+    ip: &str,
+) -> RedisResult<Option<u64>> {
+    let key = ip_key(ip);
+    let count = hit_window(conn, &key, config.ip_window_secs)?;
+
+    if count <= config.ip_limit {
+        return Ok(None);
+    }
+
+    // wait until the oldest hit leave the window
+    let oldest: Vec<(String, i64)> = conn.zrange_withscores(&key, 0, 0)?;
+    let now = Utc::now().timestamp_millis();
+    let retry_after = oldest
+        .first()
+        .map(|(_, score)| {
+            let left = score + (config.ip_window_secs * 1000) as i64 - now;
+            (left.max(0) as u64).div_ceil(1000)
+        })
+        .unwrap_or(config.ip_window_secs)
+        .max(1);
+
+    Ok(Some(retry_after))
+}
+
+/// Return seconds left if account is locked
+pub fn account_lockout(conn: &mut Connection, account: &str) -> RedisResult<Option<u64>> {
+    let ttl: i64 = conn.ttl(until_key(account))?;
+
+    Ok((ttl > 0).then_some(ttl as u64))
+}
+
+/// Count failed sign in of account
+///
+/// When threshold is reached account is locked, every next lockout is twice longer.
+/// Return lockout duration if account was locked
+pub fn register_failure(
+    conn: &mut Connection,
+    config: &RateLimitConfig,
+    account: &str,
+) -> RedisResult<Option<u64>> {
+    let fails = hit_window(conn, &fails_key(account), config.lockout_window_secs)?;
+
+    if fails < config.lockout_threshold {
+        return Ok(None);
+    }
+
+    let level: u32 = conn.incr(level_key(account), 1)?;
+    let _: () = conn.expire(level_key(account), LOCKOUT_LEVEL_SECS as i64)?;
+
+    let duration = config
+        .lockout_base_secs
+        .saturating_mul(2u64.saturating_pow(level.saturating_sub(1)))
+        .min(config.lockout_max_secs)
+        .max(1);
+
+    let _: () = redis::pipe()
+        .set_ex(until_key(account), 1, duration)
+        .ignore()
+        .del(fails_key(account))
+        .ignore()
+        .query(conn)?;
+
+    Ok(Some(duration))
+}
+
+/// Count invalid code for 2FA challenge, return count of failures of this challenge
+pub fn register_challenge_failure(
+    conn: &mut Connection,
+    jti: &str,
+    ttl_secs: u64,
+) -> RedisResult<u64> {
+    let (fails,): (u64,) = redis::pipe()
+        .incr(challenge_key(jti), 1)
+        .expire(challenge_key(jti), ttl_secs as i64)
+        .ignore()
+        .query(conn)?;
+
+    Ok(fails)
+}
+
+/// Forget failed attempts of account after successful sign in
+pub fn reset_failures(conn: &mut Connection, account: &str) -> RedisResult<()> {
+    conn.del(&[fails_key(account), level_key(account)])
+}
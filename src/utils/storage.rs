@@ -0,0 +1,743 @@
+use std::{
+    env,
+    io::{self, ErrorKind, Read, SeekFrom},
+    path::PathBuf,
+    pin::Pin,
+    time::{Duration, SystemTime},
+};
+
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crypto::{digest::Digest, hmac::Hmac, mac::Mac, sha2::Sha256};
+use dotenvy::dotenv;
+use futures_util::{stream, Stream};
+use tokio::{
+    fs,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt},
+};
+use uuid::Uuid;
+
+/// Size of chunks in which files are streamed
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Time to connect to S3 storage
+const S3_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time of one read or write of socket, whole transfer of big file may take longer
+const S3_IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Kind of stored files, every kind is kept in its own directory or prefix
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlobKind {
+    /// EPUB files of books
+    Book,
+    /// Published texts of lessons
+    Lesson,
+    /// Images with thumbnails
+    Image,
+}
+
+impl BlobKind {
+    /// Directory or key prefix of kind
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            BlobKind::Book => "books",
+            BlobKind::Lesson => "lessons",
+            BlobKind::Image => "images",
+        }
+    }
+}
+
+/// Size and time of last change of stored file
+#[derive(Clone, Copy, Debug)]
+pub struct BlobMeta {
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Inclusive range of bytes inside stored file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn size(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+/// Content of stored file which is read by chunks
+pub type BlobStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// Stored file in listing of store
+#[derive(Clone, Debug)]
+pub struct BlobInfo {
+    pub key: String,
+    pub meta: BlobMeta,
+}
+
+/// Storage of uploaded and generated files
+///
+/// Missing files are reported as error with `ErrorKind::NotFound`
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Save file, it is replaced at once, readers never see half-written file
+    async fn put(&self, kind: BlobKind, key: &str, data: Vec<u8>) -> io::Result<()>;
+
+    async fn get(&self, kind: BlobKind, key: &str) -> io::Result<Vec<u8>>;
+
+    /// Read whole file or its `range` without loading it into memory,
+    /// range must be inside file
+    async fn stream(
+        &self,
+        kind: BlobKind,
+        key: &str,
+        range: Option<ByteRange>,
+    ) -> io::Result<BlobStream>;
+
+    async fn stat(&self, kind: BlobKind, key: &str) -> io::Result<BlobMeta>;
+
+    async fn copy(&self, kind: BlobKind, from: &str, to: &str) -> io::Result<()>;
+
+    async fn delete(&self, kind: BlobKind, key: &str) -> io::Result<()>;
+
+    /// All files of kind, files which are being written now are not listed
+    async fn list(&self, kind: BlobKind) -> io::Result<Vec<BlobInfo>>;
+}
+
+/// Stream of chunks read from `reader` until its end
+fn read_stream<R: AsyncRead + Unpin + Send + 'static>(reader: R) -> BlobStream {
+    Box::pin(stream::try_unfold(reader, |mut reader| async move {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let read = reader.read(&mut chunk).await?;
+
+        if read == 0 {
+            return Ok(None);
+        }
+
+        chunk.truncate(read);
+
+        Ok(Some((Bytes::from(chunk), reader)))
+    }))
+}
+
+/// Keys are names generated by server, but they must not leave directory of store
+fn check_key(key: &str) -> io::Result<()> {
+    if key.is_empty() || key.contains(['/', '\\']) || key.starts_with('.') {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "invalid key of file",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Files in directories of local file system
+pub struct LocalStore {
+    pub books_dir: PathBuf,
+    pub lessons_dir: PathBuf,
+    pub images_dir: PathBuf,
+}
+
+impl LocalStore {
+    fn path(&self, kind: BlobKind, key: &str) -> io::Result<PathBuf> {
+        check_key(key)?;
+
+        let dir = match kind {
+            BlobKind::Book => &self.books_dir,
+            BlobKind::Lesson => &self.lessons_dir,
+            BlobKind::Image => &self.images_dir,
+        };
+
+        Ok(dir.join(key))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalStore {
+    async fn put(&self, kind: BlobKind, key: &str, data: Vec<u8>) -> io::Result<()> {
+        let path = self.path(kind, key)?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+
+        // write into temporary file and move it
+        let tmp_path = path.with_file_name(format!(".{}.{}", key, Uuid::new_v4()));
+        fs::write(&tmp_path, data).await?;
+
+        if let Err(err) = fs::rename(&tmp_path, &path).await {
+            fs::remove_file(&tmp_path).await.ok();
+
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, kind: BlobKind, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(kind, key)?).await
+    }
+
+    async fn stream(
+        &self,
+        kind: BlobKind,
+        key: &str,
+        range: Option<ByteRange>,
+    ) -> io::Result<BlobStream> {
+        let mut file = fs::File::open(self.path(kind, key)?).await?;
+
+        let len = match range {
+            Some(range) => {
+                file.seek(SeekFrom::Start(range.start)).await?;
+                range.size()
+            }
+            None => file.metadata().await?.len(),
+        };
+
+        Ok(read_stream(file.take(len)))
+    }
+
+    async fn stat(&self, kind: BlobKind, key: &str) -> io::Result<BlobMeta> {
+        let metadata = fs::metadata(self.path(kind, key)?).await?;
+
+        Ok(BlobMeta {
+            size: metadata.len(),
+            modified: metadata.modified()?,
+        })
+    }
+
+    async fn copy(&self, kind: BlobKind, from: &str, to: &str) -> io::Result<()> {
+        fs::copy(self.path(kind, from)?, self.path(kind, to)?)
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete(&self, kind: BlobKind, key: &str) -> io::Result<()> {
+        fs::remove_file(self.path(kind, key)?).await
+    }
+
+    async fn list(&self, kind: BlobKind) -> io::Result<Vec<BlobInfo>> {
+        let dir = match kind {
+            BlobKind::Book => &self.books_dir,
+            BlobKind::Lesson => &self.lessons_dir,
+            BlobKind::Image => &self.images_dir,
+        };
+
+        let mut entries = match fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut blobs = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            let key = entry.file_name().to_string_lossy().to_string();
+            let metadata = entry.metadata().await?;
+
+            // temporary files of `put`
+            if key.starts_with('.') || !metadata.is_file() {
+                continue;
+            }
+
+            blobs.push(BlobInfo {
+                key,
+                meta: BlobMeta {
+                    size: metadata.len(),
+                    modified: metadata.modified()?,
+                },
+            });
+        }
+
+        Ok(blobs)
+    }
+}
+
+/// Bucket of S3 compatible storage (AWS, MinIO), requests are signed with AWS Signature V4
+///
+/// Path-style addressing is used: `{endpoint}/{bucket}/{prefix}/{key}`, endpoint may
+/// have path when storage is behind proxy
+#[derive(Clone)]
+pub struct S3Store {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    agent: ureq::Agent,
+}
+
+/// Response of storage: status, headers which are needed and body
+struct S3Response {
+    status: u16,
+    content_length: Option<u64>,
+    last_modified: Option<String>,
+    body: Vec<u8>,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input(data);
+
+    hasher.result_str()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::new(Sha256::new(), key);
+    mac.input(data);
+
+    mac.result().code().to_vec()
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Encode path as it is required by signature: everything except unreserved chars and `/`
+fn uri_encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Encode value of query parameter, `/` is encoded too
+fn query_encode(value: &str) -> String {
+    uri_encode(value).replace('/', "%2F")
+}
+
+/// Text of first element with name `tag`, entities of XML are unescaped
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let len = xml[start..].find(&format!("</{}>", tag))?;
+
+    Some(xml_unescape(&xml[start..start + len]))
+}
+
+/// Replace predefined and numeric entities of XML, unknown entities are kept as is
+fn xml_unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let ch = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => name
+                .strip_prefix("#x")
+                .map(|code| u32::from_str_radix(code, 16))
+                .or_else(|| name.strip_prefix('#').map(|code| code.parse()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        });
+
+        match (ch, entity) {
+            (Some(ch), Some((_, end))) => {
+                result.push(ch);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+
+    result
+}
+
+impl S3Store {
+    pub fn new(
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+    ) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(S3_CONNECT_TIMEOUT)
+            .timeout_read(S3_IO_TIMEOUT)
+            .timeout_write(S3_IO_TIMEOUT)
+            .build();
+
+        S3Store {
+            endpoint,
+            bucket,
+            region,
+            access_key,
+            secret_key,
+            agent,
+        }
+    }
+
+    fn object_path(&self, kind: BlobKind, key: &str) -> io::Result<String> {
+        check_key(key)?;
+
+        Ok(uri_encode(&format!(
+            "/{}/{}/{}",
+            self.bucket,
+            kind.prefix(),
+            key
+        )))
+    }
+
+    /// Send signed request, `amz_headers` are additional `x-amz-*` or `range` headers,
+    /// body of response is not read
+    fn open(
+        &self,
+        method: &str,
+        path: &str,
+        amz_headers: &[(&str, String)],
+        body: &[u8],
+    ) -> io::Result<ureq::Response> {
+        let url = url::Url::parse(&self.endpoint)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidInput, err))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = sha256_hex(body);
+
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        headers.extend(amz_headers.iter().cloned());
+        headers.sort_by(|a, b| a.0.cmp(b.0));
+
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<&str>>()
+            .join(";");
+
+        // query of path must be sorted and encoded already, path of endpoint is
+        // signed too, because storage sees it
+        let (uri, query) = path.split_once('?').unwrap_or((path, ""));
+        let uri = format!("{}{}", url.path().trim_end_matches('/'), uri);
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method, uri, query, canonical_headers, signed_headers, payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+
+        let signing_key = [
+            self.region.as_bytes(),
+            b"s3".as_slice(),
+            b"aws4_request".as_slice(),
+        ]
+        .iter()
+        .fold(
+            hmac_sha256(
+                format!("AWS4{}", self.secret_key).as_bytes(),
+                date.as_bytes(),
+            ),
+            |key, data| hmac_sha256(&key, data),
+        );
+        let signature = to_hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key, scope, signed_headers, signature
+        );
+
+        let mut target = format!("{}{}", url.origin().ascii_serialization(), uri);
+        if !query.is_empty() {
+            target.push('?');
+            target.push_str(query);
+        }
+
+        let mut request = self
+            .agent
+            .request(method, &target)
+            .set("Authorization", &authorization);
+
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.set(name, value);
+        }
+
+        match request.send_bytes(body) {
+            Ok(response) => Ok(response),
+            Err(ureq::Error::Status(_, response)) => Ok(response),
+            Err(err) => Err(io::Error::other(err.to_string())),
+        }
+    }
+
+    /// Send signed request and read its response
+    fn send(
+        &self,
+        method: &str,
+        path: &str,
+        amz_headers: &[(&str, String)],
+        body: &[u8],
+    ) -> io::Result<S3Response> {
+        let response = self.open(method, path, amz_headers, body)?;
+
+        let status = response.status();
+        let content_length = response
+            .header("Content-Length")
+            .and_then(|length| length.parse().ok());
+        let last_modified = response
+            .header("Last-Modified")
+            .map(|date| date.to_string());
+
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body)?;
+
+        Ok(S3Response {
+            status,
+            content_length,
+            last_modified,
+            body,
+        })
+    }
+
+    /// Run blocking request in thread pool
+    async fn request(
+        &self,
+        method: &'static str,
+        path: String,
+        amz_headers: Vec<(&'static str, String)>,
+        body: Vec<u8>,
+    ) -> io::Result<S3Response> {
+        let store = self.clone();
+
+        let response =
+            tokio::task::spawn_blocking(move || store.send(method, &path, &amz_headers, &body))
+                .await
+                .map_err(io::Error::other)??;
+
+        match response.status {
+            200..=299 => Ok(response),
+            status => Err(status_error(status, &response.body)),
+        }
+    }
+}
+
+fn status_error(status: u16, body: &[u8]) -> io::Error {
+    match status {
+        404 => io::Error::new(ErrorKind::NotFound, "file not found"),
+        status => io::Error::other(format!(
+            "storage responded with status {}: {}",
+            status,
+            String::from_utf8_lossy(body)
+        )),
+    }
+}
+
+/// Stream of chunks read from blocking `reader` in thread pool
+fn blocking_read_stream(reader: Box<dyn Read + Send + Sync>) -> BlobStream {
+    Box::pin(stream::try_unfold(reader, |mut reader| async move {
+        let (chunk, reader) = tokio::task::spawn_blocking(move || {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let read = reader.read(&mut chunk)?;
+            chunk.truncate(read);
+
+            Ok::<_, io::Error>((chunk, reader))
+        })
+        .await
+        .map_err(io::Error::other)??;
+
+        if chunk.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some((Bytes::from(chunk), reader)))
+    }))
+}
+
+#[async_trait]
+impl BlobStore for S3Store {
+    async fn put(&self, kind: BlobKind, key: &str, data: Vec<u8>) -> io::Result<()> {
+        let path = self.object_path(kind, key)?;
+
+        self.request("PUT", path, vec![], data).await.map(|_| ())
+    }
+
+    async fn get(&self, kind: BlobKind, key: &str) -> io::Result<Vec<u8>> {
+        let path = self.object_path(kind, key)?;
+
+        Ok(self.request("GET", path, vec![], vec![]).await?.body)
+    }
+
+    async fn stream(
+        &self,
+        kind: BlobKind,
+        key: &str,
+        range: Option<ByteRange>,
+    ) -> io::Result<BlobStream> {
+        let path = self.object_path(kind, key)?;
+        let headers: Vec<(&str, String)> = range
+            .map(|range| vec![("range", format!("bytes={}-{}", range.start, range.end))])
+            .unwrap_or_default();
+        let store = self.clone();
+
+        let reader = tokio::task::spawn_blocking(move || {
+            let response = store.open("GET", &path, &headers, &[])?;
+
+            match response.status() {
+                200..=299 => Ok(response.into_reader()),
+                status => {
+                    let mut body = Vec::new();
+                    response.into_reader().read_to_end(&mut body)?;
+
+                    Err(status_error(status, &body))
+                }
+            }
+        })
+        .await
+        .map_err(io::Error::other)??;
+
+        Ok(blocking_read_stream(reader))
+    }
+
+    async fn stat(&self, kind: BlobKind, key: &str) -> io::Result<BlobMeta> {
+        let path = self.object_path(kind, key)?;
+        let response = self.request("HEAD", path, vec![], vec![]).await?;
+
+        // unknown time is treated as fresh file, so it never looks old enough to be removed
+        let modified = response
+            .last_modified
+            .and_then(|date| DateTime::parse_from_rfc2822(&date).ok())
+            .map(SystemTime::from)
+            .unwrap_or_else(SystemTime::now);
+
+        Ok(BlobMeta {
+            size: response.content_length.unwrap_or_default(),
+            modified,
+        })
+    }
+
+    async fn copy(&self, kind: BlobKind, from: &str, to: &str) -> io::Result<()> {
+        let source = self.object_path(kind, from)?;
+        let path = self.object_path(kind, to)?;
+
+        self.request("PUT", path, vec![("x-amz-copy-source", source)], vec![])
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete(&self, kind: BlobKind, key: &str) -> io::Result<()> {
+        let path = self.object_path(kind, key)?;
+
+        self.request("DELETE", path, vec![], vec![])
+            .await
+            .map(|_| ())
+    }
+
+    async fn list(&self, kind: BlobKind) -> io::Result<Vec<BlobInfo>> {
+        let prefix = format!("{}/", kind.prefix());
+        let mut blobs = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            // parameters of query are sorted by name for signature
+            let mut query = String::new();
+
+            if let Some(token) = &token {
+                query.push_str(&format!("continuation-token={}&", query_encode(token)));
+            }
+
+            query.push_str(&format!("list-type=2&prefix={}", query_encode(&prefix)));
+
+            let path = format!("{}?{}", uri_encode(&format!("/{}", self.bucket)), query);
+            let response = self.request("GET", path, vec![], vec![]).await?;
+            let xml = String::from_utf8_lossy(&response.body);
+
+            for contents in xml.split("<Contents>").skip(1) {
+                let Some(key) = xml_value(contents, "Key")
+                    .and_then(|key| key.strip_prefix(&prefix).map(|key| key.to_string()))
+                else {
+                    continue;
+                };
+
+                // unknown time is treated as fresh file, so gc skips it
+                let modified = xml_value(contents, "LastModified")
+                    .and_then(|date| DateTime::parse_from_rfc3339(&date).ok())
+                    .map(SystemTime::from)
+                    .unwrap_or_else(SystemTime::now);
+                let size = xml_value(contents, "Size")
+                    .and_then(|size| size.parse().ok())
+                    .unwrap_or_default();
+
+                blobs.push(BlobInfo {
+                    key,
+                    meta: BlobMeta { size, modified },
+                });
+            }
+
+            token = match xml_value(&xml, "IsTruncated").as_deref() {
+                Some("true") => xml_value(&xml, "NextContinuationToken"),
+                _ => None,
+            };
+
+            if token.is_none() {
+                return Ok(blobs);
+            }
+        }
+    }
+}
+
+/// Build storage of files from environment
+///
+/// `STORAGE=s3` use `S3_ENDPOINT`, `S3_BUCKET`, `S3_REGION`, `S3_ACCESS_KEY` and `S3_SECRET_KEY`,
+/// otherwise files are saved into `BOOKS_DIR`, `LESSONS_DIR` and `IMAGE_DIR`
+pub fn blob_store_from_env() -> Box<dyn BlobStore> {
+    dotenv().ok();
+
+    match env::var("STORAGE").unwrap_or_default().as_str() {
+        "s3" => Box::new(S3Store::new(
+            env::var("S3_ENDPOINT").expect("S3_ENDPOINT must be set"),
+            env::var("S3_BUCKET").expect("S3_BUCKET must be set"),
+            env::var("S3_REGION").unwrap_or("us-east-1".to_string()),
+            env::var("S3_ACCESS_KEY").expect("S3_ACCESS_KEY must be set"),
+            env::var("S3_SECRET_KEY").expect("S3_SECRET_KEY must be set"),
+        )),
+        _ => Box::new(LocalStore {
+            books_dir: PathBuf::from(
+                env::var("BOOKS_DIR").unwrap_or("./uploads/books".to_string()),
+            ),
+            lessons_dir: PathBuf::from(
+                env::var("LESSONS_DIR").unwrap_or("./uploads/lessons".to_string()),
+            ),
+            images_dir: PathBuf::from(
+                env::var("IMAGE_DIR").unwrap_or("./uploads/images".to_string()),
+            ),
+        }),
+    }
+}
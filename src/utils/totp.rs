@@ -0,0 +1,75 @@
+use std::error::Error;
+
+use chrono::Utc;
+use rand::{distributions::Alphanumeric, Rng};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+/// Name of service in authenticator app
+const ISSUER: &str = "ReadCraft";
+const STEP_SECS: u64 = 30;
+/// Accept code of previous and next step to allow clock drift
+const SKEW: u8 = 1;
+
+pub const RECOVERY_CODES_COUNT: usize = 10;
+pub const CHALLENGE_DURATION_MIN: u64 = 5;
+/// Challenge is denied after this count of invalid codes, user has to sign in again
+pub const CHALLENGE_MAX_ATTEMPTS: u64 = 3;
+
+/// Generate new base32 encoded secret
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+fn build_totp(secret: &str, email: &str) -> Result<TOTP, Box<dyn Error>> {
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        // neighbour steps are checked one by one in `check_code` to know which step matched
+        0,
+        STEP_SECS,
+        Secret::Encoded(secret.to_string()).to_bytes()?,
+        Some(ISSUER.to_string()),
+        email.to_string(),
+    )?;
+
+    Ok(totp)
+}
+
+/// URI for QR code of authenticator app
+pub fn otpauth_url(secret: &str, email: &str) -> Result<String, Box<dyn Error>> {
+    Ok(build_totp(secret, email)?.get_url())
+}
+
+/// Check code and return time step which code matched
+///
+/// Code is rejected if its time step or later one was already used
+pub fn check_code(
+    secret: &str,
+    email: &str,
+    code: &str,
+    last_used_step: Option<i64>,
+) -> Result<Option<i64>, Box<dyn Error>> {
+    let totp = build_totp(secret, email)?;
+    let step = Utc::now().timestamp() / STEP_SECS as i64;
+
+    let matched = (step - SKEW as i64..=step + SKEW as i64)
+        .filter(|step| !last_used_step.is_some_and(|last| last >= *step))
+        .find(|step| totp.check(code, *step as u64 * STEP_SECS));
+
+    Ok(matched)
+}
+
+/// Generate one time recovery codes in `xxxxx-xxxxx` format
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES_COUNT)
+        .map(|_| {
+            let code: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(|c| char::from(c).to_ascii_lowercase())
+                .collect();
+
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
@@ -11,8 +11,20 @@ use fake::{
 };
 use rc_api::main_config;
 
-use rc_api::services::user::find_user_by_email;
-use rc_api::{get_app_data, get_db_conn, get_key, models::auth::*, utils::jwt::JwtUtil};
+use rc_api::services::{
+    audit::find_failed_logins,
+    session::{find_session_by_id, find_user_sessions},
+    user::{create_email_verification, find_user_by_email},
+};
+use rc_api::{
+    get_app_data, get_db_conn,
+    models::{auth::*, two_factor::*},
+    utils::{
+        jwt::JwtUtil,
+        rate_limit::{client_ip, RateLimitConfig},
+    },
+};
+use totp_rs::{Algorithm, Secret, TOTP};
 
 fn signup_req(data: SignUpData) -> TestRequest {
     test::TestRequest::post()
@@ -26,6 +38,36 @@ fn signin_req(data: SignInData) -> TestRequest {
         .set_json(data)
 }
 
+fn signin_device_req(data: SignInData, device_id: &str) -> TestRequest {
+    signin_req(data).insert_header(("X-Device-Id", device_id))
+}
+
+fn sessions_req(token: &str) -> TestRequest {
+    test::TestRequest::get()
+        .uri("/api/auth/sessions")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+}
+
+fn revoke_all_sessions_req(token: &str) -> TestRequest {
+    test::TestRequest::delete()
+        .uri("/api/auth/sessions")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+}
+
+fn change_password_req(data: ChangePasswordData, token: &str) -> TestRequest {
+    test::TestRequest::post()
+        .uri("/api/auth/password")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        .set_json(data)
+}
+
+fn delete_account_req(data: DeleteAccountData, token: &str) -> TestRequest {
+    test::TestRequest::delete()
+        .uri("/api/auth/account")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        .set_json(data)
+}
+
 fn logout_req(token: &str) -> TestRequest {
     test::TestRequest::post()
         .uri("/api/auth/logout")
@@ -38,6 +80,207 @@ fn refresh_req(token: &str) -> TestRequest {
         .append_header((header::AUTHORIZATION, format!("Bearer {}", token)))
 }
 
+fn verify_req(token: &str) -> TestRequest {
+    test::TestRequest::post()
+        .uri("/api/auth/verify")
+        .set_json(VerifyEmailData {
+            token: token.to_string(),
+        })
+}
+
+fn jwks_req() -> TestRequest {
+    test::TestRequest::get().uri("/.well-known/jwks.json")
+}
+
+fn oauth_authorize_req(provider: &str) -> TestRequest {
+    test::TestRequest::get().uri(format!("/api/auth/oauth/{}/authorize", provider).as_str())
+}
+
+fn oauth_callback_req(provider: &str, data: OAuthCallbackData) -> TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/auth/oauth/{}/callback", provider).as_str())
+        .set_json(data)
+}
+
+fn two_factor_enroll_req(token: &str) -> TestRequest {
+    test::TestRequest::post()
+        .uri("/api/auth/2fa/enroll")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+}
+
+fn two_factor_confirm_req(code: &str, token: &str) -> TestRequest {
+    test::TestRequest::post()
+        .uri("/api/auth/2fa/confirm")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        .set_json(TotpCodeData {
+            code: code.to_string(),
+        })
+}
+
+fn two_factor_disable_req(code: &str, token: &str) -> TestRequest {
+    test::TestRequest::delete()
+        .uri("/api/auth/2fa")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+        .set_json(TotpCodeData {
+            code: code.to_string(),
+        })
+}
+
+fn two_factor_verify_req(challenge: &str, code: &str) -> TestRequest {
+    test::TestRequest::post()
+        .uri("/api/auth/2fa/verify")
+        .set_json(TwoFactorVerifyData {
+            challenge: challenge.to_string(),
+            code: code.to_string(),
+        })
+}
+
+fn wrong_signin_req(email: &str) -> TestRequest {
+    signin_req(SignInData {
+        email: email.to_string(),
+        password: String::from("wrong password"),
+    })
+}
+
+/// Configure local mock identity provider, endpoints are not called by these tests
+fn mock_oidc_env() {
+    std::env::set_var("OIDC_PROVIDERS", "mock");
+    std::env::set_var("OIDC_MOCK_CLIENT_ID", "rc-client");
+    std::env::set_var("OIDC_MOCK_AUTHORIZE_URL", "http://127.0.0.1:9090/authorize");
+    std::env::set_var("OIDC_MOCK_TOKEN_URL", "http://127.0.0.1:9090/token");
+    std::env::set_var("OIDC_MOCK_USERINFO_URL", "http://127.0.0.1:9090/userinfo");
+    std::env::set_var(
+        "OIDC_MOCK_REDIRECT_URI",
+        "http://127.0.0.1:3000/oauth/callback",
+    );
+}
+
+fn rand_ip() -> std::net::Ipv4Addr {
+    let octets: [u8; 4] = [10, (0..255).fake(), (0..255).fake(), (1..255).fake()];
+
+    std::net::Ipv4Addr::from(octets)
+}
+
+/// New user, returns email, password and tokens of signup
+async fn init_user() -> (String, String, Tokens) {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let email: String = FreeEmail(EN).fake();
+    let password: String = Password(EN, 6..12).fake();
+
+    let signup_req = signup_req(SignUpData {
+        email: email.clone(),
+        username: Username(EN).fake(),
+        password: password.clone(),
+    })
+    .to_request();
+    let tokens: Tokens = test::call_and_read_body_json(&app, signup_req).await;
+
+    (email, password, tokens)
+}
+
+async fn init_device(email: &str, password: &str, device_id: &str) -> Tokens {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let signin_req = signin_device_req(
+        SignInData {
+            email: email.to_string(),
+            password: password.to_string(),
+        },
+        device_id,
+    )
+    .to_request();
+
+    test::call_and_read_body_json(&app, signin_req).await
+}
+
+/// User which reached threshold of failed signins, returns email and valid password
+async fn init_locked_user() -> (String, String) {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, _) = init_user().await;
+
+    // default threshold is 5 failed attempts
+    for _ in 0..5 {
+        wrong_signin_req(&email).send_request(&app).await;
+    }
+
+    (email, password)
+}
+
+/// Enable 2FA for user, returns TOTP generator and recovery codes
+async fn init_two_factor(email: &str, token: &str) -> (TOTP, Vec<String>) {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let enroll_req = two_factor_enroll_req(token).to_request();
+    let enroll: TotpEnrollOut = test::call_and_read_body_json(&app, enroll_req).await;
+
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(enroll.secret).to_bytes().unwrap(),
+        None,
+        email.to_string(),
+    )
+    .unwrap();
+
+    let confirm_req = two_factor_confirm_req(&totp.generate_current().unwrap(), token).to_request();
+    let recovery: RecoveryCodesOut = test::call_and_read_body_json(&app, confirm_req).await;
+
+    (totp, recovery.recovery_codes)
+}
+
+/// Code of the next time step, code of current step is already used by confirmation
+fn next_code(totp: &TOTP) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    totp.generate(now + totp.step)
+}
+
+/// Sign in user with enabled 2FA, returns challenge
+async fn init_challenge(email: &str, password: &str) -> String {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let signin_req = signin_req(SignInData {
+        email: email.to_string(),
+        password: password.to_string(),
+    })
+    .to_request();
+    let challenge: TwoFactorChallenge = test::call_and_read_body_json(&app, signin_req).await;
+
+    challenge.challenge
+}
+
 #[actix_web::test]
 async fn test_signup() {
     let app = test::init_service(
@@ -62,8 +305,10 @@ async fn test_signup() {
     let db = &get_db_conn().await;
 
     let user = find_user_by_email(&email, db).await.unwrap();
+    let sessions = find_user_sessions(user.id, db).await.unwrap();
 
-    assert!(user.refresh_token_hash.is_some());
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].refresh_token_hash.is_some());
 }
 
 #[actix_web::test]
@@ -97,14 +342,17 @@ async fn test_signup_tokens() {
     hasher.input_str(tokens.refresh.as_str());
     let refresh_hash = hasher.result_str();
 
-    assert_eq!(user.refresh_token_hash.unwrap(), refresh_hash);
-
     // check JWT claims
-    let jwt = JwtUtil { key: get_key() };
+    let jwt = JwtUtil::from_env();
     let claims = jwt.decode_token(&tokens.access).unwrap();
 
     assert_eq!(user.id, claims.uid);
     assert_eq!(user.email, claims.email);
+
+    let session = find_session_by_id(claims.sid.unwrap(), db).await.unwrap();
+
+    assert_eq!(session.user_id, user.id);
+    assert_eq!(session.refresh_token_hash.unwrap(), refresh_hash);
 }
 
 #[actix_web::test]
@@ -203,8 +451,10 @@ async fn test_signin() {
     let db = &get_db_conn().await;
 
     let user = find_user_by_email(&email, db).await.unwrap();
+    let sessions = find_user_sessions(user.id, db).await.unwrap();
 
-    assert!(user.refresh_token_hash.is_some());
+    // signup and signin without device id are different devices
+    assert_eq!(sessions.len(), 2);
 }
 
 #[actix_web::test]
@@ -270,8 +520,9 @@ async fn test_logout() {
     let db = &get_db_conn().await;
 
     let user = find_user_by_email(&email, db).await.unwrap();
+    let sessions = find_user_sessions(user.id, db).await.unwrap();
 
-    assert!(user.refresh_token_hash.is_none());
+    assert!(sessions.is_empty());
 }
 
 #[actix_web::test]
@@ -308,14 +559,13 @@ async fn test_logout_and_signin() {
     let db = &get_db_conn().await;
 
     let user = find_user_by_email(&email, db).await.unwrap();
-    assert!(user.refresh_token_hash.is_none());
+    assert!(find_user_sessions(user.id, db).await.unwrap().is_empty());
 
     let signin_res = signin_req(signin_data).send_request(&app).await;
 
     assert_eq!(signin_res.status(), StatusCode::OK);
 
-    let user = find_user_by_email(&email, db).await.unwrap();
-    assert!(user.refresh_token_hash.is_some());
+    assert_eq!(find_user_sessions(user.id, db).await.unwrap().len(), 1);
 }
 
 #[actix_web::test]
@@ -412,11 +662,12 @@ async fn test_refresh_token() {
     hasher.input_str(new_tokens.refresh.as_str());
     let new_refresh_hash = hasher.result_str();
 
-    assert!(user.refresh_token_hash.is_some());
+    let sessions = find_user_sessions(user.id, db).await.unwrap();
+    assert_eq!(sessions.len(), 1);
 
-    let user_refresh_token_hash = user.refresh_token_hash.unwrap();
-    assert_ne!(user_refresh_token_hash, old_refresh_hash);
-    assert_eq!(user_refresh_token_hash, new_refresh_hash);
+    let session_refresh_token_hash = sessions[0].refresh_token_hash.clone().unwrap();
+    assert_ne!(session_refresh_token_hash, old_refresh_hash);
+    assert_eq!(session_refresh_token_hash, new_refresh_hash);
 }
 
 #[actix_web::test]
@@ -469,3 +720,844 @@ async fn test_refresh_tokens_with_access() {
     let refresh_res = refresh_req(&tokens.access).send_request(&app).await;
     assert_eq!(refresh_res.status(), StatusCode::BAD_REQUEST);
 }
+
+#[actix_web::test]
+async fn test_verify_email() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, _, _) = init_user().await;
+
+    let db = &get_db_conn().await;
+
+    let user = find_user_by_email(&email, db).await.unwrap();
+
+    assert!(!user.email_verified);
+
+    let token = create_email_verification(user.id, db).await.unwrap();
+
+    let verify_res = verify_req(&token).send_request(&app).await;
+
+    assert_eq!(verify_res.status(), StatusCode::OK);
+
+    let user = find_user_by_email(&email, db).await.unwrap();
+
+    assert!(user.email_verified);
+}
+
+#[actix_web::test]
+async fn test_verify_email_token_used() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, _, _) = init_user().await;
+
+    let db = &get_db_conn().await;
+
+    let user = find_user_by_email(&email, db).await.unwrap();
+    let token = create_email_verification(user.id, db).await.unwrap();
+
+    verify_req(&token).send_request(&app).await;
+
+    // token can be used only once
+    let verify_res = verify_req(&token).send_request(&app).await;
+
+    assert_eq!(verify_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_verify_email_invalid_token() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let verify_res = verify_req("invalid token").send_request(&app).await;
+
+    assert_eq!(verify_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_signin_same_device() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+
+    init_device(&email, &password, "phone").await;
+    init_device(&email, &password, "tablet").await;
+
+    // signin again from the same device reuses its session
+    init_device(&email, &password, "phone").await;
+
+    let sessions_res = sessions_req(&tokens.access).send_request(&app).await;
+
+    assert_eq!(sessions_res.status(), StatusCode::OK);
+
+    let sessions: Vec<serde_json::Value> = test::read_body_json(sessions_res).await;
+
+    assert_eq!(sessions.len(), 3);
+}
+
+#[actix_web::test]
+async fn test_logout_device() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, _) = init_user().await;
+
+    let phone = init_device(&email, &password, "phone").await;
+
+    let logout_res = logout_req(&phone.access).send_request(&app).await;
+
+    assert_eq!(logout_res.status(), StatusCode::OK);
+
+    let refresh_res = refresh_req(&phone.refresh).send_request(&app).await;
+
+    assert_eq!(refresh_res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_logout_keeps_other_devices() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, _) = init_user().await;
+
+    let phone = init_device(&email, &password, "phone").await;
+    let tablet = init_device(&email, &password, "tablet").await;
+
+    logout_req(&phone.access).send_request(&app).await;
+
+    let refresh_res = refresh_req(&tablet.refresh).send_request(&app).await;
+
+    assert_eq!(refresh_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_refresh_token_reuse() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (_, _, tokens) = init_user().await;
+
+    std::thread::sleep(std::time::Duration::from_secs(1));
+
+    let refresh_res = refresh_req(&tokens.refresh).send_request(&app).await;
+
+    assert_eq!(refresh_res.status(), StatusCode::OK);
+
+    let refresh_res = refresh_req(&tokens.refresh).send_request(&app).await;
+
+    assert_eq!(refresh_res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_refresh_token_reuse_revokes_session() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, _, tokens) = init_user().await;
+
+    std::thread::sleep(std::time::Duration::from_secs(1));
+
+    let refresh_res = refresh_req(&tokens.refresh).send_request(&app).await;
+    let new_tokens: Tokens = test::read_body_json(refresh_res).await;
+
+    refresh_req(&tokens.refresh).send_request(&app).await;
+
+    // whole session is revoked, so new refresh token is not valid too
+    let refresh_res = refresh_req(&new_tokens.refresh).send_request(&app).await;
+
+    assert_eq!(refresh_res.status(), StatusCode::UNAUTHORIZED);
+
+    let db = &get_db_conn().await;
+    let user = find_user_by_email(&email, db).await.unwrap();
+
+    assert!(find_user_sessions(user.id, db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_revoke_all_sessions() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+
+    init_device(&email, &password, "phone").await;
+
+    let revoke_res = revoke_all_sessions_req(&tokens.access)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(revoke_res.status(), StatusCode::OK);
+
+    let db = &get_db_conn().await;
+    let user = find_user_by_email(&email, db).await.unwrap();
+
+    assert!(find_user_sessions(user.id, db).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_logout_revokes_access_token() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (_, _, tokens) = init_user().await;
+
+    let logout_res = logout_req(&tokens.access).send_request(&app).await;
+
+    assert_eq!(logout_res.status(), StatusCode::OK);
+
+    let sessions_res = sessions_req(&tokens.access).send_request(&app).await;
+
+    assert_eq!(sessions_res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_change_password() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (_, password, tokens) = init_user().await;
+
+    let change_res = change_password_req(
+        ChangePasswordData {
+            old_password: password,
+            new_password: Password(EN, 6..12).fake(),
+        },
+        &tokens.access,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(change_res.status(), StatusCode::OK);
+
+    let new_tokens: Tokens = test::read_body_json(change_res).await;
+
+    let sessions_res = sessions_req(&new_tokens.access).send_request(&app).await;
+
+    assert_eq!(sessions_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_change_password_revokes_tokens() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (_, password, tokens) = init_user().await;
+
+    change_password_req(
+        ChangePasswordData {
+            old_password: password,
+            new_password: Password(EN, 6..12).fake(),
+        },
+        &tokens.access,
+    )
+    .send_request(&app)
+    .await;
+
+    let sessions_res = sessions_req(&tokens.access).send_request(&app).await;
+
+    assert_eq!(sessions_res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_change_password_and_signin() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+    let new_password: String = Password(EN, 6..12).fake();
+
+    change_password_req(
+        ChangePasswordData {
+            old_password: password.clone(),
+            new_password: new_password.clone(),
+        },
+        &tokens.access,
+    )
+    .send_request(&app)
+    .await;
+
+    let signin_res = signin_req(SignInData {
+        email: email.clone(),
+        password,
+    })
+    .send_request(&app)
+    .await;
+
+    assert_eq!(signin_res.status(), StatusCode::FORBIDDEN);
+
+    let signin_res = signin_req(SignInData {
+        email,
+        password: new_password,
+    })
+    .send_request(&app)
+    .await;
+
+    assert_eq!(signin_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_delete_account() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+
+    let delete_res = delete_account_req(DeleteAccountData { password }, &tokens.access)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(delete_res.status(), StatusCode::OK);
+
+    let db = &get_db_conn().await;
+
+    assert!(find_user_by_email(&email, db).await.is_err());
+}
+
+#[actix_web::test]
+async fn test_delete_account_revokes_tokens() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (_, password, tokens) = init_user().await;
+
+    delete_account_req(DeleteAccountData { password }, &tokens.access)
+        .send_request(&app)
+        .await;
+
+    let sessions_res = sessions_req(&tokens.access).send_request(&app).await;
+
+    assert_eq!(sessions_res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_jwks() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let jwks_res = jwks_req().send_request(&app).await;
+
+    assert_eq!(jwks_res.status(), StatusCode::OK);
+
+    let jwks: JwkSet = test::read_body_json(jwks_res).await;
+
+    // secret of symmetric key is never published
+    assert!(jwks.keys.iter().all(|key| key.kty != "oct"));
+}
+
+#[actix_web::test]
+async fn test_oauth_authorize() {
+    mock_oidc_env();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let authorize_res = oauth_authorize_req("mock").send_request(&app).await;
+
+    assert_eq!(authorize_res.status(), StatusCode::OK);
+
+    let authorize: OAuthAuthorizeOut = test::read_body_json(authorize_res).await;
+
+    assert!(authorize
+        .url
+        .starts_with("http://127.0.0.1:9090/authorize?"));
+    assert!(authorize.url.contains("code_challenge_method=S256"));
+    assert!(authorize.url.contains("state="));
+}
+
+#[actix_web::test]
+async fn test_oauth_authorize_unknown_provider() {
+    mock_oidc_env();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let authorize_res = oauth_authorize_req("unknown").send_request(&app).await;
+
+    assert_eq!(authorize_res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_oauth_callback_invalid_state() {
+    mock_oidc_env();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let callback_res = oauth_callback_req(
+        "mock",
+        OAuthCallbackData {
+            code: String::from("code"),
+            state: String::from("unknown-state"),
+        },
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(callback_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_signin_lockout() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, _, _) = init_user().await;
+
+    // default threshold is 5 failed attempts
+    for _ in 0..4 {
+        let signin_res = wrong_signin_req(&email).send_request(&app).await;
+
+        assert_eq!(signin_res.status(), StatusCode::FORBIDDEN);
+    }
+
+    let signin_res = wrong_signin_req(&email).send_request(&app).await;
+
+    assert_eq!(signin_res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(signin_res.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[actix_web::test]
+async fn test_signin_lockout_valid_password() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password) = init_locked_user().await;
+
+    let signin_res = signin_req(SignInData { email, password })
+        .send_request(&app)
+        .await;
+
+    assert_eq!(signin_res.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_web::test]
+async fn test_signin_lockout_audit() {
+    let (email, _) = init_locked_user().await;
+
+    let db = &get_db_conn().await;
+    let attempts = find_failed_logins(&email, db).await.unwrap();
+
+    assert_eq!(attempts.len(), 5);
+    assert!(attempts
+        .iter()
+        .all(|attempt| attempt.reason == "invalid password"));
+}
+
+#[actix_web::test]
+async fn test_ip_rate_limit() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    // unique address, so other tests do not share the window
+    let peer = std::net::SocketAddr::from((rand_ip(), 4000));
+
+    // default limit is 20 requests per minute
+    for _ in 0..20 {
+        let verify_res = verify_req("invalid")
+            .peer_addr(peer)
+            .send_request(&app)
+            .await;
+
+        assert_eq!(verify_res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    let verify_res = verify_req("invalid")
+        .peer_addr(peer)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(verify_res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(verify_res.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[actix_web::test]
+async fn test_client_ip_behind_proxy() {
+    let proxy = std::net::Ipv4Addr::new(10, 0, 0, 1);
+    let config = RateLimitConfig {
+        trusted_proxies: vec![proxy.into()],
+        ..Default::default()
+    };
+
+    // client puts fake address to the left, proxy appends real one
+    let req = test::TestRequest::default()
+        .peer_addr(std::net::SocketAddr::from((proxy, 4000)))
+        .insert_header((header::X_FORWARDED_FOR, "1.1.1.1, 203.0.113.7, 10.0.0.1"))
+        .to_http_request();
+
+    assert_eq!(client_ip(&req, &config), Some(String::from("203.0.113.7")));
+}
+
+#[actix_web::test]
+async fn test_client_ip_untrusted_peer() {
+    let peer = std::net::SocketAddr::from((rand_ip(), 4000));
+
+    let req = test::TestRequest::default()
+        .peer_addr(peer)
+        .insert_header((header::X_FORWARDED_FOR, "1.1.1.1"))
+        .to_http_request();
+
+    assert_eq!(
+        client_ip(&req, &RateLimitConfig::default()),
+        Some(peer.ip().to_string())
+    );
+}
+
+#[actix_web::test]
+async fn test_two_factor_enroll() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (_, _, tokens) = init_user().await;
+
+    let enroll_res = two_factor_enroll_req(&tokens.access)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(enroll_res.status(), StatusCode::OK);
+
+    let enroll: TotpEnrollOut = test::read_body_json(enroll_res).await;
+
+    assert!(enroll.otpauth_url.starts_with("otpauth://totp/"));
+}
+
+#[actix_web::test]
+async fn test_two_factor_confirm() {
+    let (email, _, tokens) = init_user().await;
+
+    let (_, recovery_codes) = init_two_factor(&email, &tokens.access).await;
+
+    assert_eq!(recovery_codes.len(), 10);
+}
+
+#[actix_web::test]
+async fn test_two_factor_signin() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+
+    init_two_factor(&email, &tokens.access).await;
+
+    // sign in returns challenge instead of tokens
+    let signin_res = signin_req(SignInData { email, password })
+        .send_request(&app)
+        .await;
+
+    assert_eq!(signin_res.status(), StatusCode::ACCEPTED);
+
+    let _: TwoFactorChallenge = test::read_body_json(signin_res).await;
+}
+
+#[actix_web::test]
+async fn test_two_factor_verify() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+    let (totp, _) = init_two_factor(&email, &tokens.access).await;
+    let challenge = init_challenge(&email, &password).await;
+
+    let verify_res = two_factor_verify_req(&challenge, &next_code(&totp))
+        .send_request(&app)
+        .await;
+
+    assert_eq!(verify_res.status(), StatusCode::OK);
+
+    let _: Tokens = test::read_body_json(verify_res).await;
+}
+
+#[actix_web::test]
+async fn test_two_factor_verify_wrong_code() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+    init_two_factor(&email, &tokens.access).await;
+    let challenge = init_challenge(&email, &password).await;
+
+    let verify_res = two_factor_verify_req(&challenge, "wrong-code")
+        .send_request(&app)
+        .await;
+
+    assert_eq!(verify_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_two_factor_verify_recovery_code() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+    let (_, recovery_codes) = init_two_factor(&email, &tokens.access).await;
+    let challenge = init_challenge(&email, &password).await;
+
+    let verify_res = two_factor_verify_req(&challenge, &recovery_codes[0])
+        .send_request(&app)
+        .await;
+
+    assert_eq!(verify_res.status(), StatusCode::OK);
+
+    let _: Tokens = test::read_body_json(verify_res).await;
+}
+
+#[actix_web::test]
+async fn test_two_factor_challenge_used() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+    let (_, recovery_codes) = init_two_factor(&email, &tokens.access).await;
+    let challenge = init_challenge(&email, &password).await;
+
+    two_factor_verify_req(&challenge, &recovery_codes[0])
+        .send_request(&app)
+        .await;
+
+    // challenge can be used only once
+    let verify_res = two_factor_verify_req(&challenge, &recovery_codes[1])
+        .send_request(&app)
+        .await;
+
+    assert_eq!(verify_res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_two_factor_recovery_code_used() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+    let (_, recovery_codes) = init_two_factor(&email, &tokens.access).await;
+
+    let challenge = init_challenge(&email, &password).await;
+
+    two_factor_verify_req(&challenge, &recovery_codes[0])
+        .send_request(&app)
+        .await;
+
+    // recovery code can be used only once
+    let challenge = init_challenge(&email, &password).await;
+
+    let verify_res = two_factor_verify_req(&challenge, &recovery_codes[0])
+        .send_request(&app)
+        .await;
+
+    assert_eq!(verify_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_two_factor_code_used() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+    let (totp, _) = init_two_factor(&email, &tokens.access).await;
+    let code = next_code(&totp);
+
+    let challenge = init_challenge(&email, &password).await;
+
+    two_factor_verify_req(&challenge, &code)
+        .send_request(&app)
+        .await;
+
+    // code can be used only once
+    let challenge = init_challenge(&email, &password).await;
+
+    let verify_res = two_factor_verify_req(&challenge, &code)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(verify_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_two_factor_challenge_guessed() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+    let (totp, _) = init_two_factor(&email, &tokens.access).await;
+    let challenge = init_challenge(&email, &password).await;
+
+    // challenge is denied after 3 invalid codes
+    for _ in 0..3 {
+        two_factor_verify_req(&challenge, "000000")
+            .send_request(&app)
+            .await;
+    }
+
+    let verify_res = two_factor_verify_req(&challenge, &next_code(&totp))
+        .send_request(&app)
+        .await;
+
+    assert_eq!(verify_res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_two_factor_verify_account_locked() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, password, tokens) = init_user().await;
+    let (totp, _) = init_two_factor(&email, &tokens.access).await;
+    let challenge = init_challenge(&email, &password).await;
+
+    for _ in 0..5 {
+        wrong_signin_req(&email).send_request(&app).await;
+    }
+
+    let verify_res = two_factor_verify_req(&challenge, &next_code(&totp))
+        .send_request(&app)
+        .await;
+
+    assert_eq!(verify_res.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_web::test]
+async fn test_two_factor_disable_invalid_code() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let (email, _, tokens) = init_user().await;
+    init_two_factor(&email, &tokens.access).await;
+
+    let disable_res = two_factor_disable_req("123", &tokens.access)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(disable_res.status(), StatusCode::BAD_REQUEST);
+}
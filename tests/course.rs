@@ -11,12 +11,20 @@ use fake::{
     Fake,
 };
 use rc_api::{
-    get_app_data, get_db_conn, get_key, main_config,
+    get_app_data, get_db_conn, main_config,
     models::{
-        auth::{SignUpData, Tokens},
-        course::{CourseOut, CreateCourse, UpdateCourse},
+        auth::{SignInData, SignUpData, Tokens},
+        course::{
+            AddCourseMember, CatalogPage, CloneCourse, CourseLevel, CourseMember, CourseOut,
+            CourseRole, CreateCourse, PublishCourse, TemplateCourse, TransferCourse, UpdateCourse,
+            UpdateCourseDetails,
+        },
+        invite::{CourseInvite, CreateInvite, InviteOut},
         language::Language,
+        lesson::{CreateLesson, Lesson},
+        role::Role,
     },
+    services::role::assign_role,
     utils::jwt::{scopes, JwtUtil},
 };
 
@@ -112,13 +120,364 @@ async fn init_user() -> (i32, String) {
 
     let tokens: Tokens = test::call_and_read_body_json(&app, signup_req).await;
 
-    let jwt = JwtUtil { key: get_key() };
+    let jwt = JwtUtil::from_env();
 
     let id = jwt.get_claims(&tokens.access, scopes::ACCESS).unwrap().uid;
 
     (id, tokens.access)
 }
 
+/// Create user with additional role and return fresh token with this role
+async fn init_user_with_role(role: Role) -> (i32, String) {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let email: String = FreeEmail(EN).fake();
+    let password: String = Password(EN, 6..12).fake();
+
+    let signup_req = signup_req(SignUpData {
+        email: email.clone(),
+        username: Username(EN).fake(),
+        password: password.clone(),
+    })
+    .to_request();
+
+    let tokens: Tokens = test::call_and_read_body_json(&app, signup_req).await;
+
+    let id = JwtUtil::from_env()
+        .get_claims(&tokens.access, scopes::ACCESS)
+        .unwrap()
+        .uid;
+
+    assign_role(id, role, &get_db_conn().await).await.unwrap();
+
+    let signin_req = test::TestRequest::post()
+        .uri("/api/auth/signin")
+        .set_json(SignInData { email, password })
+        .to_request();
+
+    let tokens: Tokens = test::call_and_read_body_json(&app, signin_req).await;
+
+    (id, tokens.access)
+}
+
+/// Send reqeust to **/api/course/publish/{id}**
+fn publish_course_req(course_id: i32, public: bool, token: &str) -> test::TestRequest {
+    test::TestRequest::put()
+        .uri(format!("/api/course/publish/{course_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(PublishCourse { public })
+}
+
+/// Send reqeust to **/api/course/{id}/details**
+fn update_course_details_req(
+    course_id: i32,
+    details: UpdateCourseDetails,
+    token: &str,
+) -> test::TestRequest {
+    test::TestRequest::put()
+        .uri(format!("/api/course/{course_id}/details").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(details)
+}
+
+/// Send reqeust to **/api/course/catalog?{query}**
+fn catalog_req(query: &str, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/course/catalog?{query}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send reqeust to **/api/course/{id}/members**
+fn get_members_req(course_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/course/{course_id}/members").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send reqeust to **/api/course/{id}/members**
+fn add_member_req(course_id: i32, member: AddCourseMember, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/course/{course_id}/members").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(member)
+}
+
+/// Send reqeust to **/api/course/{id}/members/{user_id}**
+fn remove_member_req(course_id: i32, user_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::delete()
+        .uri(format!("/api/course/{course_id}/members/{user_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send reqeust to **/api/course/{id}/transfer**
+fn transfer_course_req(course_id: i32, user_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/course/{course_id}/transfer").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(TransferCourse { user_id })
+}
+
+/// Send reqeust to **/api/course/{id}/invites**
+fn create_invite_req(course_id: i32, invite: CreateInvite, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/course/{course_id}/invites").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(invite)
+}
+
+/// Send reqeust to **/api/course/{id}/invites**
+fn get_invites_req(course_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/course/{course_id}/invites").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send reqeust to **/api/course/{id}/invites/{invite_id}**
+fn revoke_invite_req(course_id: i32, invite_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::delete()
+        .uri(format!("/api/course/{course_id}/invites/{invite_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send reqeust to **/api/course/template/{id}**
+fn template_course_req(course_id: i32, template: bool, token: &str) -> test::TestRequest {
+    test::TestRequest::put()
+        .uri(format!("/api/course/template/{course_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(TemplateCourse { template })
+}
+
+/// Send reqeust to **/api/course/templates**
+fn get_templates_req(token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri("/api/course/templates")
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send reqeust to **/api/course/{id}/clone**
+fn clone_course_req(course_id: i32, title: Option<String>, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/course/{course_id}/clone").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(CloneCourse { title })
+}
+
+/// Send reqeust to **/api/lesson/create**
+fn create_lesson_req(lesson: CreateLesson, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/lesson/create")
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(lesson)
+}
+
+/// Send reqeust to **/api/lesson/all?course={id}**
+fn get_lessons_in_course_req(course_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/lesson/all?course={course_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send reqeust to **/api/admin/users/{id}/roles**
+fn get_user_roles_req(user_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/admin/users/{user_id}/roles").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+async fn init_course(token: &str) -> i32 {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let title: Vec<String> = Words(EN, 5..12).fake();
+
+    let create_course_res = create_course_req(
+        CreateCourse {
+            title: title.join(" "),
+            language: Language::En,
+        },
+        token,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_course_res.status(), StatusCode::CREATED);
+
+    test::read_body_json(create_course_res).await
+}
+
+/// New user which was added to course by owner as editor
+async fn init_editor(course_id: i32, owner: &str) -> (i32, String) {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let email: String = FreeEmail(EN).fake();
+
+    let signup_req = signup_req(SignUpData {
+        email: email.clone(),
+        username: Username(EN).fake(),
+        password: Password(EN, 6..12).fake(),
+    })
+    .to_request();
+
+    let tokens: Tokens = test::call_and_read_body_json(&app, signup_req).await;
+
+    let id = JwtUtil::from_env()
+        .get_claims(&tokens.access, scopes::ACCESS)
+        .unwrap()
+        .uid;
+
+    let add_member_res = add_member_req(
+        course_id,
+        AddCourseMember {
+            email: Some(email),
+            username: None,
+            role: CourseRole::Editor,
+        },
+        owner,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(add_member_res.status(), StatusCode::OK);
+
+    (id, tokens.access)
+}
+
+/// Invite which can be used only once
+async fn init_invite(course_id: i32, owner: &str) -> CourseInvite {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let create_invite_res = create_invite_req(
+        course_id,
+        CreateInvite {
+            name: Some(String::from("single use")),
+            expires_at: None,
+            max_uses: Some(1),
+        },
+        owner,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_invite_res.status(), StatusCode::OK);
+
+    test::read_body_json(create_invite_res).await
+}
+
+/// Three german courses for beginners with `tag`, only first two are public
+async fn init_catalog(tag: &str, teacher: &str) -> Vec<i32> {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let mut course_ids = Vec::new();
+
+    for public in [true, true, false] {
+        let title: Vec<String> = Words(EN, 5..12).fake();
+
+        let create_course_res = create_course_req(
+            CreateCourse {
+                title: title.join(" "),
+                language: Language::De,
+            },
+            teacher,
+        )
+        .send_request(&app)
+        .await;
+        let course_id: i32 = test::read_body_json(create_course_res).await;
+
+        let details_res = update_course_details_req(
+            course_id,
+            UpdateCourseDetails {
+                description: format!("course about {tag}"),
+                level: Some(CourseLevel::Beginner),
+                tags: vec![tag.to_uppercase(), String::from("grammar")],
+            },
+            teacher,
+        )
+        .send_request(&app)
+        .await;
+
+        assert_eq!(details_res.status(), StatusCode::OK);
+
+        if public {
+            let publish_res = publish_course_req(course_id, true, teacher)
+                .send_request(&app)
+                .await;
+
+            assert_eq!(publish_res.status(), StatusCode::OK);
+        }
+
+        course_ids.push(course_id);
+    }
+
+    course_ids
+}
+
+/// Public course with one lesson, `template` makes it template
+async fn init_public_course(teacher: &str, template: bool) -> i32 {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let course_id = init_course(teacher).await;
+
+    let create_lesson_res = create_lesson_req(
+        CreateLesson {
+            title: String::from("first lesson"),
+            cover_path: None,
+            subject: None,
+            course_id,
+            module_id: None,
+        },
+        teacher,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_lesson_res.status(), StatusCode::CREATED);
+
+    let publish_res = publish_course_req(course_id, true, teacher)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(publish_res.status(), StatusCode::OK);
+
+    if template {
+        let template_res = template_course_req(course_id, true, teacher)
+            .send_request(&app)
+            .await;
+
+        assert_eq!(template_res.status(), StatusCode::OK);
+    }
+
+    course_id
+}
+
 #[actix_web::test]
 async fn test_create_course_success() {
     let app = test::init_service(
@@ -286,6 +645,19 @@ async fn test_get_all_courses() {
 
     let user = init_user().await;
 
+    // private course is visible for its owner
+    let title: Vec<String> = Words(EN, 5..12).fake();
+    let create_course_res = create_course_req(
+        CreateCourse {
+            title: title.join(" "),
+            language: Language::En,
+        },
+        user.1.as_str(),
+    )
+    .send_request(&app)
+    .await;
+    assert!(create_course_res.status().is_success());
+
     let get_courses_res = get_courses_req(user.1.as_str()).send_request(&app).await;
 
     assert_eq!(get_courses_res.status(), StatusCode::OK);
@@ -964,3 +1336,697 @@ async fn test_delete_course_not_found() {
 
     assert_eq!(delete_course_res.status(), StatusCode::NOT_FOUND);
 }
+
+#[actix_web::test]
+async fn test_publish_course_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let student = init_user().await;
+    let course_id = init_course(&student.1).await;
+
+    let publish_res = publish_course_req(course_id, true, &student.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(publish_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_publish_course_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let course_id = init_course(&teacher.1).await;
+
+    let publish_res = publish_course_req(course_id, true, &teacher.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(publish_res.status(), StatusCode::OK);
+
+    let get_course_res = get_course_req(course_id, &teacher.1)
+        .send_request(&app)
+        .await;
+    let course: CourseOut = test::read_body_json(get_course_res).await;
+
+    assert!(course.is_public);
+}
+
+#[actix_web::test]
+async fn test_update_course_by_admin() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let admin = init_user_with_role(Role::Admin).await;
+    let course_id = init_course(&owner.1).await;
+
+    let update_course_res = update_course_req(
+        UpdateCourse {
+            id: course_id,
+            title: String::from("moderated"),
+            language: Language::En,
+        },
+        &admin.1,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(update_course_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_delete_course_by_admin() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let admin = init_user_with_role(Role::Admin).await;
+    let course_id = init_course(&owner.1).await;
+
+    let delete_course_res = delete_course_req(course_id, &admin.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(delete_course_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_get_user_roles_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let student = init_user().await;
+
+    let roles_res = get_user_roles_req(student.0, &student.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(roles_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_get_user_roles_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let student = init_user().await;
+    let admin = init_user_with_role(Role::Admin).await;
+
+    let roles_res = get_user_roles_req(student.0, &admin.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(roles_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_get_members_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner.1).await;
+
+    let members_res = get_members_req(course_id, &stranger.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(members_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_add_member_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner.1).await;
+    let editor = init_editor(course_id, &owner.1).await;
+
+    let members_res = get_members_req(course_id, &editor.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(members_res.status(), StatusCode::OK);
+
+    let members: Vec<CourseMember> = test::read_body_json(members_res).await;
+
+    assert_eq!(members.len(), 2);
+}
+
+#[actix_web::test]
+async fn test_create_lesson_by_editor() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner.1).await;
+    let editor = init_editor(course_id, &owner.1).await;
+
+    let create_lesson_res = create_lesson_req(
+        CreateLesson {
+            title: String::from("lesson by editor"),
+            cover_path: None,
+            subject: None,
+            course_id,
+            module_id: None,
+        },
+        &editor.1,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_lesson_res.status(), StatusCode::CREATED);
+}
+
+#[actix_web::test]
+async fn test_delete_course_by_editor_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner.1).await;
+    let editor = init_editor(course_id, &owner.1).await;
+
+    let delete_course_res = delete_course_req(course_id, &editor.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(delete_course_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_transfer_course_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner.1).await;
+    let editor = init_editor(course_id, &owner.1).await;
+
+    let transfer_res = transfer_course_req(course_id, editor.0, &owner.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(transfer_res.status(), StatusCode::OK);
+
+    let is_owner_res = user_is_owner_req(course_id, &editor.1)
+        .send_request(&app)
+        .await;
+    let is_owner: bool = test::read_body_json(is_owner_res).await;
+
+    assert!(is_owner);
+}
+
+#[actix_web::test]
+async fn test_remove_member_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner.1).await;
+    let editor = init_editor(course_id, &owner.1).await;
+
+    let transfer_res = transfer_course_req(course_id, editor.0, &owner.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(transfer_res.status(), StatusCode::OK);
+
+    // previous owner became editor and can be removed by new owner
+    let remove_member_res = remove_member_req(course_id, owner.0, &editor.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(remove_member_res.status(), StatusCode::OK);
+
+    let members_res = get_members_req(course_id, &owner.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(members_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_create_invite_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner.1).await;
+
+    let create_invite_res = create_invite_req(
+        course_id,
+        CreateInvite {
+            name: None,
+            expires_at: None,
+            max_uses: None,
+        },
+        &stranger.1,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_invite_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_subscribe_used_up_invite() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let first_user = init_user().await;
+    let second_user = init_user().await;
+    let course_id = init_course(&owner.1).await;
+    let invite = init_invite(course_id, &owner.1).await;
+
+    let subscribe_res = subscribe_to_course_req(&invite.token, &first_user.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(subscribe_res.status(), StatusCode::OK);
+
+    let subscribe_res = subscribe_to_course_req(&invite.token, &second_user.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(subscribe_res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_get_invites_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let student = init_user().await;
+    let course_id = init_course(&owner.1).await;
+    let invite = init_invite(course_id, &owner.1).await;
+
+    let subscribe_res = subscribe_to_course_req(&invite.token, &student.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(subscribe_res.status(), StatusCode::OK);
+
+    let invites_res = get_invites_req(course_id, &owner.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(invites_res.status(), StatusCode::OK);
+
+    let invites: Vec<InviteOut> = test::read_body_json(invites_res).await;
+
+    assert_eq!(invites.len(), 1);
+    assert_eq!(invites[0].invite.uses, 1);
+    assert_eq!(invites[0].members.len(), 1);
+    assert_eq!(invites[0].members[0].user_id, student.0);
+}
+
+#[actix_web::test]
+async fn test_subscribe_revoked_invite() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let student = init_user().await;
+    let course_id = init_course(&owner.1).await;
+    let invite = init_invite(course_id, &owner.1).await;
+
+    let revoke_res = revoke_invite_req(course_id, invite.id, &owner.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(revoke_res.status(), StatusCode::OK);
+
+    let subscribe_res = subscribe_to_course_req(&invite.token, &student.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(subscribe_res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_catalog_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+
+    // unique tag to separate courses of this test
+    let tag = uuid::Uuid::new_v4().simple().to_string();
+    let course_ids = init_catalog(&tag, &teacher.1).await;
+
+    // private course is not in catalog, newest course is first
+    let catalog_res = catalog_req(
+        &format!("tags={tag},grammar&level=beginner&language=de"),
+        &student.1,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(catalog_res.status(), StatusCode::OK);
+
+    let page: CatalogPage = test::read_body_json(catalog_res).await;
+    let ids: Vec<i32> = page.courses.iter().map(|course| course.id).collect();
+
+    assert_eq!(ids, vec![course_ids[1], course_ids[0]]);
+    assert!(page.next_cursor.is_none());
+}
+
+#[actix_web::test]
+async fn test_catalog_filter_by_level() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+
+    let tag = uuid::Uuid::new_v4().simple().to_string();
+    init_catalog(&tag, &teacher.1).await;
+
+    let catalog_res = catalog_req(&format!("tags={tag}&level=advanced"), &student.1)
+        .send_request(&app)
+        .await;
+    let page: CatalogPage = test::read_body_json(catalog_res).await;
+
+    assert!(page.courses.is_empty());
+}
+
+#[actix_web::test]
+async fn test_catalog_next_page() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+
+    let tag = uuid::Uuid::new_v4().simple().to_string();
+    init_catalog(&tag, &teacher.1).await;
+
+    let catalog_res = catalog_req(&format!("q={tag}&limit=1&sort=popular"), &student.1)
+        .send_request(&app)
+        .await;
+    let page: CatalogPage = test::read_body_json(catalog_res).await;
+
+    assert_eq!(page.courses.len(), 1);
+
+    let cursor = page.next_cursor.unwrap();
+
+    let catalog_res = catalog_req(
+        &format!("q={tag}&limit=1&sort=popular&cursor={cursor}"),
+        &student.1,
+    )
+    .send_request(&app)
+    .await;
+    let next_page: CatalogPage = test::read_body_json(catalog_res).await;
+
+    assert_eq!(next_page.courses.len(), 1);
+    assert_ne!(next_page.courses[0].id, page.courses[0].id);
+    assert!(next_page.next_cursor.is_none());
+}
+
+#[actix_web::test]
+async fn test_catalog_cursor_of_other_sort() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+
+    let tag = uuid::Uuid::new_v4().simple().to_string();
+    init_catalog(&tag, &teacher.1).await;
+
+    let catalog_res = catalog_req(&format!("q={tag}&limit=1&sort=popular"), &student.1)
+        .send_request(&app)
+        .await;
+    let page: CatalogPage = test::read_body_json(catalog_res).await;
+    let cursor = page.next_cursor.unwrap();
+
+    // cursor of popular courses cannot be used with other sort
+    let catalog_res = catalog_req(
+        &format!("q={tag}&limit=1&sort=recent&cursor={cursor}"),
+        &student.1,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(catalog_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_get_public_course_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+    let course_id = init_public_course(&teacher.1, false).await;
+
+    let get_course_res = get_course_req(course_id, &student.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_course_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_get_private_course_not_found() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+    let course_id = init_course(&teacher.1).await;
+
+    // private course is available only for members
+    let get_course_res = get_course_req(course_id, &student.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_course_res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_clone_course_not_template() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+    let course_id = init_public_course(&teacher.1, false).await;
+
+    let clone_res = clone_course_req(course_id, None, &student.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(clone_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_template_course_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+    let course_id = init_public_course(&teacher.1, false).await;
+
+    let template_res = template_course_req(course_id, true, &student.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(template_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_get_templates_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+    let course_id = init_public_course(&teacher.1, true).await;
+
+    let templates_res = get_templates_req(&student.1).send_request(&app).await;
+
+    assert_eq!(templates_res.status(), StatusCode::OK);
+
+    let templates: Vec<CourseOut> = test::read_body_json(templates_res).await;
+
+    assert!(templates.iter().any(|course| course.id == course_id));
+}
+
+#[actix_web::test]
+async fn test_clone_course_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+    let course_id = init_public_course(&teacher.1, true).await;
+
+    let clone_res = clone_course_req(course_id, Some(String::from("my copy")), &student.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(clone_res.status(), StatusCode::CREATED);
+
+    let new_course_id: i32 = test::read_body_json(clone_res).await;
+
+    assert_ne!(new_course_id, course_id);
+
+    let get_course_res = get_course_req(new_course_id, &student.1)
+        .send_request(&app)
+        .await;
+    let course: CourseOut = test::read_body_json(get_course_res).await;
+
+    assert_eq!(course.title, "my copy");
+    assert!(course.is_owner);
+    assert!(!course.is_public);
+    assert!(!course.is_template);
+
+    let lessons_res = get_lessons_in_course_req(new_course_id, &student.1)
+        .send_request(&app)
+        .await;
+    let lessons: Vec<Lesson> = test::read_body_json(lessons_res).await;
+
+    assert_eq!(lessons.len(), 1);
+    assert_eq!(lessons[0].title, "first lesson");
+}
+
+#[actix_web::test]
+async fn test_cloned_course_is_private() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let teacher = init_user_with_role(Role::Teacher).await;
+    let student = init_user().await;
+    let course_id = init_public_course(&teacher.1, true).await;
+
+    let clone_res = clone_course_req(course_id, None, &student.1)
+        .send_request(&app)
+        .await;
+    let new_course_id: i32 = test::read_body_json(clone_res).await;
+
+    let get_course_res = get_course_req(new_course_id, &teacher.1)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_course_res.status(), StatusCode::NOT_FOUND);
+}
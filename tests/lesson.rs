@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use actix_web::{
     http::{header, StatusCode},
     test, App,
@@ -12,14 +14,37 @@ use fake::{
 };
 
 use rc_api::{
-    get_app_data, main_config,
+    get_app_data, get_db_conn, main_config,
     models::{
         auth::{SignUpData, Tokens},
-        course::CreateCourse,
+        card::Card,
+        course::{AddCourseMember, CourseRole, CreateCourse},
+        exercise::{
+            CreateExercise, ExerciseAnswer, ExerciseAttempt, ExerciseContent, ExerciseOut,
+            ExerciseTask, SubmitAnswer,
+        },
+        image::Image,
         language::Language,
-        lesson::{CreateLesson, Lesson, UpdateLesson},
+        lesson::{
+            CreateLesson, CreateModule, DiffLine, DiffTag, Lesson, LessonOrder, LessonOut,
+            PreviewLesson, ReorderLessons, RevisionInfo, UpdateLesson,
+        },
+        progress::{CourseDashboard, CourseProgress, UpdateLessonProgress},
+        vocabulary::{CreateLessonWord, LessonWord, UpdateLessonWord},
     },
+    services::course::set_course_public,
+    utils::{gc::collect_garbage, storage::BlobKind},
 };
+use uuid::Uuid;
+
+/// Boundary of multipart form of uploaded images
+const IMAGE_BOUNDARY: &str = "image-boundary";
+
+/// Article with embedded image for import
+const ARTICLE: &str = "<html><head><title>Present Simple</title></head>\
+                       <body><h2>Rules</h2><p>I <b>work</b> every day.</p>\
+                       <img alt=\"office\" src=\"data:image/svg+xml;base64,PHN2ZyB4bWxucz0iaHR0cDovL3d3dy53My5vcmcvMjAwMC9zdmciIHdpZHRoPSIxIiBoZWlnaHQ9IjEiPjxyZWN0IHdpZHRoPSIxIiBoZWlnaHQ9IjEiLz48L3N2Zz4=\">\
+                       </body></html>";
 
 /// Send request to **/api/lesson/create**
 fn create_lesson_req(lesson: CreateLesson, token: &str) -> test::TestRequest {
@@ -73,6 +98,261 @@ fn upload_lesson_req(id: i32, text: String, token: &str) -> test::TestRequest {
         .set_payload(text)
 }
 
+/// Send request to **/api/lesson/text/{id}**, `query` is appended to path
+fn get_lesson_text_req(id: i32, query: &str, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/lesson/text/{id}{query}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/lesson/module/create**
+fn create_module_req(module: CreateModule, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/lesson/module/create")
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(module)
+}
+
+/// Send request to **/api/lesson/modules/{course_id}**
+fn get_modules_req(course_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/lesson/modules/{course_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/lesson/order/{course_id}**
+fn reorder_lessons_req(course_id: i32, order: ReorderLessons, token: &str) -> test::TestRequest {
+    test::TestRequest::put()
+        .uri(format!("/api/lesson/order/{course_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(order)
+}
+
+/// Send request to **/api/lesson/preview/{id}**
+fn preview_lesson_req(id: i32, preview: bool, token: &str) -> test::TestRequest {
+    test::TestRequest::put()
+        .uri(format!("/api/lesson/preview/{id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(PreviewLesson { preview })
+}
+
+/// Send request to **/api/lesson/revision/{id}/publish**
+fn publish_revision_req(id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/lesson/revision/{id}/publish").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/lesson/revision/{id}/restore**
+fn restore_revision_req(id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/lesson/revision/{id}/restore").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/lesson/revisions/{lesson_id}**
+fn get_revisions_req(lesson_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/lesson/revisions/{lesson_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/lesson/diff/{from}/{to}**
+fn diff_revisions_req(from: i32, to: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/lesson/diff/{from}/{to}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/lesson/import/{course_id}** with one file
+fn import_lessons_req(
+    course_id: i32,
+    filename: &str,
+    content: &str,
+    token: &str,
+) -> test::TestRequest {
+    let boundary = "import-boundary";
+    let body = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n\
+         {content}\r\n\
+         --{boundary}--\r\n"
+    );
+
+    test::TestRequest::post()
+        .uri(format!("/api/lesson/import/{course_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .append_header((
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={boundary}"),
+        ))
+        .set_payload(body)
+}
+
+/// Part of multipart form of **/api/image/upload** with file
+fn image_part(filename: &str, content: &[u8]) -> Vec<u8> {
+    let mut part = format!(
+        "--{IMAGE_BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n"
+    )
+    .into_bytes();
+    part.extend_from_slice(content);
+    part.extend_from_slice(b"\r\n");
+
+    part
+}
+
+/// Send request to **/api/image/upload** with all `parts` of form
+fn upload_image_req(parts: &[Vec<u8>], token: &str) -> test::TestRequest {
+    let mut body = parts.concat();
+    body.extend_from_slice(format!("--{IMAGE_BOUNDARY}--\r\n").as_bytes());
+
+    test::TestRequest::post()
+        .uri("/api/image/upload")
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .append_header((
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={IMAGE_BOUNDARY}"),
+        ))
+        .set_payload(body)
+}
+
+/// Send request to **/api/image/{filename}**, `query` is appended to path
+fn get_image_req(filename: &str, query: &str) -> test::TestRequest {
+    test::TestRequest::get().uri(format!("/api/image/{filename}{query}").as_str())
+}
+
+/// Send request to **/api/image/info/{filename}**
+fn get_image_info_req(filename: &str, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/image/info/{filename}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/progress/lesson/{lesson_id}**
+fn update_progress_req(
+    lesson_id: i32,
+    progress: UpdateLessonProgress,
+    token: &str,
+) -> test::TestRequest {
+    test::TestRequest::put()
+        .uri(format!("/api/progress/lesson/{lesson_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(progress)
+}
+
+/// Send request to **/api/progress/course/{course_id}**
+fn get_course_progress_req(course_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/progress/course/{course_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/progress/course/{course_id}/dashboard**
+fn get_dashboard_req(course_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/progress/course/{course_id}/dashboard").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/exercise/create**
+fn create_exercise_req(exercise: CreateExercise, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/exercise/create")
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(exercise)
+}
+
+/// Send request to **/api/exercise/lesson/{lesson_id}**
+fn get_exercises_req(lesson_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/exercise/lesson/{lesson_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/exercise/submit/{id}**
+fn submit_answer_req(id: i32, answer: ExerciseAnswer, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/exercise/submit/{id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(SubmitAnswer { answer })
+}
+
+/// Send request to **/api/exercise/attempts/{id}**
+fn get_attempts_req(id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/exercise/attempts/{id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/vocabulary/create**
+fn create_word_req(word: CreateLessonWord, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/vocabulary/create")
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(word)
+}
+
+/// Send request to **/api/vocabulary/update**
+fn update_word_req(word: UpdateLessonWord, token: &str) -> test::TestRequest {
+    test::TestRequest::put()
+        .uri("/api/vocabulary/update")
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(word)
+}
+
+/// Send request to **/api/vocabulary/delete/{id}**
+fn delete_word_req(id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::delete()
+        .uri(format!("/api/vocabulary/delete/{id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/vocabulary/lesson/{lesson_id}**
+fn get_words_req(lesson_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/vocabulary/lesson/{lesson_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/vocabulary/lesson/{lesson_id}/cards**
+fn add_words_to_cards_req(lesson_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/vocabulary/lesson/{lesson_id}/cards").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// Send request to **/api/card/all/{group_id}**
+fn get_cards_req(group_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/card/all/{group_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// send request to **/api/course/invite/generate/{course_id}**
+fn generate_invite_link_req(course_id: i32, token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(format!("/api/course/invite/generate/{course_id}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// send request to **/api/course/subscribe/{invite_link}**
+fn subscribe_req(invite_link: &str, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/course/subscribe/{invite_link}").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+}
+
+/// send request to **/api/course/{course_id}/members**
+fn add_member_req(course_id: i32, member: AddCourseMember, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(format!("/api/course/{course_id}/members").as_str())
+        .append_header((header::AUTHORIZATION, format!("Bearer {token}")))
+        .set_json(member)
+}
+
 /// send request to **/api/auth/signup**
 fn signup_req(data: SignUpData) -> test::TestRequest {
     test::TestRequest::post()
@@ -155,6 +435,7 @@ async fn init_lesson(course_id: i32, user: &str) -> i32 {
             subject,
             cover_path: None,
             course_id,
+            module_id: None,
         },
         user,
     )
@@ -166,6 +447,190 @@ async fn init_lesson(course_id: i32, user: &str) -> i32 {
     test::read_body_json(create_lesson_res).await
 }
 
+async fn init_module(course_id: i32, user: &str) -> i32 {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let create_module_res = create_module_req(
+        CreateModule {
+            title: String::from("first module"),
+            course_id,
+        },
+        user,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_module_res.status(), StatusCode::CREATED);
+
+    test::read_body_json(create_module_res).await
+}
+
+/// New user which joined course by invite link
+async fn init_student(course_id: i32, owner: &str) -> String {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let generate_link_res = generate_invite_link_req(course_id, owner)
+        .send_request(&app)
+        .await;
+    let invite_link: String = test::read_body_json(generate_link_res).await;
+
+    let student = init_user().await;
+
+    let subscribe_res = subscribe_req(&invite_link, &student)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(subscribe_res.status(), StatusCode::OK);
+
+    student
+}
+
+/// New user which was added to course by owner as editor
+async fn init_editor(course_id: i32, owner: &str) -> String {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let email: String = FreeEmail(EN).fake();
+
+    let signup_res = signup_req(SignUpData {
+        email: email.clone(),
+        username: Username(EN).fake(),
+        password: Password(EN, 6..12).fake(),
+    })
+    .send_request(&app)
+    .await;
+    let tokens: Tokens = test::read_body_json(signup_res).await;
+
+    let add_member_res = add_member_req(
+        course_id,
+        AddCourseMember {
+            email: Some(email),
+            username: None,
+            role: CourseRole::Editor,
+        },
+        owner,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(add_member_res.status(), StatusCode::OK);
+
+    tokens.access
+}
+
+async fn init_word(lesson_id: i32, word: &str, translation: &str, user: &str) -> i32 {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let create_word_res = create_word_req(
+        CreateLessonWord {
+            word: String::from(word),
+            translation: String::from(translation),
+            example: None,
+            lesson_id,
+        },
+        user,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_word_res.status(), StatusCode::CREATED);
+
+    test::read_body_json(create_word_res).await
+}
+
+/// Exercise with one blank, `am` and `'m` are correct answers
+async fn init_exercise(lesson_id: i32, user: &str) -> i32 {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let create_exercise_res = create_exercise_req(
+        CreateExercise {
+            prompt: String::from("complete the task"),
+            content: ExerciseContent::FillBlank {
+                text: String::from("I ___ a student"),
+                answers: vec![vec![String::from("am"), String::from("'m")]],
+            },
+            lesson_id,
+        },
+        user,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_exercise_res.status(), StatusCode::CREATED);
+
+    test::read_body_json(create_exercise_res).await
+}
+
+/// Draft of lesson text, id of revision is returned
+async fn init_revision(lesson_id: i32, text: &str, user: &str) -> i32 {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let upload_lesson_res = upload_lesson_req(lesson_id, String::from(text), user)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(upload_lesson_res.status(), StatusCode::OK);
+
+    test::read_body_json(upload_lesson_res).await
+}
+
+async fn init_image(filename: &str, content: &[u8], user: &str) -> String {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let upload_res = upload_image_req(&[image_part(filename, content)], user)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(upload_res.status(), StatusCode::OK);
+
+    test::read_body_json(upload_res).await
+}
+
+/// PNG image 800x400
+fn cover_png() -> Vec<u8> {
+    let mut png = Vec::new();
+
+    image::RgbImage::from_pixel(800, 400, image::Rgb([200, 30, 30]))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+
+    png
+}
+
 #[actix_web::test]
 async fn test_create_lesson_success() {
     let app = test::init_service(
@@ -190,6 +655,7 @@ async fn test_create_lesson_success() {
             subject,
             cover_path: None,
             course_id,
+            module_id: None,
         },
         &user,
     )
@@ -226,6 +692,7 @@ async fn test_create_lesson_fordibben() {
             subject,
             cover_path: None,
             course_id,
+            module_id: None,
         },
         &user,
     )
@@ -253,6 +720,7 @@ async fn test_create_lesson_bad_request() {
             subject: None,
             cover_path: None,
             course_id,
+            module_id: None,
         },
         &user,
     )
@@ -286,6 +754,7 @@ async fn test_create_lesson_is_private() {
             subject,
             cover_path: None,
             course_id,
+            module_id: None,
         },
         "wrong data",
     )
@@ -669,3 +1138,1430 @@ async fn test_upload_lesson_text_forbidden() {
 
     assert_eq!(upload_lesson_res.status(), StatusCode::FORBIDDEN);
 }
+
+#[actix_web::test]
+async fn test_reorder_lessons_bad_request() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let user = init_user().await;
+    let course_id = init_course(&user).await;
+    let first_lesson_id = init_lesson(course_id, &user).await;
+    init_lesson(course_id, &user).await;
+    let module_id = init_module(course_id, &user).await;
+
+    // every lesson of course must be in new order
+    let reorder_res = reorder_lessons_req(
+        course_id,
+        ReorderLessons {
+            modules: vec![module_id],
+            lessons: vec![LessonOrder {
+                id: first_lesson_id,
+                module_id: None,
+            }],
+        },
+        &user,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(reorder_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_reorder_lessons_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let user = init_user().await;
+    let course_id = init_course(&user).await;
+
+    let mut lesson_ids = Vec::new();
+    for _ in 0..3 {
+        lesson_ids.push(init_lesson(course_id, &user).await);
+    }
+
+    let module_id = init_module(course_id, &user).await;
+
+    let new_order = vec![lesson_ids[2], lesson_ids[0], lesson_ids[1]];
+    let reorder_res = reorder_lessons_req(
+        course_id,
+        ReorderLessons {
+            modules: vec![module_id],
+            lessons: new_order
+                .iter()
+                .map(|id| LessonOrder {
+                    id: *id,
+                    module_id: Some(module_id),
+                })
+                .collect(),
+        },
+        &user,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(reorder_res.status(), StatusCode::OK);
+
+    let get_lessons_res = get_lessons_in_course_req(course_id, &user)
+        .send_request(&app)
+        .await;
+    let lessons: Vec<Lesson> = test::read_body_json(get_lessons_res).await;
+    let ids: Vec<i32> = lessons.iter().map(|lesson| lesson.id).collect();
+
+    assert_eq!(ids, new_order);
+    assert!(lessons
+        .iter()
+        .all(|lesson| lesson.module_id == Some(module_id)));
+
+    let get_lesson_res = get_lesson_req(lesson_ids[0], &user)
+        .send_request(&app)
+        .await;
+    let lesson: LessonOut = test::read_body_json(get_lesson_res).await;
+
+    assert_eq!(lesson.prev.unwrap().id, lesson_ids[2]);
+    assert_eq!(lesson.next.unwrap().id, lesson_ids[1]);
+
+    let get_lesson_res = get_lesson_req(lesson_ids[2], &user)
+        .send_request(&app)
+        .await;
+    let lesson: LessonOut = test::read_body_json(get_lesson_res).await;
+
+    assert!(lesson.prev.is_none());
+}
+
+#[actix_web::test]
+async fn test_update_progress_not_found() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+
+    // course is private, user has to join it first
+    let progress_res = update_progress_req(lesson_id, UpdateLessonProgress::default(), &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(progress_res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_update_progress_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let first_lesson_id = init_lesson(course_id, &owner).await;
+    let second_lesson_id = init_lesson(course_id, &owner).await;
+    let student = init_student(course_id, &owner).await;
+
+    for time_spent_secs in [60, 30] {
+        let progress_res = update_progress_req(
+            first_lesson_id,
+            UpdateLessonProgress {
+                completed: Some(true),
+                time_spent_secs,
+                last_position: Some(120),
+            },
+            &student,
+        )
+        .send_request(&app)
+        .await;
+
+        assert_eq!(progress_res.status(), StatusCode::OK);
+    }
+
+    let course_progress_res = get_course_progress_req(course_id, &student)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(course_progress_res.status(), StatusCode::OK);
+
+    let progress: CourseProgress = test::read_body_json(course_progress_res).await;
+
+    assert_eq!(progress.total_lessons, 2);
+    assert_eq!(progress.completed_lessons, 1);
+    assert_eq!(progress.percent, 50.0);
+    assert_eq!(progress.time_spent_secs, 90);
+    assert_eq!(progress.next_lesson.unwrap().id, second_lesson_id);
+}
+
+#[actix_web::test]
+async fn test_get_dashboard_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let student = init_student(course_id, &owner).await;
+
+    let dashboard_res = get_dashboard_req(course_id, &student)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(dashboard_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_get_dashboard_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    init_lesson(course_id, &owner).await;
+    let student = init_student(course_id, &owner).await;
+
+    let progress_res = update_progress_req(
+        lesson_id,
+        UpdateLessonProgress {
+            completed: Some(true),
+            time_spent_secs: 60,
+            last_position: None,
+        },
+        &student,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(progress_res.status(), StatusCode::OK);
+
+    let dashboard_res = get_dashboard_req(course_id, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(dashboard_res.status(), StatusCode::OK);
+
+    let dashboard: CourseDashboard = test::read_body_json(dashboard_res).await;
+
+    assert_eq!(dashboard.students.len(), 1);
+    assert_eq!(dashboard.students[0].completed_lessons, 1);
+    assert_eq!(dashboard.average_percent, 50.0);
+}
+
+#[actix_web::test]
+async fn test_create_exercise_bad_request() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+
+    // answer refers to missing option
+    let create_exercise_res = create_exercise_req(
+        CreateExercise {
+            prompt: String::from("choose the answer"),
+            content: ExerciseContent::MultipleChoice {
+                options: vec![String::from("a")],
+                answers: vec![3],
+            },
+            lesson_id,
+        },
+        &owner,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_exercise_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_get_exercises_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    init_exercise(lesson_id, &owner).await;
+
+    let get_exercises_res = get_exercises_req(lesson_id, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_exercises_res.status(), StatusCode::OK);
+
+    let exercises: Vec<ExerciseOut> = test::read_body_json(get_exercises_res).await;
+
+    // answers are not sent with task
+    assert_eq!(exercises.len(), 1);
+    assert_eq!(
+        exercises[0].task,
+        ExerciseTask::FillBlank {
+            text: String::from("I ___ a student")
+        }
+    );
+}
+
+#[actix_web::test]
+async fn test_get_exercises_not_found() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    init_exercise(lesson_id, &owner).await;
+
+    let get_exercises_res = get_exercises_req(lesson_id, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_exercises_res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_submit_answer_bad_request() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let exercise_id = init_exercise(lesson_id, &owner).await;
+
+    // kind of answer differs from kind of exercise
+    let submit_res = submit_answer_req(
+        exercise_id,
+        ExerciseAnswer::Translate {
+            text: String::from("am"),
+        },
+        &owner,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(submit_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_submit_answer_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let exercise_id = init_exercise(lesson_id, &owner).await;
+
+    for (blank, correct) in [("is", false), (" AM ", true)] {
+        let submit_res = submit_answer_req(
+            exercise_id,
+            ExerciseAnswer::FillBlank {
+                blanks: vec![String::from(blank)],
+            },
+            &owner,
+        )
+        .send_request(&app)
+        .await;
+
+        assert_eq!(submit_res.status(), StatusCode::OK);
+
+        let attempt: ExerciseAttempt = test::read_body_json(submit_res).await;
+
+        assert_eq!(attempt.correct, correct);
+    }
+
+    let get_attempts_res = get_attempts_req(exercise_id, &owner)
+        .send_request(&app)
+        .await;
+    let attempts: Vec<ExerciseAttempt> = test::read_body_json(get_attempts_res).await;
+
+    // the newest attempt is first
+    assert_eq!(attempts.len(), 2);
+    assert!(attempts[0].correct);
+}
+
+#[actix_web::test]
+async fn test_create_word_bad_request() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+
+    let create_word_res = create_word_req(
+        CreateLessonWord {
+            word: String::new(),
+            translation: String::from("кот"),
+            example: None,
+            lesson_id,
+        },
+        &owner,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(create_word_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_add_words_to_cards_not_found() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    init_word(lesson_id, "cat", "кот", &owner).await;
+
+    let add_to_cards_res = add_words_to_cards_req(lesson_id, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(add_to_cards_res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_add_words_to_cards_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    init_word(lesson_id, "cat", "кот", &owner).await;
+
+    let add_to_cards_res = add_words_to_cards_req(lesson_id, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(add_to_cards_res.status(), StatusCode::CREATED);
+
+    let group_id: i32 = test::read_body_json(add_to_cards_res).await;
+
+    let get_cards_res = get_cards_req(group_id, &owner).send_request(&app).await;
+    let cards: Vec<Card> = test::read_body_json(get_cards_res).await;
+
+    assert_eq!(cards.len(), 1);
+    assert_eq!(cards[0].word, "cat");
+
+    // the same group is returned on second call
+    let add_to_cards_res = add_words_to_cards_req(lesson_id, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(add_to_cards_res.status(), StatusCode::OK);
+
+    let same_group_id: i32 = test::read_body_json(add_to_cards_res).await;
+
+    assert_eq!(group_id, same_group_id);
+}
+
+#[actix_web::test]
+async fn test_update_word_changes_cards() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let word_id = init_word(lesson_id, "cat", "кот", &owner).await;
+
+    let add_to_cards_res = add_words_to_cards_req(lesson_id, &owner)
+        .send_request(&app)
+        .await;
+    let group_id: i32 = test::read_body_json(add_to_cards_res).await;
+
+    // changes of vocabulary are applied to cards
+    init_word(lesson_id, "dog", "собака", &owner).await;
+
+    let update_word_res = update_word_req(
+        UpdateLessonWord {
+            id: word_id,
+            word: String::from("kitten"),
+            translation: String::from("котёнок"),
+            example: Some(String::from("The kitten is asleep")),
+        },
+        &owner,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(update_word_res.status(), StatusCode::OK);
+
+    let get_cards_res = get_cards_req(group_id, &owner).send_request(&app).await;
+    let cards: Vec<Card> = test::read_body_json(get_cards_res).await;
+    let mut words: Vec<String> = cards.into_iter().map(|card| card.word).collect();
+    words.sort();
+
+    assert_eq!(words, vec!["dog", "kitten"]);
+}
+
+#[actix_web::test]
+async fn test_delete_word_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let word_id = init_word(lesson_id, "cat", "кот", &owner).await;
+    init_word(lesson_id, "dog", "собака", &owner).await;
+
+    let add_to_cards_res = add_words_to_cards_req(lesson_id, &owner)
+        .send_request(&app)
+        .await;
+    let group_id: i32 = test::read_body_json(add_to_cards_res).await;
+
+    let delete_word_res = delete_word_req(word_id, &owner).send_request(&app).await;
+
+    assert_eq!(delete_word_res.status(), StatusCode::OK);
+
+    let get_cards_res = get_cards_req(group_id, &owner).send_request(&app).await;
+    let cards: Vec<Card> = test::read_body_json(get_cards_res).await;
+
+    assert_eq!(cards.len(), 1);
+    assert_eq!(cards[0].word, "dog");
+
+    let get_words_res = get_words_req(lesson_id, &owner).send_request(&app).await;
+
+    assert_eq!(get_words_res.status(), StatusCode::OK);
+
+    let words: Vec<LessonWord> = test::read_body_json(get_words_res).await;
+
+    assert_eq!(words.len(), 1);
+    assert_eq!(words[0].translation, "собака");
+}
+
+#[actix_web::test]
+async fn test_get_lesson_text_draft_is_not_published() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    init_revision(lesson_id, "first\nline", &owner).await;
+
+    let get_text_res = get_lesson_text_req(lesson_id, "", &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_text_res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_publish_revision_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let first_id = init_revision(lesson_id, "first\nline", &owner).await;
+
+    let publish_res = publish_revision_req(first_id, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(publish_res.status(), StatusCode::OK);
+
+    init_revision(lesson_id, "second\nline", &owner).await;
+
+    let get_text_res = get_lesson_text_req(lesson_id, "", &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_text_res.status(), StatusCode::OK);
+
+    let text: String = test::read_body_json(get_text_res).await;
+
+    assert_eq!(text, "first\nline");
+
+    let get_text_res = get_lesson_text_req(lesson_id, "?draft=true", &owner)
+        .send_request(&app)
+        .await;
+    let text: String = test::read_body_json(get_text_res).await;
+
+    assert_eq!(text, "second\nline");
+}
+
+#[actix_web::test]
+async fn test_publish_older_revision() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let first_id = init_revision(lesson_id, "first", &owner).await;
+    let second_id = init_revision(lesson_id, "second", &owner).await;
+
+    publish_revision_req(second_id, &owner)
+        .send_request(&app)
+        .await;
+    publish_revision_req(first_id, &owner)
+        .send_request(&app)
+        .await;
+
+    let get_text_res = get_lesson_text_req(lesson_id, "", &owner)
+        .send_request(&app)
+        .await;
+    let text: String = test::read_body_json(get_text_res).await;
+
+    assert_eq!(text, "first");
+}
+
+#[actix_web::test]
+async fn test_get_lesson_text_draft_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    init_revision(lesson_id, "first\nline", &owner).await;
+
+    let get_text_res = get_lesson_text_req(lesson_id, "?draft=true", &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_text_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_diff_revisions_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let first_id = init_revision(lesson_id, "first\nline", &owner).await;
+    let second_id = init_revision(lesson_id, "second\nline", &owner).await;
+
+    let diff_res = diff_revisions_req(first_id, second_id, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(diff_res.status(), StatusCode::OK);
+
+    let diff: Vec<DiffLine> = test::read_body_json(diff_res).await;
+
+    assert_eq!(
+        diff,
+        vec![
+            DiffLine {
+                tag: DiffTag::Delete,
+                text: String::from("first"),
+            },
+            DiffLine {
+                tag: DiffTag::Insert,
+                text: String::from("second"),
+            },
+            DiffLine {
+                tag: DiffTag::Equal,
+                text: String::from("line"),
+            },
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn test_restore_revision_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let first_id = init_revision(lesson_id, "first\nline", &owner).await;
+
+    let publish_res = publish_revision_req(first_id, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(publish_res.status(), StatusCode::OK);
+
+    let second_id = init_revision(lesson_id, "second\nline", &owner).await;
+
+    // restored text is saved as new draft
+    let restore_res = restore_revision_req(first_id, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(restore_res.status(), StatusCode::CREATED);
+
+    let restored_id: i32 = test::read_body_json(restore_res).await;
+
+    let get_text_res = get_lesson_text_req(lesson_id, "?draft=true", &owner)
+        .send_request(&app)
+        .await;
+    let text: String = test::read_body_json(get_text_res).await;
+
+    assert_eq!(text, "first\nline");
+
+    let get_revisions_res = get_revisions_req(lesson_id, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_revisions_res.status(), StatusCode::OK);
+
+    let revisions: Vec<RevisionInfo> = test::read_body_json(get_revisions_res).await;
+    let revisions: Vec<(i32, bool, bool)> = revisions
+        .into_iter()
+        .map(|revision| (revision.id, revision.published, revision.draft))
+        .collect();
+
+    assert_eq!(
+        revisions,
+        vec![
+            (restored_id, false, true),
+            (second_id, false, true),
+            (first_id, true, false),
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn test_lesson_text_html() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    init_word(lesson_id, "cat", "кот", &owner).await;
+
+    let lesson_text = [
+        "The [[Cat]] sleeps.<script>alert(1)</script>",
+        "",
+        "| word | translation |",
+        "| ---- | ----------- |",
+        "| cat  | кот         |",
+        "",
+        "![cat](image:cat-picture) ![](audio:cat-sound \"listen\")",
+    ]
+    .join("\n");
+
+    init_revision(lesson_id, &lesson_text, &owner).await;
+
+    let get_text_res = get_lesson_text_req(lesson_id, "?draft=true&format=html", &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_text_res.status(), StatusCode::OK);
+
+    let html: String = test::read_body_json(get_text_res).await;
+
+    assert!(html.contains(r#"<mark class="vocabulary" title="кот">Cat</mark>"#));
+    assert!(!html.contains("<script>"));
+    assert!(html.contains("<table>"));
+    assert!(html.contains(r#"src="/api/image/cat-picture""#));
+    assert!(html.contains("<audio"));
+    assert!(html.contains(r#"src="/api/image/cat-sound""#));
+}
+
+#[actix_web::test]
+async fn test_preview_lesson_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+
+    let preview_res = preview_lesson_req(lesson_id, true, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(preview_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_preview_lesson_by_editor() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let editor = init_editor(course_id, &owner).await;
+
+    let preview_res = preview_lesson_req(lesson_id, true, &editor)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(preview_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_course_members_read_lessons() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    init_lesson(course_id, &owner).await;
+    let editor = init_editor(course_id, &owner).await;
+    let student = init_student(course_id, &owner).await;
+
+    for member in [&owner, &editor, &student] {
+        let get_lesson_res = get_lesson_req(lesson_id, member).send_request(&app).await;
+
+        assert_eq!(get_lesson_res.status(), StatusCode::OK);
+
+        let get_lessons_res = get_lessons_in_course_req(course_id, member)
+            .send_request(&app)
+            .await;
+
+        assert_eq!(get_lessons_res.status(), StatusCode::OK);
+
+        let lessons: Vec<Lesson> = test::read_body_json(get_lessons_res).await;
+
+        assert_eq!(lessons.len(), 2);
+
+        let modules_res = get_modules_req(course_id, member).send_request(&app).await;
+
+        assert_eq!(modules_res.status(), StatusCode::OK);
+    }
+}
+
+#[actix_web::test]
+async fn test_stranger_reads_only_preview_lesson() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let preview_id = init_lesson(course_id, &owner).await;
+
+    let preview_res = preview_lesson_req(preview_id, true, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(preview_res.status(), StatusCode::OK);
+
+    let get_lesson_res = get_lesson_req(lesson_id, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_lesson_res.status(), StatusCode::NOT_FOUND);
+
+    let get_text_res = get_lesson_text_req(lesson_id, "", &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_text_res.status(), StatusCode::NOT_FOUND);
+
+    let get_lesson_res = get_lesson_req(preview_id, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_lesson_res.status(), StatusCode::OK);
+
+    let get_lessons_res = get_lessons_in_course_req(course_id, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_lessons_res.status(), StatusCode::OK);
+
+    let lessons: Vec<Lesson> = test::read_body_json(get_lessons_res).await;
+    let lesson_ids: Vec<i32> = lessons.iter().map(|lesson| lesson.id).collect();
+
+    assert_eq!(lesson_ids, vec![preview_id]);
+
+    let get_lessons_res = get_lessons_req(&stranger).send_request(&app).await;
+    let lessons: Vec<Lesson> = test::read_body_json(get_lessons_res).await;
+
+    assert!(lessons.iter().any(|lesson| lesson.id == preview_id));
+    assert!(!lessons.iter().any(|lesson| lesson.id == lesson_id));
+}
+
+#[actix_web::test]
+async fn test_get_modules_not_found() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner).await;
+
+    let modules_res = get_modules_req(course_id, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(modules_res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_public_course_lessons_need_membership() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner).await;
+    let lesson_id = init_lesson(course_id, &owner).await;
+    let student = init_student(course_id, &owner).await;
+
+    // public course is only listed in catalog
+    set_course_public(course_id, true, &get_db_conn().await)
+        .await
+        .unwrap();
+
+    let get_lesson_res = get_lesson_req(lesson_id, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_lesson_res.status(), StatusCode::NOT_FOUND);
+
+    let get_lessons_res = get_lessons_req(&stranger).send_request(&app).await;
+    let lessons: Vec<Lesson> = test::read_body_json(get_lessons_res).await;
+
+    assert!(!lessons.iter().any(|lesson| lesson.id == lesson_id));
+
+    // course has no preview lessons
+    let get_lessons_res = get_lessons_in_course_req(course_id, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_lessons_res.status(), StatusCode::NOT_FOUND);
+
+    let get_lesson_res = get_lesson_req(lesson_id, &student).send_request(&app).await;
+
+    assert_eq!(get_lesson_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_import_lessons_forbidden() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let stranger = init_user().await;
+    let course_id = init_course(&owner).await;
+
+    let import_res = import_lessons_req(course_id, "article.html", ARTICLE, &stranger)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(import_res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_import_lessons_bad_request() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+
+    let import_res = import_lessons_req(course_id, "article.pdf", ARTICLE, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(import_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_import_lessons_from_html() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let course_id = init_course(&owner).await;
+
+    let import_res = import_lessons_req(course_id, "article.html", ARTICLE, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(import_res.status(), StatusCode::CREATED);
+
+    let lesson_ids: Vec<i32> = test::read_body_json(import_res).await;
+
+    assert_eq!(lesson_ids.len(), 1);
+
+    let get_lesson_res = get_lesson_req(lesson_ids[0], &owner)
+        .send_request(&app)
+        .await;
+    let lesson: Lesson = test::read_body_json(get_lesson_res).await;
+
+    assert_eq!(lesson.title, "Present Simple");
+    assert_eq!(lesson.course_id, Some(course_id));
+
+    let get_text_res = get_lesson_text_req(lesson_ids[0], "?draft=true", &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(get_text_res.status(), StatusCode::OK);
+
+    let text: String = test::read_body_json(get_text_res).await;
+
+    // embedded image is saved into image store
+    assert!(text.contains("Rules"));
+    assert!(text.contains("**work**"));
+    assert!(text.contains("](image:"));
+    assert!(!text.contains("data:image"));
+}
+
+#[actix_web::test]
+async fn test_upload_image_unsupported_type() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+
+    // type is detected by content, not by name
+    let upload_res = upload_image_req(&[image_part("cover.png", b"not an image")], &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(upload_res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[actix_web::test]
+async fn test_upload_image_broken() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+
+    let upload_res = upload_image_req(
+        &[image_part("cover.png", b"\x89PNG\r\n\x1a\nbroken")],
+        &owner,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(upload_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_upload_image_success() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+
+    let upload_res = upload_image_req(&[image_part("cover.png", &cover_png())], &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(upload_res.status(), StatusCode::OK);
+
+    let filename: String = test::read_body_json(upload_res).await;
+
+    let info_res = get_image_info_req(&filename, &owner)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(info_res.status(), StatusCode::OK);
+
+    let info: Image = test::read_body_json(info_res).await;
+
+    assert_eq!(info.content_type, "image/png");
+    assert_eq!((info.width, info.height), (Some(800), Some(400)));
+    assert_eq!(info.original_name.as_deref(), Some("cover.png"));
+
+    let image_res = get_image_req(&filename, "").send_request(&app).await;
+
+    assert_eq!(image_res.status(), StatusCode::OK);
+    assert_eq!(
+        image_res.headers().get(header::CONTENT_TYPE).unwrap(),
+        "image/png"
+    );
+}
+
+#[actix_web::test]
+async fn test_upload_image_file_is_missing() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+
+    let upload_res = upload_image_req(&[], &owner).send_request(&app).await;
+
+    assert_eq!(upload_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_upload_image_requires_one_file() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let svg = br#"<svg xmlns="http://www.w3.org/2000/svg"></svg>"#;
+
+    let upload_res = upload_image_req(
+        &[image_part("first.svg", svg), image_part("second.svg", svg)],
+        &owner,
+    )
+    .send_request(&app)
+    .await;
+
+    assert_eq!(upload_res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_get_image_thumbnail() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let filename = init_image("cover.png", &cover_png(), &owner).await;
+
+    let image_res = get_image_req(&filename, "?size=small&format=webp")
+        .send_request(&app)
+        .await;
+
+    assert_eq!(image_res.status(), StatusCode::OK);
+    assert_eq!(
+        image_res.headers().get(header::CONTENT_TYPE).unwrap(),
+        "image/webp"
+    );
+    assert!(image_res.headers().contains_key(header::CACHE_CONTROL));
+
+    let thumbnail = image::load_from_memory(&test::read_body(image_res).await).unwrap();
+
+    assert_eq!((thumbnail.width(), thumbnail.height()), (160, 80));
+}
+
+#[actix_web::test]
+async fn test_get_image_not_modified() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let filename = init_image("cover.png", &cover_png(), &owner).await;
+
+    let image_res = get_image_req(&filename, "").send_request(&app).await;
+    let etag = image_res.headers().get(header::ETAG).unwrap().clone();
+
+    let image_res = get_image_req(&filename, "")
+        .append_header((header::IF_NONE_MATCH, etag))
+        .send_request(&app)
+        .await;
+
+    assert_eq!(image_res.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[actix_web::test]
+async fn test_get_image_range() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let png = cover_png();
+    let filename = init_image("cover.png", &png, &owner).await;
+
+    let image_res = get_image_req(&filename, "")
+        .append_header((header::RANGE, "bytes=0-7"))
+        .send_request(&app)
+        .await;
+
+    assert_eq!(image_res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        image_res.headers().get(header::CONTENT_RANGE).unwrap(),
+        format!("bytes 0-7/{}", png.len()).as_str()
+    );
+    assert_eq!(
+        test::read_body(image_res).await.as_ref(),
+        b"\x89PNG\r\n\x1a\n"
+    );
+}
+
+#[actix_web::test]
+async fn test_get_image_range_not_satisfiable() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let png = cover_png();
+    let filename = init_image("cover.png", &png, &owner).await;
+
+    let image_res = get_image_req(&filename, "")
+        .append_header((header::RANGE, format!("bytes={}-", png.len())))
+        .send_request(&app)
+        .await;
+
+    assert_eq!(image_res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+}
+
+#[actix_web::test]
+async fn test_get_image_range_with_weak_tag() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+    let filename = init_image("cover.png", &cover_png(), &owner).await;
+
+    let image_res = get_image_req(&filename, "").send_request(&app).await;
+    let etag = image_res.headers().get(header::ETAG).unwrap().clone();
+
+    assert!(etag.to_str().unwrap().starts_with("W/"));
+
+    // weak tag cannot validate range, whole file is sent
+    let image_res = get_image_req(&filename, "")
+        .append_header((header::RANGE, "bytes=0-7"))
+        .append_header((header::IF_RANGE, etag))
+        .send_request(&app)
+        .await;
+
+    assert_eq!(image_res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_get_svg_image_is_sanitized() {
+    let app = test::init_service(
+        App::new()
+            .app_data(get_app_data().await)
+            .configure(main_config),
+    )
+    .await;
+
+    let owner = init_user().await;
+
+    let svg = br#"<?xml version="1.0"?>
+        <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10" onload="alert(1)">
+            <script>alert(2)</script>
+            <circle cx="5" cy="5" r="4" fill="red"/>
+        </svg>"#;
+
+    let filename = init_image("icon.txt", svg, &owner).await;
+
+    let image_res = get_image_req(&filename, "?size=small")
+        .send_request(&app)
+        .await;
+
+    assert_eq!(
+        image_res.headers().get(header::CONTENT_TYPE).unwrap(),
+        "image/svg+xml"
+    );
+
+    let body = String::from_utf8(test::read_body(image_res).await.to_vec()).unwrap();
+
+    assert!(body.contains("<circle"));
+    assert!(!body.contains("alert"));
+}
+
+#[actix_web::test]
+async fn test_storage_gc_keeps_referenced_images() {
+    let state = get_app_data().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(main_config)).await;
+
+    let user = init_user().await;
+    let course_id = init_course(&user).await;
+    let lesson_id = init_lesson(course_id, &user).await;
+
+    let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 1 1"></svg>"#;
+    let used = init_image("used.svg", svg, &user).await;
+    let unused = init_image("unused.svg", svg, &user).await;
+
+    // files without metadata were uploaded before it was tracked
+    let untracked = Uuid::new_v4().to_string();
+    state
+        .storage
+        .put(BlobKind::Image, &untracked, b"image".to_vec())
+        .await
+        .unwrap();
+
+    let lesson_text = format!(
+        "# Lesson\n\n<img alt=\"picture\" src=\"https://example.com/api/image/{used}?size=small\">"
+    );
+    let upload_lesson_res = upload_lesson_req(lesson_id, lesson_text, &user)
+        .send_request(&app)
+        .await;
+
+    assert_eq!(upload_lesson_res.status(), StatusCode::OK);
+
+    // dry run does not touch files of other tests
+    let report = collect_garbage(state.storage.as_ref(), &state.pool, Duration::ZERO, true)
+        .await
+        .unwrap();
+
+    let removed: Vec<&String> = report
+        .removed
+        .iter()
+        .filter(|(kind, _)| *kind == BlobKind::Image)
+        .map(|(_, key)| key)
+        .collect();
+
+    assert!(removed.contains(&&unused));
+    assert!(!removed.iter().any(|key| key.starts_with(&used)));
+    assert!(!removed.contains(&&untracked));
+    assert!(state.storage.stat(BlobKind::Image, &unused).await.is_ok());
+}